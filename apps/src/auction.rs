@@ -0,0 +1,187 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Simulation of the auction of an offer, block by block, to check its parameters before
+//! submitting a request.
+//!
+//! The price rises linearly from the minimum to the maximum over the ramp-up, from the start of
+//! the bidding, then stays at the maximum until the lock timeout. After it, the client pays
+//! nothing: a prover fulfilling the request is paid by the stake of the prover that locked it.
+//! The request expires at its timeout.
+
+use std::path::PathBuf;
+
+use alloy::primitives::{utils::format_units, U256};
+use anyhow::{anyhow, ensure, Context, Result};
+use boundless_market::{request_builder::OfferLayerConfig, Offer, ProofRequest};
+use clap::Args;
+
+use crate::failure::Failure;
+
+/// Arguments of the auction command.
+#[derive(Args, Clone, Debug)]
+pub struct AuctionArgs {
+    /// JSON file holding a request, or only its offer, to simulate instead of the offer of the
+    /// flags.
+    #[clap(long, value_name = "PATH")]
+    request: Option<PathBuf>,
+    /// Minimum price of the offer, in wei.
+    #[clap(long, default_value_t = U256::ZERO, conflicts_with = "request")]
+    min_price: U256,
+    /// Maximum price of the offer, in wei.
+    #[clap(long, required_unless_present = "request", conflicts_with = "request")]
+    max_price: Option<U256>,
+    /// Duration in seconds for the price to ramp up from the minimum to the maximum.
+    #[clap(long, default_value_t = OfferLayerConfig::default().ramp_up_period, conflicts_with = "request")]
+    ramp_up_period: u32,
+    /// Time in seconds after the start of the bidding that a prover has to fulfill a locked
+    /// request.
+    #[clap(long, default_value_t = OfferLayerConfig::default().lock_timeout, conflicts_with = "request")]
+    lock_timeout: u32,
+    /// Time in seconds after the start of the bidding at which the request expires.
+    #[clap(long, default_value_t = OfferLayerConfig::default().timeout, conflicts_with = "request")]
+    timeout: u32,
+    /// Time between blocks on the chain of the market, in seconds.
+    #[clap(long, default_value_t = 12, value_parser = clap::value_parser!(u64).range(1..))]
+    block_time: u64,
+}
+
+/// Blocks over which the price of the offer is the same, or a single block during the ramp-up.
+#[derive(Debug, PartialEq)]
+struct Span {
+    first_block: u64,
+    last_block: u64,
+    price: U256,
+}
+
+/// Print the price of the offer at each block, from the start of the bidding to the timeout.
+pub fn run(args: AuctionArgs) -> Result<()> {
+    let offer = args.offer().context(Failure::Config)?;
+    let spans = simulate(&offer, args.block_time)?;
+    println!(
+        "{:<13} {:<15} {:>24}  Price (ETH)",
+        "Block", "Time", "Price (wei)"
+    );
+    for span in &spans {
+        let (blocks, time) = if span.first_block == span.last_block {
+            let offset = span.first_block * args.block_time;
+            (span.first_block.to_string(), format!("+{offset}s"))
+        } else {
+            let first = span.first_block * args.block_time;
+            let last = span.last_block * args.block_time;
+            (
+                format!("{}-{}", span.first_block, span.last_block),
+                format!("+{first}s-{last}s"),
+            )
+        };
+        let note = if span.price.is_zero() && span.first_block > 0 {
+            "  lock expired, paid by the stake of the locking prover"
+        } else {
+            ""
+        };
+        println!(
+            "{blocks:<13} {time:<15} {:>24}  {}{note}",
+            span.price,
+            format_units(span.price, "ether")?
+        );
+    }
+    println!(
+        "Locked requests must be fulfilled by +{}s, and the request expires at +{}s",
+        offer.lockTimeout, offer.timeout
+    );
+    Ok(())
+}
+
+impl AuctionArgs {
+    /// Offer of the request file, or of the flags, with the bidding starting at 0.
+    fn offer(&self) -> Result<Offer> {
+        let offer = match &self.request {
+            Some(path) => {
+                let json = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                match serde_json::from_str::<ProofRequest>(&json) {
+                    Ok(request) => request.offer,
+                    Err(_) => serde_json::from_str::<Offer>(&json).with_context(|| {
+                        format!("{} is neither a request nor an offer", path.display())
+                    })?,
+                }
+            }
+            None => Offer::default()
+                .with_min_price(self.min_price)
+                .with_max_price(self.max_price.ok_or_else(|| anyhow!("no maximum price"))?)
+                .with_ramp_up_period(self.ramp_up_period)
+                .with_lock_timeout(self.lock_timeout)
+                .with_timeout(self.timeout),
+        };
+        ensure!(
+            offer.minPrice <= offer.maxPrice,
+            "the minimum price is over the maximum price"
+        );
+        ensure!(
+            offer.rampUpPeriod <= offer.lockTimeout && offer.lockTimeout <= offer.timeout,
+            "the ramp-up must end by the lock timeout, and the lock timeout by the timeout"
+        );
+        Ok(offer.with_bidding_start(0))
+    }
+}
+
+/// Price of the offer at each block from the start of the bidding to the timeout, with the
+/// blocks of the same price after the ramp-up grouped.
+fn simulate(offer: &Offer, block_time: u64) -> Result<Vec<Span>> {
+    let mut spans: Vec<Span> = Vec::new();
+    let blocks = u64::from(offer.timeout) / block_time;
+    let ramp_up_end = offer.biddingStart + u64::from(offer.rampUpPeriod);
+    for block in 0..=blocks {
+        let timestamp = offer.biddingStart + block * block_time;
+        let price = offer.price_at(timestamp).context("invalid offer")?;
+        match spans.last_mut() {
+            Some(span) if span.price == price && timestamp > ramp_up_end => span.last_block = block,
+            _ => spans.push(Span {
+                first_block: block,
+                last_block: block,
+                price,
+            }),
+        }
+    }
+    Ok(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulates_the_auction_block_by_block() {
+        let offer = Offer::default()
+            .with_min_price(U256::from(100))
+            .with_max_price(U256::from(500))
+            .with_ramp_up_period(20)
+            .with_lock_timeout(60)
+            .with_timeout(100);
+        let span = |first_block, last_block, price| Span {
+            first_block,
+            last_block,
+            price: U256::from(price),
+        };
+        assert_eq!(
+            simulate(&offer, 10).unwrap(),
+            [
+                span(0, 0, 100),
+                span(1, 1, 300),
+                span(2, 6, 500),
+                span(7, 10, 0)
+            ]
+        );
+    }
+}
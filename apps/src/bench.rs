@@ -0,0 +1,124 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Throughput benchmark of the publishing pipeline against a local devnet.
+//!
+//! The benchmark runs the dev-mode flow, so proofs are replaced by fake receipts and nothing is
+//! uploaded or submitted to the market. It measures what the app itself can push through: the
+//! preflight executions, the seals, and the signing, broadcasting and confirmation of the
+//! transactions.
+
+use std::{
+    num::NonZeroUsize,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::Address;
+use anyhow::{ensure, Result};
+use clap::Args;
+
+use crate::{dev, ClientArgs, SubmitArgs};
+
+/// Arguments of the bench command.
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Number of numbers to publish.
+    #[clap(long, default_value_t = 20)]
+    count: u32,
+    /// Address of the EvenNumber contract, deployed with a mock verifier.
+    #[clap(short, long, env)]
+    even_number_address: Address,
+    #[clap(flatten)]
+    client: ClientArgs,
+}
+
+/// Publish `count` synthetic even numbers in dev mode, and report the throughput and the time
+/// spent in each phase.
+pub async fn run(args: BenchArgs) -> Result<()> {
+    ensure!(args.count > 0, "count must be positive");
+    // Derive the numbers from the current time, so that runs don't hit the preflight cache of a
+    // previous one, and execute each of them.
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as u32;
+    let numbers = (0..args.count)
+        .map(|i| seed.wrapping_add(i).wrapping_mul(2))
+        .collect();
+    let submit = SubmitArgs {
+        numbers,
+        input: Default::default(),
+        concurrency: NonZeroUsize::MIN,
+        even_number_addresses: vec![args.even_number_address.into()],
+        program_url: None,
+        expires_at: None,
+        print_calldata: false,
+        export_bundle: None,
+        auto_deposit: false,
+        client_contract: None,
+        no_budget_check: false,
+        fallback_local_prove: false,
+        prover: crate::ProverBackend::Market,
+        lock_alert: Default::default(),
+        offchain: false,
+        request_index: None,
+        tags: Vec::new(),
+        state: crate::state_dir::StateArgs::in_memory(),
+        poll_interval: 5,
+        max_poll_interval: 60,
+        no_preflight_cache: true,
+        dev_mode: true,
+        client: args.client,
+        consumer: Default::default(),
+        prices: Default::default(),
+    };
+
+    let start = Instant::now();
+    let timings = dev::submit(&submit).await?;
+    let elapsed = start.elapsed();
+
+    println!(
+        "Published {} numbers in {:.1}s, {:.1} per minute",
+        args.count,
+        elapsed.as_secs_f64(),
+        f64::from(args.count) * 60.0 / elapsed.as_secs_f64()
+    );
+    let phases = [
+        ("preflight", "executing the guest", timings.preflight),
+        ("seal", "building the fake receipts", timings.seal),
+        (
+            "send",
+            "signing and broadcasting the transactions",
+            timings.send,
+        ),
+        (
+            "confirm",
+            "waiting for the transactions to be confirmed",
+            timings.confirm,
+        ),
+    ];
+    for (name, _, duration) in phases {
+        println!(
+            "{name:<10} {:>7.2}s {:>4.0}%",
+            duration.as_secs_f64(),
+            share(duration, elapsed)
+        );
+    }
+    if let Some((name, description, _)) = phases.iter().max_by_key(|(_, _, duration)| *duration) {
+        println!("Bottleneck: {name}, {description}");
+    }
+    Ok(())
+}
+
+/// Percentage of `total` spent in `part`.
+fn share(part: Duration, total: Duration) -> f64 {
+    100.0 * part.as_secs_f64() / total.as_secs_f64()
+}
@@ -0,0 +1,287 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Lightweight indexer recording `EvenNumber` updates and the related Boundless Market
+//! fulfillments into a SQLite database.
+
+use std::{path::PathBuf, time::Duration};
+
+use alloy::{
+    primitives::Address,
+    providers::{Provider, ProviderBuilder},
+    rpc::types::Filter,
+    sol_types::SolEvent,
+};
+use anyhow::{bail, Context, Result};
+use boundless_app::even_number::IEvenNumber;
+use boundless_market::{contracts::IBoundlessMarket, Deployment};
+use clap::{Args, Parser, Subcommand};
+use rusqlite::{params, Connection, OptionalExtension};
+use url::Url;
+
+/// Maximum number of blocks queried for logs in a single request.
+const BLOCK_RANGE: u64 = 1000;
+
+/// Indexer for the EvenNumber contract and its Boundless Market fulfillments.
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+struct Cli {
+    /// Path of the SQLite database.
+    #[clap(long, env = "INDEXER_DB", default_value = "indexer.sqlite")]
+    db: PathBuf,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    /// Follow the chain and record new events into the database.
+    Run(RunArgs),
+    /// List the numbers set on the EvenNumber contract, most recent first.
+    Numbers(QueryArgs),
+    /// List the market fulfillments for the EvenNumber guest, most recent first.
+    Fulfillments(QueryArgs),
+}
+
+/// Arguments of the run command.
+#[derive(Args, Debug)]
+struct RunArgs {
+    /// URL of the Ethereum RPC endpoint, or path of the IPC socket of a local node.
+    #[clap(short, long, env, value_parser = boundless_app::transport::parse_rpc_url)]
+    rpc_url: Url,
+    /// Address of the EvenNumber contract.
+    #[clap(short, long, env)]
+    even_number_address: Address,
+    /// Block to start indexing from when the database is empty. Defaults to the latest block.
+    #[clap(long)]
+    from_block: Option<u64>,
+    /// Interval, in seconds, between polls for new blocks once the indexer has caught up.
+    #[clap(long, default_value_t = 12)]
+    poll_interval: u64,
+    /// Deployment of the Boundless contracts and services to use.
+    ///
+    /// Will be automatically resolved from the connected chain ID if unspecified.
+    #[clap(flatten, next_help_heading = "Boundless Market Deployment")]
+    deployment: Option<Deployment>,
+}
+
+/// Arguments of the query commands.
+#[derive(Args, Debug)]
+struct QueryArgs {
+    /// Maximum number of rows to print.
+    #[clap(long, default_value_t = 20)]
+    limit: u32,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    match dotenvy::dotenv() {
+        Ok(path) => tracing::debug!("Loaded environment variables from {:?}", path),
+        Err(e) if e.not_found() => tracing::debug!("No .env file found"),
+        Err(e) => bail!("failed to load .env file: {}", e),
+    }
+
+    let cli = Cli::parse();
+    let db = Database::open(&cli.db)?;
+    match cli.command {
+        Command::Run(args) => run(db, args).await,
+        Command::Numbers(args) => {
+            for (block, tx_hash, number) in db.numbers(args.limit)? {
+                println!("{block}\t{tx_hash}\t{number}");
+            }
+            Ok(())
+        }
+        Command::Fulfillments(args) => {
+            for (block, request_id, prover) in db.fulfillments(args.limit)? {
+                println!("{block}\t{request_id}\t{prover}");
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn run(db: Database, args: RunArgs) -> Result<()> {
+    let provider = ProviderBuilder::new()
+        .connect(args.rpc_url.as_str())
+        .await
+        .context("failed to connect provider")?;
+    let chain_id = provider
+        .get_chain_id()
+        .await
+        .context("failed to query chain ID")?;
+    let deployment = args
+        .deployment
+        .or_else(|| Deployment::from_chain_id(chain_id))
+        .with_context(|| format!("no deployment provided for unknown chain_id {chain_id}"))?;
+
+    // Only fulfillments for the guest accepted by the EvenNumber contract are relevant.
+    let even_number = IEvenNumber::new(args.even_number_address, provider.clone());
+    let image_id = even_number
+        .imageId()
+        .call()
+        .await
+        .context("failed to get image ID")?;
+    tracing::info!(
+        "Indexing EvenNumber {} with image ID {}",
+        args.even_number_address,
+        image_id
+    );
+
+    let latest = provider.get_block_number().await?;
+    let mut next_block = match db.cursor()? {
+        Some(block) => block + 1,
+        None => args.from_block.unwrap_or(latest),
+    };
+
+    loop {
+        let latest = provider.get_block_number().await?;
+        if next_block > latest {
+            tokio::time::sleep(Duration::from_secs(args.poll_interval)).await;
+            continue;
+        }
+        let to_block = latest.min(next_block + BLOCK_RANGE - 1);
+
+        let number_logs = provider
+            .get_logs(
+                &Filter::new()
+                    .address(args.even_number_address)
+                    .event_signature(IEvenNumber::NumberSet::SIGNATURE_HASH)
+                    .from_block(next_block)
+                    .to_block(to_block),
+            )
+            .await
+            .context("failed to query NumberSet events")?;
+        let fulfillment_logs = provider
+            .get_logs(
+                &Filter::new()
+                    .address(deployment.boundless_market_address)
+                    .event_signature(IBoundlessMarket::RequestFulfilled::SIGNATURE_HASH)
+                    .from_block(next_block)
+                    .to_block(to_block),
+            )
+            .await
+            .context("failed to query RequestFulfilled events")?;
+
+        let tx = db.conn.unchecked_transaction()?;
+        for log in number_logs {
+            let event = log.log_decode::<IEvenNumber::NumberSet>()?;
+            tx.execute(
+                "INSERT OR IGNORE INTO numbers (block, tx_hash, log_index, number) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    log.block_number,
+                    log.transaction_hash.unwrap_or_default().to_string(),
+                    log.log_index,
+                    event.inner.number.to_string()
+                ],
+            )?;
+        }
+        for log in fulfillment_logs {
+            let event = log.log_decode::<IBoundlessMarket::RequestFulfilled>()?;
+            let fulfillment = &event.inner.fulfillment;
+            if fulfillment.imageId != image_id {
+                continue;
+            }
+            tx.execute(
+                "INSERT OR IGNORE INTO fulfillments \
+                 (block, tx_hash, log_index, request_id, prover, journal, seal) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    log.block_number,
+                    log.transaction_hash.unwrap_or_default().to_string(),
+                    log.log_index,
+                    format!("{:x}", event.inner.requestId),
+                    event.inner.prover.to_string(),
+                    fulfillment.journal.to_vec(),
+                    fulfillment.seal.to_vec()
+                ],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO cursor (id, block) VALUES (0, ?1) ON CONFLICT (id) DO UPDATE SET block = ?1",
+            params![to_block],
+        )?;
+        tx.commit()?;
+
+        tracing::info!("Indexed blocks {next_block} to {to_block}");
+        next_block = to_block + 1;
+    }
+}
+
+/// SQLite storage for the indexed events.
+struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    fn open(path: &PathBuf) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open database {}", path.display()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS cursor (
+                id INTEGER PRIMARY KEY CHECK (id = 0),
+                block INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS numbers (
+                block INTEGER NOT NULL,
+                tx_hash TEXT NOT NULL,
+                log_index INTEGER NOT NULL,
+                number TEXT NOT NULL,
+                PRIMARY KEY (tx_hash, log_index)
+            );
+            CREATE TABLE IF NOT EXISTS fulfillments (
+                block INTEGER NOT NULL,
+                tx_hash TEXT NOT NULL,
+                log_index INTEGER NOT NULL,
+                request_id TEXT NOT NULL,
+                prover TEXT NOT NULL,
+                journal BLOB NOT NULL,
+                seal BLOB NOT NULL,
+                PRIMARY KEY (tx_hash, log_index)
+            );",
+        )?;
+        Ok(Self { conn })
+    }
+
+    /// Last block that was fully indexed.
+    fn cursor(&self) -> Result<Option<u64>> {
+        Ok(self
+            .conn
+            .query_row("SELECT block FROM cursor WHERE id = 0", [], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    fn numbers(&self, limit: u32) -> Result<Vec<(u64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT block, tx_hash, number FROM numbers ORDER BY block DESC, log_index DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn fulfillments(&self, limit: u32) -> Result<Vec<(u64, String, String)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT block, request_id, prover FROM fulfillments \
+             ORDER BY block DESC, log_index DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
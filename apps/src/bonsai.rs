@@ -0,0 +1,127 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Bonsai proving mode, behind `--prover bonsai`: the submit flow with proofs from Bonsai instead
+//! of the Boundless Market.
+//!
+//! The guest and its input are sent to Bonsai, with the `BONSAI_API_URL` and `BONSAI_API_KEY` of
+//! the environment, and the receipt is compressed to a Groth16 seal, which the verifier router of
+//! the EvenNumber contract accepts like a seal from the market. The number is then posted as in
+//! the submit flow. Nothing is submitted to the market, so nothing is recorded in the history: the
+//! proving time and the gas cost of each number are logged instead, to compare with the market.
+
+use std::{rc::Rc, time::Instant};
+
+use alloy::{
+    primitives::{utils::format_ether, U256},
+    providers::{Provider, ProviderBuilder},
+};
+use anyhow::{anyhow, Context, Result};
+use futures_util::{stream, StreamExt};
+use guests::Journal;
+use risc0_zkvm::{BonsaiProver, Prover};
+
+use crate::{
+    failure::Failure, history::History, local_prove, preflight, send_set, Consumer, GuestInput,
+    SubmitArgs, Timings,
+};
+
+/// Environment variables Bonsai is configured with.
+const BONSAI_ENV: &[&str] = &["BONSAI_API_URL", "BONSAI_API_KEY"];
+
+/// Publish each number, or the number committed by the guest on the raw input, with a proof from
+/// Bonsai.
+pub async fn submit(args: &SubmitArgs) -> Result<()> {
+    if let Some(var) = BONSAI_ENV
+        .iter()
+        .find(|var| std::env::var_os(var).is_none())
+    {
+        return Err(anyhow!("{var} must be set to prove with Bonsai")).context(Failure::Config);
+    }
+    let provider = ProviderBuilder::new()
+        .wallet(args.client.private_key.clone())
+        .connect(args.client.rpc_url.as_str())
+        .await
+        .context("failed to connect to the RPC endpoint")
+        .context(Failure::Config)?;
+    let consumer = args
+        .connect_consumer(provider.erased())
+        .await
+        .context(Failure::Config)?;
+    let history = args.state.open(crate::state_dir::Access::Shared)?;
+
+    let inputs = match args.input.read().context(Failure::Config)? {
+        Some(input) => vec![GuestInput::Raw(input)],
+        None => args
+            .numbers
+            .iter()
+            .copied()
+            .map(GuestInput::Number)
+            .collect(),
+    };
+    let results: Vec<_> = stream::iter(inputs)
+        .map(|input| publish(&consumer, &history, args, input))
+        .buffer_unordered(args.concurrency.get())
+        .collect()
+        .await;
+    let count = results.len();
+    let mut errors = results.into_iter().filter_map(Result::err);
+    match errors.next() {
+        None => Ok(()),
+        Some(e) if count == 1 => Err(e),
+        Some(e) => Err(e).context(format!("{} of {count} numbers failed", errors.count() + 1)),
+    }
+}
+
+fn bonsai() -> Rc<dyn Prover> {
+    Rc::new(BonsaiProver::new("bonsai"))
+}
+
+async fn publish(
+    consumer: &Consumer,
+    history: &History,
+    args: &SubmitArgs,
+    input: GuestInput,
+) -> Result<()> {
+    let input = match input {
+        GuestInput::Number(number) => guests::encode_input(U256::from(number)),
+        GuestInput::Raw(input) => input,
+    };
+    // Executed locally for the number committed by the guest, and the cycles proven.
+    let preflight = preflight::preflight(history, &input, !args.no_preflight_cache)
+        .await
+        .context(Failure::Preflight)?;
+    let journal = Journal::decode(&preflight.journal.bytes)
+        .context("the guest committed a journal that is not a number")
+        .context(Failure::Preflight)?;
+    let number = u32::try_from(journal.number)
+        .map_err(|_| anyhow!("the guest committed {}, over u32::MAX", journal.number))
+        .context(Failure::Preflight)?;
+    tracing::info!("Proving {number} with Bonsai, {} cycles", preflight.cycles);
+
+    let start = Instant::now();
+    let seal = local_prove::prove_with(bonsai, input, &preflight.journal.bytes)
+        .await
+        .context("failed to prove with Bonsai")?;
+    let proving = start.elapsed();
+    let mut timings = Timings::default();
+    let posted = send_set(consumer, &args.consumer.fees, number, seal, &mut timings).await?;
+    tracing::info!(
+        "Published {number} in tx {}: proven by Bonsai in {:.1}s, set tx of {} ETH",
+        posted.tx_hash,
+        proving.as_secs_f64(),
+        format_ether(posted.gas_cost)
+    );
+    Ok(())
+}
@@ -0,0 +1,231 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Checking that the wallet can pay for a run, before anything is uploaded.
+//!
+//! The upload of the program and the preflight of the requests take minutes, lost if the wallet
+//! then can't pay for the requests. The worst case of the run is estimated upfront: the maximum
+//! price of each proof, beyond the balance of the client in the market, and the gas of the
+//! `submitRequest` and `set` transactions of each request at the current fees. A run the wallets
+//! can't cover fails with their shortfall, unless `--no-budget-check` is given.
+//!
+//! The maximum price of a proof is the one the offer layer gives for the cycle count of the
+//! preflight of its input: the guest is executed locally for the inputs not in the preflight
+//! cache, and the submissions then reuse the cached executions.
+
+use std::fmt;
+
+use alloy::{
+    primitives::{utils::format_ether, Address, U256},
+    providers::Provider,
+};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::StandardClient;
+
+use crate::{
+    costs, failure::Failure, history::History, preflight, Consumer, GuestInput, SubmitArgs,
+};
+
+/// Amount a run could spend on something.
+struct Spend {
+    what: String,
+    wei: U256,
+}
+
+impl fmt::Display for Spend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ETH {}", format_ether(self.wei), self.what)
+    }
+}
+
+/// Fail with a configuration error if the wallets can't cover the worst case of the run.
+pub async fn check(
+    client: &StandardClient,
+    consumer: &Consumer,
+    history: &History,
+    args: &SubmitArgs,
+    inputs: &[GuestInput],
+) -> Result<()> {
+    let count = inputs.len();
+    let provider = client.provider();
+    let gas_price = U256::from(provider.get_gas_price().await?);
+
+    // The proofs are paid from the balance of the client in the market, which the requests top up
+    // from the wallet, unless the client is a contract.
+    let mut market_spends = Vec::new();
+    let proofs = max_prices(
+        client,
+        history,
+        args,
+        inputs,
+        consumer.cross_chain,
+        gas_price,
+    )
+    .await?;
+    let client_address = args.client_contract.unwrap_or(client.caller());
+    let balance = client
+        .boundless_market
+        .balance_of(client_address)
+        .await
+        .context("failed to get the balance of the client in the market")?;
+    let from_wallet = proofs.saturating_sub(balance);
+    if args.client_contract.is_some() {
+        if !from_wallet.is_zero() {
+            return Err(anyhow!(
+                "the balance of {} ETH of the client contract {client_address} in the market is \
+                 {} ETH short of the maximum price of the {count} proofs of the run, {} ETH",
+                format_ether(balance),
+                format_ether(from_wallet),
+                format_ether(proofs)
+            ))
+            .context(Failure::Config);
+        }
+    } else if !from_wallet.is_zero() {
+        market_spends.push(Spend {
+            what: format!(
+                "for the {count} proofs, beyond the balance of {} ETH in the market",
+                format_ether(balance)
+            ),
+            wei: from_wallet,
+        });
+    }
+    market_spends.push(Spend {
+        what: format!("for {count} submitRequest txs"),
+        wei: gas_price * U256::from(costs::SUBMIT_GAS) * U256::from(count),
+    });
+
+    if !args.print_calldata {
+        let set_txs = set_txs(consumer, args, count).await?;
+        if consumer.cross_chain {
+            let balance = consumer.provider.get_balance(consumer.sender).await?;
+            ensure_covers(consumer.sender, balance, &[set_txs])?;
+        } else {
+            market_spends.push(set_txs);
+        }
+    }
+    let balance = provider.get_balance(client.caller()).await?;
+    ensure_covers(client.caller(), balance, &market_spends)
+}
+
+/// Sum of the maximum prices of the proofs of the inputs, at the cycle counts of their preflights.
+async fn max_prices(
+    client: &StandardClient,
+    history: &History,
+    args: &SubmitArgs,
+    inputs: &[GuestInput],
+    groth16: bool,
+    gas_price: U256,
+) -> Result<U256> {
+    let config = &client
+        .request_builder
+        .as_ref()
+        .context("the client has no request builder")?
+        .offer_layer
+        .config;
+    // The gas of the prover, as estimated by the offer layer.
+    let mut gas = config.lock_gas_estimate + config.fulfill_gas_estimate;
+    if groth16 {
+        gas += config.groth16_verify_gas_estimate;
+    }
+    if args.client_contract.is_some() {
+        gas += config.smart_contract_sig_verify_gas_estimate;
+    }
+    let gas_cost = gas_price * U256::from(gas);
+    let mut sum = U256::ZERO;
+    for input in inputs {
+        let input = match input {
+            GuestInput::Number(number) => guests::encode_input(U256::from(*number)),
+            GuestInput::Raw(input) => input.clone(),
+        };
+        let cycles = preflight::preflight(history, &input, !args.no_preflight_cache)
+            .await
+            .context(Failure::Preflight)?
+            .cycles;
+        sum +=
+            config.max_price_per_cycle * U256::from(cycles) + gas_cost + gas_cost / U256::from(10);
+    }
+    Ok(sum)
+}
+
+/// Cost of the `set` transactions of the run, at the fees they are sent at.
+async fn set_txs(consumer: &Consumer, args: &SubmitArgs, count: usize) -> Result<Spend> {
+    let groth16 = consumer.cross_chain;
+    let gas_price = args.consumer.fees.gas_price(&consumer.provider).await?;
+    let l1_data_fee =
+        costs::set_l1_data_fee(&consumer.provider, consumer.even_number_address, groth16)
+            .await?
+            .unwrap_or_default();
+    let cost = U256::from(gas_price) * U256::from(costs::set_gas(groth16)) + l1_data_fee;
+    // The result of each request is posted to every target.
+    let count = count * consumer.targets().count();
+    Ok(Spend {
+        what: format!("for {count} set txs"),
+        wei: cost * U256::from(count),
+    })
+}
+
+/// Fail with the shortfall if the balance of the wallet doesn't cover the spends.
+fn ensure_covers(wallet: Address, balance: U256, spends: &[Spend]) -> Result<()> {
+    let total = spends.iter().map(|spend| spend.wei).sum::<U256>();
+    let spends = spends
+        .iter()
+        .map(Spend::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if balance < total {
+        return Err(anyhow!(
+            "the wallet {wallet} holds {} ETH, {} ETH short of the worst case of the run: \
+             {spends}",
+            format_ether(balance),
+            format_ether(total - balance)
+        ))
+        .context(Failure::Config);
+    }
+    tracing::info!(
+        "The wallet {wallet} holds {} ETH, enough for the worst case of the run: {spends}",
+        format_ether(balance)
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::utils::parse_ether;
+
+    use super::*;
+
+    #[test]
+    fn reports_the_shortfall() {
+        let spends = [
+            Spend {
+                what: "for 2 proofs".to_string(),
+                wei: parse_ether("0.02").unwrap(),
+            },
+            Spend {
+                what: "for 2 set txs".to_string(),
+                wei: parse_ether("0.005").unwrap(),
+            },
+        ];
+        assert!(ensure_covers(Address::ZERO, parse_ether("0.025").unwrap(), &spends).is_ok());
+        let error =
+            ensure_covers(Address::ZERO, parse_ether("0.01").unwrap(), &spends).unwrap_err();
+        assert_eq!(Failure::of(&error), Some(Failure::Config));
+        assert_eq!(
+            format!("{:#}", error.root_cause()),
+            "the wallet 0x0000000000000000000000000000000000000000 holds 0.010000000000000000 \
+             ETH, 0.015000000000000000 ETH short of the worst case of the run: \
+             0.020000000000000000 ETH for 2 proofs, 0.005000000000000000 ETH for 2 set txs"
+        );
+    }
+}
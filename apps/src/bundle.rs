@@ -0,0 +1,382 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Attestation bundles, exported with `--export-bundle`, with which a third party can verify the
+//! proof of a request later on, without access to the chain or the infrastructure of the app.
+//!
+//! A bundle is a ZIP archive, of entries stored uncompressed as they mostly hold hashes and
+//! proofs, of:
+//! - `bundle.json`, the [Manifest]: the image ID and the hash of the ELF of the guest, the
+//!   request ID, and the chains and contracts involved;
+//! - `journal.bin` and `seal.bin`, the journal and seal of the fulfillment, as posted to the
+//!   EvenNumber contract;
+//! - `request.json`, the request as submitted to the market;
+//! - `receipt.json`, for a seal proving the inclusion of the journal in a set of proofs, the
+//!   receipt of that inclusion with the Groth16 receipt of the root of the set, which the seal
+//!   leaves out as the set verifier contract already verified it.
+//!
+//! `verify-bundle` verifies them offline.
+
+use std::{
+    fs,
+    io::{Cursor, Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    hex,
+    primitives::{Address, B256, U256},
+    providers::Provider,
+};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{ProofRequest, StandardClient};
+use guests::{IS_EVEN_ELF, IS_EVEN_ID};
+use risc0_ethereum_contracts::{
+    receipt::Receipt,
+    selector::{Selector, SelectorType},
+};
+use risc0_zkvm::sha::Digest;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipArchive, ZipWriter};
+
+use crate::{Consumer, FulfilledRequest};
+
+/// Version of the layout of the bundles.
+pub const VERSION: u32 = 1;
+
+pub const MANIFEST: &str = "bundle.json";
+pub const JOURNAL: &str = "journal.bin";
+pub const SEAL: &str = "seal.bin";
+pub const REQUEST: &str = "request.json";
+pub const RECEIPT: &str = "receipt.json";
+
+/// Description of the proof of a bundle.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub request_id: String,
+    /// Number proven even, as committed in the journal.
+    pub number: String,
+    /// Image ID of the guest, in hex.
+    pub image_id: String,
+    /// SHA-256 of the ELF of the guest, in hex.
+    pub elf_sha256: String,
+    /// SHA-256 of the journal, in hex.
+    pub journal_sha256: String,
+    /// Image ID of the set builder guest, in hex, when the seal proves inclusion in a set.
+    #[serde(default)]
+    pub set_builder_image_id: Option<String>,
+    pub market: MarketChain,
+    pub consumer: ConsumerChain,
+    /// Hash of the `set` transaction, unless it was not sent by the app.
+    #[serde(default)]
+    pub set_tx: Option<B256>,
+    /// Timestamp at which the bundle was exported.
+    pub exported_at: u64,
+}
+
+/// Chain of the Boundless Market, which fulfilled the request.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MarketChain {
+    pub chain_id: u64,
+    pub boundless_market: Address,
+    pub set_verifier: Address,
+}
+
+/// Chain of the EvenNumber contract, the result is posted to.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConsumerChain {
+    pub chain_id: u64,
+    pub even_number: Address,
+}
+
+/// Contents of a bundle.
+pub struct Bundle {
+    pub manifest: Manifest,
+    pub journal: Vec<u8>,
+    pub seal: Vec<u8>,
+    pub request: ProofRequest,
+    /// Receipt of the inclusion of the journal in a set, with the receipt of its root.
+    pub receipt: Option<Receipt>,
+}
+
+impl Bundle {
+    /// ZIP archive of the bundle.
+    pub fn to_zip(&self) -> Result<Vec<u8>> {
+        let manifest = serde_json::to_vec_pretty(&self.manifest)?;
+        let request = serde_json::to_vec_pretty(&self.request)?;
+        let receipt = self.receipt.as_ref().map(serde_json::to_vec).transpose()?;
+        let mut entries = vec![
+            (MANIFEST, manifest.as_slice()),
+            (JOURNAL, &self.journal),
+            (SEAL, &self.seal),
+            (REQUEST, &request),
+        ];
+        if let Some(receipt) = &receipt {
+            entries.push((RECEIPT, receipt));
+        }
+        write_zip(&entries)
+    }
+
+    /// Bundle of a ZIP archive.
+    pub fn from_zip(archive: &[u8]) -> Result<Self> {
+        let mut entries = read_zip(archive)?;
+        let mut take = |name: &str| {
+            entries
+                .iter()
+                .position(|(entry, _)| entry == name)
+                .map(|i| entries.swap_remove(i).1)
+        };
+        let mut required = |name: &str| take(name).ok_or_else(|| anyhow!("{name} is missing"));
+        let manifest: Manifest = serde_json::from_slice(&required(MANIFEST)?)
+            .with_context(|| format!("invalid {MANIFEST}"))?;
+        let journal = required(JOURNAL)?;
+        let seal = required(SEAL)?;
+        let request = serde_json::from_slice(&required(REQUEST)?)
+            .with_context(|| format!("invalid {REQUEST}"))?;
+        let receipt = take(RECEIPT)
+            .map(|receipt| serde_json::from_slice(&receipt))
+            .transpose()
+            .with_context(|| format!("invalid {RECEIPT}"))?;
+        Ok(Self {
+            manifest,
+            journal,
+            seal,
+            request,
+            receipt,
+        })
+    }
+}
+
+/// ZIP archive of the entries, by name, in order. The entries are stored with a fixed date, so
+/// that the archive only depends on them.
+fn write_zip(entries: &[(&str, &[u8])]) -> Result<Vec<u8>> {
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .last_modified_time(DateTime::default());
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        writer.start_file(*name, options)?;
+        writer.write_all(data)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Entries of a ZIP archive of stored entries, by name, in the order of its central directory.
+fn read_zip(archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = ZipArchive::new(Cursor::new(archive)).context("not a ZIP archive")?;
+    (0..archive.len())
+        .map(|i| {
+            let mut entry = archive.by_index(i)?;
+            let mut data = Vec::new();
+            entry
+                .read_to_end(&mut data)
+                .with_context(|| format!("failed to read {}", entry.name()))?;
+            Ok((entry.name().to_string(), data))
+        })
+        .collect()
+}
+
+/// Path of the bundle of a request: `path` itself, unless several requests are exported, whose
+/// bundles get their request ID appended to the file stem.
+pub fn path_for(path: &Path, request_id: U256, several: bool) -> PathBuf {
+    if !several {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}-{request_id:x}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{request_id:x}"),
+    };
+    path.with_file_name(name)
+}
+
+/// Export the bundle of the fulfilled request to `path`.
+pub async fn export(
+    path: &Path,
+    client: &StandardClient,
+    consumer: &Consumer,
+    request: &ProofRequest,
+    fulfilled: &FulfilledRequest,
+    number: u32,
+    set_tx: Option<B256>,
+) -> Result<()> {
+    let market = MarketChain {
+        chain_id: client.provider().get_chain_id().await?,
+        boundless_market: *client.boundless_market.instance().address(),
+        set_verifier: *client.set_verifier.instance().address(),
+    };
+    let consumer = ConsumerChain {
+        chain_id: consumer.provider.get_chain_id().await?,
+        even_number: consumer.even_number_address,
+    };
+    // The root of a set is only verified by the set verifier contract, so its receipt is looked
+    // up for the bundle to be verified without it.
+    let (receipt, set_builder_image_id) = if is_set_inclusion(&fulfilled.seal) {
+        match set_inclusion_receipt(client, fulfilled).await {
+            Ok((receipt, image_id)) => (Some(receipt), Some(image_id)),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to get the receipt of the root of the set of request {:x}, its \
+                     bundle can only be verified onchain: {e:#}",
+                    request.id
+                );
+                (None, None)
+            }
+        }
+    } else {
+        (None, None)
+    };
+    let bundle = Bundle {
+        manifest: Manifest {
+            version: VERSION,
+            request_id: format!("{:#x}", request.id),
+            number: number.to_string(),
+            image_id: hex::encode(Digest::from(IS_EVEN_ID)),
+            elf_sha256: hex::encode(Sha256::digest(IS_EVEN_ELF)),
+            journal_sha256: hex::encode(Sha256::digest(&fulfilled.journal)),
+            set_builder_image_id,
+            market,
+            consumer,
+            set_tx,
+            exported_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        },
+        journal: fulfilled.journal.to_vec(),
+        seal: fulfilled.seal.to_vec(),
+        request: request.clone(),
+        receipt,
+    };
+    fs::write(path, bundle.to_zip()?)
+        .with_context(|| format!("failed to write the bundle {}", path.display()))?;
+    tracing::info!(
+        "Exported the attestation bundle of request {:x} to {}",
+        request.id,
+        path.display()
+    );
+    Ok(())
+}
+
+/// Whether the seal proves the inclusion of the journal in a set verified onchain.
+pub fn is_set_inclusion(seal: &[u8]) -> bool {
+    seal.get(..4)
+        .and_then(|selector| Selector::from_bytes(selector.try_into().ok()?))
+        .is_some_and(|selector| selector.get_type() == SelectorType::SetVerifier)
+}
+
+/// Receipt of the inclusion of the journal in its set, with the receipt of the root of the set,
+/// and the image ID of the set builder, in hex.
+async fn set_inclusion_receipt(
+    client: &StandardClient,
+    fulfilled: &FulfilledRequest,
+) -> Result<(Receipt, String)> {
+    let receipt = client
+        .set_verifier
+        .fetch_receipt(
+            fulfilled.seal.clone(),
+            IS_EVEN_ID,
+            fulfilled.journal.to_vec(),
+        )
+        .await?;
+    let (image_id, _) = client.set_verifier.image_info().await?;
+    Ok((
+        Receipt::SetInclusion(Box::new(receipt)),
+        hex::encode(image_id),
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloy::primitives::Address;
+    use boundless_market::Offer;
+
+    use super::*;
+
+    pub(crate) fn manifest() -> Manifest {
+        Manifest {
+            version: VERSION,
+            request_id: "0x1".to_string(),
+            number: "4".to_string(),
+            image_id: hex::encode(Digest::from(IS_EVEN_ID)),
+            elf_sha256: hex::encode(Sha256::digest(IS_EVEN_ELF)),
+            journal_sha256: hex::encode(Sha256::digest([4])),
+            set_builder_image_id: None,
+            market: MarketChain {
+                chain_id: 1,
+                boundless_market: Address::repeat_byte(1),
+                set_verifier: Address::repeat_byte(2),
+            },
+            consumer: ConsumerChain {
+                chain_id: 10,
+                even_number: Address::repeat_byte(3),
+            },
+            set_tx: Some(B256::repeat_byte(4)),
+            exported_at: 1000,
+        }
+    }
+
+    #[test]
+    fn names_bundles_of_several_requests() {
+        let path = Path::new("out/bundle.zip");
+        assert_eq!(path_for(path, U256::from(0xab), false), path);
+        assert_eq!(
+            path_for(path, U256::from(0xab), true),
+            Path::new("out/bundle-ab.zip")
+        );
+        assert_eq!(
+            path_for(Path::new("bundle"), U256::from(0xab), true),
+            Path::new("bundle-ab")
+        );
+    }
+
+    #[test]
+    fn reads_bundles_back() {
+        let bundle = Bundle {
+            manifest: manifest(),
+            journal: vec![4],
+            seal: vec![5; 8],
+            request: crate::wait::tests::new_request(Offer::default()),
+            receipt: None,
+        };
+        let read = Bundle::from_zip(&bundle.to_zip().unwrap()).unwrap();
+        assert_eq!(read.manifest, bundle.manifest);
+        assert_eq!((read.journal, read.seal), (bundle.journal, bundle.seal));
+        assert_eq!(read.request, bundle.request);
+        assert!(read.receipt.is_none());
+
+        let archive = write_zip(&[(MANIFEST, b"{}")]).unwrap();
+        assert!(Bundle::from_zip(&archive).is_err());
+        assert!(Bundle::from_zip(b"not an archive").is_err());
+    }
+
+    #[test]
+    fn stores_the_entries() {
+        let archive = write_zip(&[("a.txt", b"hello"), ("b.bin", &[1, 2, 3])]).unwrap();
+        assert_eq!(
+            read_zip(&archive).unwrap(),
+            [
+                ("a.txt".to_string(), b"hello".to_vec()),
+                ("b.bin".to_string(), vec![1, 2, 3])
+            ]
+        );
+        let at = archive
+            .windows(5)
+            .position(|data| data == b"hello")
+            .unwrap();
+        let mut corrupted = archive.clone();
+        corrupted[at] ^= 1;
+        assert!(read_zip(&corrupted).is_err());
+    }
+}
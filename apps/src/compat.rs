@@ -0,0 +1,115 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Compatibility of the zkVM version of the guest with the verifiers of the market.
+//!
+//! Neither the market nor the order stream advertise the zkVM versions the provers run. What
+//! bounds them is the verifier router of the deployment: a proof is only accepted if the router
+//! has a verifier for the selector of its zkVM version, so provers don't lock requests for a
+//! version it doesn't support, and the request expires unfulfilled. The router is asked for the
+//! Groth16 verifier of the version of the guest before the request is submitted.
+
+use alloy::primitives::FixedBytes;
+use anyhow::{bail, Result};
+use boundless_market::StandardClient;
+use guests::IS_EVEN_ZKVM_VERSION;
+use risc0_ethereum_contracts::selector::Selector;
+
+use crate::revert;
+
+alloy::sol!(
+    #[sol(rpc)]
+    interface IRiscZeroVerifierRouter {
+        function getVerifier(bytes4 selector) external view returns (address);
+    }
+);
+
+/// Check that the verifier router of the market can verify proofs of the zkVM version of the
+/// guest. Fails if the router has no verifier for it, and only warns if the check can't be made.
+///
+/// A version other than the one of the zkVM preflighting the request is warned about too, as the
+/// cycle count the offer is priced on may differ.
+pub async fn ensure_supported(client: &StandardClient) -> Result<()> {
+    let version = IS_EVEN_ZKVM_VERSION;
+    if minor_version(version) != minor_version(risc0_zkvm::VERSION) {
+        tracing::warn!(
+            "The guest is built with zkVM {version}, but preflighted with zkVM {}",
+            risc0_zkvm::VERSION
+        );
+    }
+    let Some(selector) = groth16_selector(version) else {
+        tracing::warn!("No known verifier for zkVM {version}, provers may not support it");
+        return Ok(());
+    };
+    let Some(router) = client.deployment.verifier_router_address else {
+        tracing::debug!("No verifier router in the deployment, skipping the zkVM version check");
+        return Ok(());
+    };
+    let router = IRiscZeroVerifierRouter::new(router, client.provider());
+    let selector = FixedBytes::from(selector as u32);
+    match router.getVerifier(selector).call().await {
+        Ok(verifier) => {
+            tracing::debug!("zkVM {version} proofs ({selector}) are verified by {verifier}");
+            Ok(())
+        }
+        Err(e) => match e.as_revert_data() {
+            Some(data) => {
+                let reason = revert::decode(&data).unwrap_or_else(|| data.to_string());
+                bail!(
+                    "the market cannot verify proofs of zkVM {version}, the version of the \
+                     guest: {reason}"
+                )
+            }
+            None => {
+                tracing::warn!("Failed to check the support of zkVM {version}: {e}");
+                Ok(())
+            }
+        },
+    }
+}
+
+/// Selector of the Groth16 verifier of a zkVM version, which only depends on its minor version.
+fn groth16_selector(version: &str) -> Option<Selector> {
+    match minor_version(version)? {
+        (1, 1) => Some(Selector::Groth16V1_1),
+        (1, 2) => Some(Selector::Groth16V1_2),
+        (2, 0) => Some(Selector::Groth16V2_0),
+        (2, 1) => Some(Selector::Groth16V2_1),
+        _ => None,
+    }
+}
+
+/// Major and minor parts of a version.
+fn minor_version(version: &str) -> Option<(u64, u64)> {
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_versions_to_selectors() {
+        assert_eq!(groth16_selector("2.1.0"), Some(Selector::Groth16V2_1));
+        assert_eq!(groth16_selector("2.0.2"), Some(Selector::Groth16V2_0));
+        assert_eq!(groth16_selector("1.2.6"), Some(Selector::Groth16V1_2));
+        assert_eq!(groth16_selector("3.0.0"), None);
+        assert_eq!(groth16_selector("invalid"), None);
+        assert_eq!(minor_version("2.1.0-rc.1"), Some((2, 1)));
+        assert!(groth16_selector(IS_EVEN_ZKVM_VERSION).is_some());
+    }
+}
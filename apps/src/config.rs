@@ -0,0 +1,303 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Project configuration, read from `boundless.toml` at the root of the repository.
+//!
+//! The file holds the settings shared by the app and the forge deploy scripts: the addresses of
+//! the networks, the defaults of the offers, the storage provider, and the programs of the guests
+//! already uploaded. Command line flags and environment variables take precedence over it:
+//!
+//! ```toml
+//! network = "sepolia"
+//!
+//! [networks.sepolia]                 # same tables as the networks file
+//! chain-id = 11155111
+//! boundless-market-address = "0x..."
+//! set-verifier-address = "0x..."
+//! verifier-router-address = "0x..."  # read by Deploy.s.sol
+//!
+//! [offer]                            # all optional, in wei and seconds
+//! min-price-per-cycle = "0"
+//! max-price-per-cycle = "100000000"
+//! bidding-start-delay = 15
+//! ramp-up-period = 60
+//! lock-timeout = 600
+//! timeout = 1200
+//! lock-stake = "5000000"
+//!
+//! [limits]                           # sizes warned about after the preflight, in bytes
+//! journal-size = 1024
+//! calldata-size = 131072
+//!
+//! [storage]                          # any storage provider option, without secrets
+//! storage-provider = "pinata"
+//!
+//! [guests.is-even]                   # used instead of uploading the program
+//! program-url = "https://..."
+//! image-id = "0x..."
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use alloy::primitives::{B256, U256};
+use anyhow::{Context, Result};
+use boundless_market::request_builder::OfferLayerConfigBuilder;
+use serde::Deserialize;
+use url::Url;
+
+use crate::networks::Network;
+
+/// Path of the project configuration, relative to the working directory, unless
+/// `BOUNDLESS_CONFIG` is set.
+pub const DEFAULT_PATH: &str = "boundless.toml";
+
+static PROJECT: OnceLock<ProjectConfig> = OnceLock::new();
+
+/// Contents of `boundless.toml`.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Network used when neither `--network` nor a deployment is given.
+    pub network: Option<String>,
+    /// Named networks, adding to or overriding the built-in ones.
+    #[serde(default)]
+    pub networks: BTreeMap<String, Network>,
+    #[serde(default)]
+    pub offer: OfferConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// Options of the storage provider, by the name of their flag.
+    #[serde(default)]
+    pub storage: BTreeMap<String, toml::Value>,
+    /// Programs already uploaded, by the name of their guest.
+    #[serde(default)]
+    pub guests: BTreeMap<String, GuestConfig>,
+}
+
+/// Defaults of the offers of the requests, replacing those of the Boundless SDK.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OfferConfig {
+    pub min_price_per_cycle: Option<U256>,
+    pub max_price_per_cycle: Option<U256>,
+    pub bidding_start_delay: Option<u64>,
+    pub ramp_up_period: Option<u32>,
+    pub lock_timeout: Option<u32>,
+    pub timeout: Option<u32>,
+    pub lock_stake: Option<U256>,
+}
+
+/// Sizes of the journal and of the calldata posting it over which a warning is logged after the
+/// preflight, in bytes.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LimitsConfig {
+    pub journal_size: Option<usize>,
+    pub calldata_size: Option<usize>,
+}
+
+/// A program uploaded ahead of time.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct GuestConfig {
+    pub program_url: Url,
+    /// Image ID of the uploaded program, which must be the one of the built guest to be used.
+    pub image_id: B256,
+}
+
+/// Load the project configuration, from `BOUNDLESS_CONFIG` or else from [DEFAULT_PATH] if it
+/// exists, and set the environment variables of its options that are not set already. Returns
+/// the path of the file loaded, if any.
+///
+/// Call it once, before the arguments are parsed.
+pub fn load() -> Result<Option<PathBuf>> {
+    let path = match std::env::var_os("BOUNDLESS_CONFIG") {
+        Some(path) => PathBuf::from(path),
+        None if Path::new(DEFAULT_PATH).exists() => PathBuf::from(DEFAULT_PATH),
+        None => return Ok(None),
+    };
+    let file = std::fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let config: ProjectConfig =
+        toml::from_str(&file).with_context(|| format!("invalid {}", path.display()))?;
+    for (name, value) in config.env_defaults() {
+        if std::env::var_os(&name).is_none() {
+            std::env::set_var(name, value);
+        }
+    }
+    // A configuration loaded already is kept.
+    let _ = PROJECT.set(config);
+    Ok(Some(path))
+}
+
+/// The project configuration loaded, or an empty one.
+pub fn project() -> &'static ProjectConfig {
+    PROJECT.get_or_init(ProjectConfig::default)
+}
+
+impl ProjectConfig {
+    /// Environment variables of the storage options given by the configuration.
+    fn env_defaults(&self) -> Vec<(String, String)> {
+        self.storage
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    toml::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+                (name.replace('-', "_").to_uppercase(), value)
+            })
+            .collect()
+    }
+
+    /// Apply the offer defaults to the configuration of the offer layer of a client.
+    pub fn configure_offer<'a>(
+        &self,
+        builder: &'a mut OfferLayerConfigBuilder,
+    ) -> &'a mut OfferLayerConfigBuilder {
+        self.offer.configure(builder)
+    }
+}
+
+impl OfferConfig {
+    /// Apply the values that are set to the configuration of the offer layer of a client.
+    pub fn configure<'a>(
+        &self,
+        builder: &'a mut OfferLayerConfigBuilder,
+    ) -> &'a mut OfferLayerConfigBuilder {
+        let offer = self;
+        if let Some(price) = offer.min_price_per_cycle {
+            builder.min_price_per_cycle(price);
+        }
+        if let Some(price) = offer.max_price_per_cycle {
+            builder.max_price_per_cycle(price);
+        }
+        if let Some(delay) = offer.bidding_start_delay {
+            builder.bidding_start_delay(delay);
+        }
+        if let Some(period) = offer.ramp_up_period {
+            builder.ramp_up_period(period);
+        }
+        if let Some(timeout) = offer.lock_timeout {
+            builder.lock_timeout(timeout);
+        }
+        if let Some(timeout) = offer.timeout {
+            builder.timeout(timeout);
+        }
+        if let Some(stake) = offer.lock_stake {
+            builder.lock_stake(stake);
+        }
+        builder
+    }
+}
+
+/// The values that are set, like in `boundless.toml`.
+impl std::fmt::Display for OfferConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let values = [
+            (
+                "min-price-per-cycle",
+                self.min_price_per_cycle.map(|v| v.to_string()),
+            ),
+            (
+                "max-price-per-cycle",
+                self.max_price_per_cycle.map(|v| v.to_string()),
+            ),
+            (
+                "bidding-start-delay",
+                self.bidding_start_delay.map(|v| v.to_string()),
+            ),
+            ("ramp-up-period", self.ramp_up_period.map(|v| v.to_string())),
+            ("lock-timeout", self.lock_timeout.map(|v| v.to_string())),
+            ("timeout", self.timeout.map(|v| v.to_string())),
+            ("lock-stake", self.lock_stake.map(|v| v.to_string())),
+        ];
+        let values: Vec<_> = values
+            .into_iter()
+            .filter_map(|(name, value)| Some(format!("{name} = {}", value?)))
+            .collect();
+        f.write_str(&values.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use boundless_market::Deployment;
+
+    use super::*;
+    use crate::networks;
+
+    #[test]
+    fn parses_config_and_env_defaults() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            network = "devnet"
+
+            [networks.devnet]
+            chain-id = 31337
+            boundless-market-address = "0x0000000000000000000000000000000000000001"
+            set-verifier-address = "0x0000000000000000000000000000000000000002"
+
+            [offer]
+            max-price-per-cycle = "100000000"
+            timeout = 600
+
+            [limits]
+            journal-size = 256
+
+            [storage]
+            storage-provider = "s3"
+            s3-use-presigned = false
+
+            [guests.is-even]
+            program-url = "https://example.com/is-even"
+            image-id = "0x0000000000000000000000000000000000000000000000000000000000000003"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.networks["devnet"].chain_id, 31337);
+        assert_eq!(
+            config.offer.max_price_per_cycle,
+            Some(U256::from(100_000_000))
+        );
+        assert_eq!(config.offer.timeout, Some(600));
+        assert_eq!(config.limits.journal_size, Some(256));
+        assert_eq!(
+            config.env_defaults(),
+            [
+                ("S3_USE_PRESIGNED".to_string(), "false".to_string()),
+                ("STORAGE_PROVIDER".to_string(), "s3".to_string()),
+            ]
+        );
+        assert!(toml::from_str::<ProjectConfig>("[offer]\nmax-price = 1").is_err());
+    }
+
+    #[test]
+    fn repository_config_matches_the_sdk() {
+        let config: ProjectConfig = toml::from_str(include_str!("../../boundless.toml")).unwrap();
+        let builtin = networks::builtin();
+        assert!(!config.networks.is_empty());
+        for (name, network) in config.networks {
+            assert_eq!(
+                format!("{:?}", Deployment::from(network)),
+                format!("{:?}", builtin[&name]),
+                "{name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Requests attributed to a smart contract client, behind `--client-contract`.
+//!
+//! The market charges the price of a request to the client in its ID, the wallet signing it by
+//! default. With `--client-contract`, the client is a contract instead, e.g. a treasury, so that
+//! requests are paid from the balance of the contract in the market rather than from a hot
+//! wallet. The ID then has the flag of smart contract signatures, and the market checks the
+//! signature of the request with the ERC-1271 `isValidSignature` of the contract when a prover
+//! locks it. The signature is the one of the wallet over the EIP-712 hash of the request, which
+//! the contract must accept, e.g. because the wallet is one of its operators. It is checked with
+//! the contract before the request is submitted, as no prover could lock it otherwise.
+//!
+//! The wallet can't send the price with the request, nor deposit it for the contract: the
+//! balance of the contract must be funded beforehand.
+
+use alloy::{
+    primitives::{Address, Bytes, FixedBytes, B256, U256},
+    providers::Provider,
+    signers::{local::PrivateKeySigner, Signer},
+};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{contracts::RequestStatus, ProofRequest, RequestId, StandardClient};
+
+use crate::failure::Failure;
+
+alloy::sol!(
+    #[sol(rpc)]
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4);
+    }
+);
+
+/// Value returned by `isValidSignature` for a valid signature.
+const MAGIC_VALUE: FixedBytes<4> = FixedBytes([0x16, 0x26, 0xba, 0x7e]);
+
+/// Random indexes tried for the ID of a request of the contract, as the SDK does for a wallet.
+const INDEX_ATTEMPTS: usize = 10;
+
+/// Attribute the request to the contract, with an ID of the contract at a random index unused by
+/// the market.
+pub async fn attribute(
+    client: &StandardClient,
+    request: &mut ProofRequest,
+    contract: Address,
+) -> Result<()> {
+    for _ in 0..INDEX_ATTEMPTS {
+        let request_id = contract_request_id(contract, rand::random());
+        let status = client
+            .boundless_market
+            .get_status(request_id, None)
+            .await
+            .context("failed to get the status of the request ID")?;
+        if status == RequestStatus::Unknown {
+            tracing::debug!("Attributing the request to the client contract {contract}");
+            request.id = request_id;
+            return Ok(());
+        }
+    }
+    Err(anyhow!(
+        "failed to find an unused request index for the client contract {contract} after \
+         {INDEX_ATTEMPTS} attempts"
+    ))
+}
+
+/// ID of the request of the contract at the index, flagged as signed by the contract.
+fn contract_request_id(contract: Address, index: u32) -> U256 {
+    RequestId::new(contract, index)
+        .set_smart_contract_signed_flag()
+        .into()
+}
+
+/// Sign the request with the wallet, and check that its client contract accepts the signature.
+pub async fn sign(client: &StandardClient, request: &ProofRequest) -> Result<Bytes> {
+    let signer = client
+        .signer
+        .as_ref()
+        .context("no signer for the request")?;
+    let chain_id = client.provider().get_chain_id().await?;
+    let market = *client.boundless_market.instance().address();
+    let (hash, signature) = wallet_signature(signer, request, market, chain_id).await?;
+
+    let contract = request.client_address();
+    let erc1271 = IERC1271::new(contract, client.provider());
+    match erc1271
+        .isValidSignature(hash, signature.clone())
+        .call()
+        .await
+    {
+        Ok(MAGIC_VALUE) => Ok(signature),
+        Ok(_) => Err(anyhow!(
+            "the client contract {contract} doesn't accept the signature of the wallet {}",
+            signer.address()
+        ))
+        .context(Failure::Config),
+        Err(e) => Err(e)
+            .context(format!(
+                "the client contract {contract} failed to check the signature of the request, \
+                 does it implement ERC-1271?"
+            ))
+            .context(Failure::Config),
+    }
+}
+
+/// EIP-712 hash of the request for the market, and the signature of the wallet over it.
+async fn wallet_signature(
+    signer: &PrivateKeySigner,
+    request: &ProofRequest,
+    market: Address,
+    chain_id: u64,
+) -> Result<(B256, Bytes)> {
+    let hash = request.signing_hash(market, chain_id)?;
+    let signature = Bytes::from(signer.sign_hash(&hash).await?.as_bytes());
+    Ok((hash, signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::Signature, sol_types::SolCall};
+    use boundless_market::Offer;
+
+    use super::*;
+
+    #[test]
+    fn magic_value_is_the_selector_of_is_valid_signature() {
+        assert_eq!(MAGIC_VALUE, IERC1271::isValidSignatureCall::SELECTOR);
+    }
+
+    #[test]
+    fn attributes_ids_to_the_contract() {
+        let contract = Address::repeat_byte(7);
+        let request_id = RequestId::from_lossy(contract_request_id(contract, 42));
+        assert_eq!(request_id.addr, contract);
+        assert_eq!(request_id.index, 42);
+        assert!(request_id.smart_contract_signed);
+    }
+
+    #[tokio::test]
+    async fn signs_the_request_with_the_wallet() {
+        let signer = PrivateKeySigner::random();
+        let contract = Address::repeat_byte(7);
+        let mut request = crate::wait::tests::new_request(Offer::default());
+        request.id = contract_request_id(contract, 1);
+        let market = Address::repeat_byte(1);
+        let (hash, signature) = wallet_signature(&signer, &request, market, 1)
+            .await
+            .unwrap();
+        assert_eq!(hash, request.signing_hash(market, 1).unwrap());
+        let signature = Signature::try_from(signature.as_ref()).unwrap();
+        assert_eq!(
+            signature.recover_address_from_prehash(&hash).unwrap(),
+            signer.address()
+        );
+        assert_eq!(request.client_address(), contract);
+        assert!(request.is_smart_contract_signed());
+    }
+}
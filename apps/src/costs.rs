@@ -0,0 +1,242 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Estimating the cost of publishing a number, before its request is submitted.
+//!
+//! On OP-stack rollups, a transaction also pays for the publication of its calldata on L1. The
+//! calldata of the `set` transaction holds the seal, so this data fee usually dominates its cost.
+//! It is estimated with the `GasPriceOracle` predeploy of the rollup, for a seal of typical size.
+
+use alloy::{
+    consensus::{SignableTransaction, TxEip1559},
+    primitives::{address, keccak256, Address, Bytes, U256},
+    providers::Provider,
+    sol_types::SolCall,
+};
+use anyhow::Result;
+use boundless_market::contracts::ProofRequest;
+
+use crate::{
+    config,
+    even_number::IEvenNumber,
+    usd::{self, EthUsd},
+};
+
+alloy::sol!(
+    #[sol(rpc)]
+    interface IGasPriceOracle {
+        function getL1Fee(bytes memory data) external view returns (uint256);
+    }
+);
+
+/// Address of the `GasPriceOracle` predeploy of the OP-stack rollups.
+const GAS_PRICE_ORACLE: Address = address!("0x420000000000000000000000000000000000000F");
+
+/// Length of a Groth16 seal: a 4-byte selector and the 8 words of the proof.
+const GROTH16_SEAL_LEN: usize = 4 + 8 * 32;
+
+/// Length of a seal proving the inclusion in a root of the set verifier: a 4-byte selector and
+/// the ABI encoding of a Merkle path of depth 8 and of an empty root seal.
+const SET_INCLUSION_SEAL_LEN: usize = 4 + 4 * 32 + 8 * 32;
+
+/// Approximate gas of a `submitRequest` transaction, which stores the request and emits it in
+/// an event.
+pub const SUBMIT_GAS: u64 = 150_000;
+
+/// Approximate gas of a `set` transaction verifying a Groth16 seal.
+pub const SET_GAS_GROTH16: u64 = 300_000;
+
+/// Approximate gas of a `set` transaction verifying the inclusion of the proof in a root of the
+/// set verifier.
+pub const SET_GAS_SET_INCLUSION: u64 = 100_000;
+
+/// Default size of the journal over which its cost is warned about: each byte is paid for in the
+/// calldata of the fulfillment, priced in by the provers, and of the transaction posting it.
+const JOURNAL_WARN_SIZE: usize = 1024;
+
+/// Default size of the calldata over which it is warned about: the maximum size of a transaction
+/// accepted by the mempools of most Ethereum clients.
+const CALLDATA_WARN_SIZE: usize = 128 * 1024;
+
+/// Estimated cost of publishing a number.
+#[derive(Debug)]
+pub struct CostEstimate {
+    /// Maximum price of the proof, in wei.
+    pub max_price: U256,
+    /// L1 data fee of the `set` transaction, in wei, when the EvenNumber contract is on an
+    /// OP-stack rollup.
+    pub l1_data_fee: Option<U256>,
+}
+
+impl CostEstimate {
+    /// Estimate the cost of the request, and of publishing its number to the EvenNumber contract
+    /// with a Groth16 seal, or with a seal of inclusion in a root of the set verifier.
+    pub async fn estimate(
+        provider: &impl Provider,
+        even_number_address: Address,
+        request: &ProofRequest,
+        groth16: bool,
+    ) -> Result<Self> {
+        Ok(Self {
+            max_price: U256::from(request.offer.maxPrice),
+            l1_data_fee: set_l1_data_fee(provider, even_number_address, groth16).await?,
+        })
+    }
+
+    /// Log the summary of the estimate, with the amounts in USD if the price is known.
+    pub fn log(&self, eth_usd: Option<EthUsd>) -> Result<()> {
+        let max_price = usd::format_eth(self.max_price, eth_usd)?;
+        match self.l1_data_fee {
+            Some(fee) => tracing::info!(
+                "Estimated cost: up to {max_price} for the proof, plus {} of L1 data fee for the \
+                 set tx",
+                usd::format_eth(fee, eth_usd)?
+            ),
+            None => tracing::info!("Estimated cost: up to {max_price} for the proof"),
+        }
+        Ok(())
+    }
+}
+
+/// Warn if the journal is large enough to make posting it expensive, or if the calldata of a
+/// transaction posting it with its seal may not be accepted by the chain, with the thresholds of
+/// the project configuration.
+pub fn check_journal_size(journal: &[u8], groth16: bool) {
+    let limits = &config::project().limits;
+    let journal_limit = limits.journal_size.unwrap_or(JOURNAL_WARN_SIZE);
+    let calldata_limit = limits.calldata_size.unwrap_or(CALLDATA_WARN_SIZE);
+    let calldata_len = post_calldata_len(journal.len(), seal_len(groth16));
+    if calldata_len > calldata_limit {
+        tracing::warn!(
+            "A transaction posting the journal of {} bytes with its seal has {calldata_len} bytes \
+             of calldata, over {calldata_limit}: it may be rejected by the chain. Commit a hash \
+             of the data to the journal instead",
+            journal.len()
+        );
+    } else if journal.len() > journal_limit {
+        tracing::warn!(
+            "The journal has {} bytes, over {journal_limit}: the fulfillment and the \
+             transactions posting it pay for each of them in calldata. Commit a hash of the data \
+             to the journal instead",
+            journal.len()
+        );
+    }
+}
+
+/// Approximate gas of a `set` transaction, with a Groth16 seal or a seal of inclusion in a root of
+/// the set verifier.
+pub fn set_gas(groth16: bool) -> u64 {
+    if groth16 {
+        SET_GAS_GROTH16
+    } else {
+        SET_GAS_SET_INCLUSION
+    }
+}
+
+/// L1 data fee of a `set` transaction with a Groth16 seal, or a seal of inclusion in a root of
+/// the set verifier, or `None` if the chain of the provider isn't an OP-stack rollup.
+pub async fn set_l1_data_fee(
+    provider: &impl Provider,
+    even_number_address: Address,
+    groth16: bool,
+) -> Result<Option<U256>> {
+    l1_data_fee(
+        provider,
+        even_number_address,
+        set_calldata(seal_len(groth16)),
+    )
+    .await
+}
+
+/// Length of the seal of a proof.
+fn seal_len(groth16: bool) -> usize {
+    if groth16 {
+        GROTH16_SEAL_LEN
+    } else {
+        SET_INCLUSION_SEAL_LEN
+    }
+}
+
+/// Length of the calldata of a call taking the journal and the seal as `bytes`: the selector,
+/// the two offsets, and the padded length and contents of each.
+fn post_calldata_len(journal_len: usize, seal_len: usize) -> usize {
+    let padded = |len: usize| 32 + len.div_ceil(32) * 32;
+    4 + 2 * 32 + padded(journal_len) + padded(seal_len)
+}
+
+/// L1 data fee of a transaction to `to` with the given calldata, or `None` if the chain of the
+/// provider isn't an OP-stack rollup.
+async fn l1_data_fee(provider: &impl Provider, to: Address, input: Bytes) -> Result<Option<U256>> {
+    if provider.get_code_at(GAS_PRICE_ORACLE).await?.is_empty() {
+        return Ok(None);
+    }
+    // The oracle prices the unsigned RLP encoding of the transaction, and accounts for the
+    // signature itself.
+    let tx = TxEip1559 {
+        chain_id: provider.get_chain_id().await?,
+        to: to.into(),
+        input,
+        ..Default::default()
+    };
+    let mut encoded = Vec::new();
+    tx.encode_for_signing(&mut encoded);
+    let fee = IGasPriceOracle::new(GAS_PRICE_ORACLE, provider)
+        .getL1Fee(encoded.into())
+        .call()
+        .await?;
+    Ok(Some(fee))
+}
+
+/// Calldata of a `set` call with a seal of the given length.
+///
+/// The seal is filled with pseudo-random bytes, which don't compress, like an actual seal: the
+/// oracle estimates the size of the calldata once compressed.
+fn set_calldata(seal_len: usize) -> Bytes {
+    let seal: Vec<u8> = (0u64..)
+        .flat_map(|i| keccak256(i.to_be_bytes()).0)
+        .take(seal_len)
+        .collect();
+    IEvenNumber::setCall {
+        x: U256::MAX,
+        seal: seal.into(),
+    }
+    .abi_encode()
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_set_call_with_seal() {
+        let calldata = set_calldata(GROTH16_SEAL_LEN);
+        let call = IEvenNumber::setCall::abi_decode(&calldata).unwrap();
+        assert_eq!(call.seal.len(), GROTH16_SEAL_LEN);
+        assert_ne!(call.seal[..32], call.seal[32..64]);
+    }
+
+    #[test]
+    fn computes_the_calldata_of_a_post() {
+        alloy::sol! {
+            function post(bytes journal, bytes seal);
+        }
+        let calldata = postCall {
+            journal: vec![1; 33].into(),
+            seal: vec![2; GROTH16_SEAL_LEN].into(),
+        }
+        .abi_encode();
+        assert_eq!(post_calldata_len(33, GROTH16_SEAL_LEN), calldata.len());
+    }
+}
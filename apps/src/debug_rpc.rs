@@ -0,0 +1,172 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Logging of the calls to remote services, behind the `--debug-rpc` flag.
+//!
+//! The calls to the RPC endpoint are logged by a [LogLayer] of its transport. The clients of the
+//! storage provider and the order stream only take URLs, so their calls go through a logging
+//! reverse proxy instead.
+
+use std::{
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use alloy::{
+    rpc::json_rpc::{RequestPacket, ResponsePacket},
+    transports::{TransportError, TransportFut},
+};
+use anyhow::Result;
+use http_body_util::BodyExt;
+use hyper::{body::Incoming, Request};
+use tower::{Layer, Service};
+use url::Url;
+
+use crate::proxy::{self, ProxyResponse};
+
+/// Maximum number of bytes of a request or response body that are logged.
+const MAX_LOGGED_BODY: usize = 2048;
+
+/// Start a proxy forwarding to the origin of `upstream` with `client`, and logging each call with
+/// its response and duration under the given name.
+///
+/// Returns the URL to use in place of `upstream`: the same URL, on the proxy.
+pub async fn proxy(name: &'static str, upstream: Url, client: reqwest::Client) -> Result<Url> {
+    let origin = upstream.clone();
+    let url = proxy::serve(name, upstream, move |request| {
+        forward(name, client.clone(), origin.clone(), request)
+    })
+    .await?;
+    tracing::info!("Logging {name} calls through {url}");
+    Ok(url)
+}
+
+async fn forward(
+    name: &'static str,
+    client: reqwest::Client,
+    url: Url,
+    request: Request<Incoming>,
+) -> ProxyResponse {
+    let (parts, body) = request.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map(|body| body.to_bytes())
+        .unwrap_or_default();
+    tracing::info!(
+        "{name} -> {} {}: {}",
+        parts.method,
+        parts.uri.path(),
+        truncate(&body)
+    );
+
+    let start = Instant::now();
+    let result = proxy::forward(&client, url, parts, body).await;
+    let elapsed = start.elapsed();
+    match &result {
+        Ok((status, _, body)) => {
+            tracing::info!("{name} <- {status} in {elapsed:?}: {}", truncate(body))
+        }
+        Err(e) => tracing::info!("{name} <- failed in {elapsed:?}: {e}"),
+    }
+    Ok(proxy::respond(result))
+}
+
+/// Layer of an RPC transport logging each call with its response and duration, if enabled.
+#[derive(Clone, Copy, Debug)]
+pub struct LogLayer {
+    enabled: bool,
+}
+
+impl LogLayer {
+    /// Layer logging the calls if `enabled`, and passing them through unlogged otherwise.
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for LogLayer {
+    type Service = LogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LogService {
+            inner,
+            enabled: self.enabled,
+        }
+    }
+}
+
+/// RPC transport logging the calls sent over the inner transport.
+#[derive(Clone, Debug)]
+pub struct LogService<S> {
+    inner: S,
+    enabled: bool,
+}
+
+impl<S> Service<RequestPacket> for LogService<S>
+where
+    S: Service<
+        RequestPacket,
+        Response = ResponsePacket,
+        Error = TransportError,
+        Future = TransportFut<'static>,
+    >,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        if !self.enabled {
+            return self.inner.call(request);
+        }
+        let body = serde_json::to_vec(&request).unwrap_or_default();
+        tracing::info!("rpc -> {}", truncate(&body));
+        let start = Instant::now();
+        let call = self.inner.call(request);
+        Box::pin(async move {
+            let result = call.await;
+            let elapsed = start.elapsed();
+            match &result {
+                Ok(response) => {
+                    let body = match response {
+                        ResponsePacket::Single(response) => serde_json::to_vec(response),
+                        ResponsePacket::Batch(responses) => serde_json::to_vec(responses),
+                    };
+                    tracing::info!(
+                        "rpc <- in {elapsed:?}: {}",
+                        truncate(&body.unwrap_or_default())
+                    )
+                }
+                Err(e) => tracing::info!("rpc <- failed in {elapsed:?}: {e}"),
+            }
+            result
+        })
+    }
+}
+
+fn truncate(body: &[u8]) -> String {
+    if body.len() <= MAX_LOGGED_BODY {
+        return String::from_utf8_lossy(body).into_owned();
+    }
+    format!(
+        "{}... ({} bytes)",
+        String::from_utf8_lossy(&body[..MAX_LOGGED_BODY]),
+        body.len()
+    )
+}
@@ -0,0 +1,190 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The balance of the client in the market, which pays for the proofs of its requests.
+//!
+//! A prover locking a request takes its price from the balance of the client, so the balance
+//! must cover the maximum price of every request waiting to be locked. The market tops it up
+//! with the value sent with a request, which the SDK sets to the shortfall of that request alone:
+//! concurrent requests of a run would then share a balance covering only one of them. Before
+//! each submission, the shortfall of the request together with the other requests of the run
+//! not locked yet is computed, and deposited with `--auto-deposit`.
+//!
+//! A smart contract client pays from its own balance, which the wallet can't top up: its
+//! shortfall fails the submission.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use alloy::{
+    primitives::{utils::format_ether, U256},
+    providers::Provider,
+};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{contracts::RequestStatus, ProofRequest, StandardClient};
+
+use crate::failure::Failure;
+
+/// Balance reserved in the market by the requests of a run.
+#[derive(Default)]
+pub struct Deposits {
+    /// Maximum prices of the requests of the run waited for, by request ID.
+    reserved: Mutex<HashMap<U256, U256>>,
+    /// Held while checking the balance and depositing, so that concurrent requests see the
+    /// deposits of one another.
+    topping_up: tokio::sync::Mutex<()>,
+}
+
+/// The maximum price of a request, reserved while the request is waited for.
+pub struct Reservation<'a> {
+    deposits: &'a Deposits,
+    request_id: U256,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.deposits
+            .reserved
+            .lock()
+            .unwrap()
+            .remove(&self.request_id);
+    }
+}
+
+impl Deposits {
+    /// Reserve the maximum price of the request, and deposit the shortfall of the balance of the
+    /// client if `auto_deposit` is set. Otherwise the shortfall is only reported.
+    pub async fn reserve(
+        &self,
+        client: &StandardClient,
+        request: &ProofRequest,
+        auto_deposit: bool,
+    ) -> Result<Reservation<'_>> {
+        let _topping_up = self.topping_up.lock().await;
+        let amount = U256::from(request.offer.maxPrice);
+        let in_flight = self.unlocked(client).await?;
+        let client_address = request.client_address();
+        let balance = client
+            .boundless_market
+            .balance_of(client_address)
+            .await
+            .context("failed to get the balance of the client in the market")?;
+        let shortfall = shortfall(balance, in_flight, amount);
+        if !shortfall.is_zero() {
+            if client_address != client.caller() {
+                return Err(anyhow!(
+                    "the balance of {} ETH of the client contract {client_address} in the market \
+                     doesn't cover the requests in flight and this one, it must be funded with {} \
+                     ETH more",
+                    format_ether(balance),
+                    format_ether(shortfall)
+                ))
+                .context(Failure::Config);
+            } else if auto_deposit {
+                deposit(client, shortfall).await?;
+            } else if in_flight.is_zero() {
+                tracing::debug!(
+                    "The shortfall of {} ETH is sent with the request",
+                    format_ether(shortfall)
+                );
+            } else {
+                tracing::warn!(
+                    "The balance of {} ETH in the market doesn't cover the other requests in \
+                     flight and this one, deposit {} ETH or pass --auto-deposit",
+                    format_ether(balance),
+                    format_ether(shortfall)
+                );
+            }
+        }
+        self.reserved.lock().unwrap().insert(request.id, amount);
+        Ok(Reservation {
+            deposits: self,
+            request_id: request.id,
+        })
+    }
+
+    /// Sum of the maximum prices of the requests reserved that no prover locked yet. The price
+    /// of a locked request already left the balance.
+    async fn unlocked(&self, client: &StandardClient) -> Result<U256> {
+        let reserved: Vec<_> = self.reserved.lock().unwrap().clone().into_iter().collect();
+        let mut sum = U256::ZERO;
+        for (request_id, amount) in reserved {
+            let status = client
+                .boundless_market
+                .get_status(request_id, None)
+                .await
+                .context("failed to get the status of a request in flight")?;
+            if status == RequestStatus::Unknown {
+                sum += amount;
+            }
+        }
+        Ok(sum)
+    }
+}
+
+/// Deposit `amount` into the market, if the wallet holds it.
+async fn deposit(client: &StandardClient, amount: U256) -> Result<()> {
+    let wallet = client.provider().get_balance(client.caller()).await?;
+    if wallet < amount {
+        return Err(anyhow!(
+            "the wallet holds {} ETH, less than the deposit of {} ETH the request needs",
+            format_ether(wallet),
+            format_ether(amount)
+        ))
+        .context(Failure::Config);
+    }
+    tracing::info!(
+        "Depositing the shortfall of {} ETH into the market",
+        format_ether(amount)
+    );
+    client
+        .boundless_market
+        .deposit(amount)
+        .await
+        .context("failed to deposit")
+        .context(Failure::TxReverted)
+}
+
+/// Amount missing from the balance to cover the requests in flight and the next one.
+fn shortfall(balance: U256, in_flight: U256, amount: U256) -> U256 {
+    (in_flight + amount).saturating_sub(balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_the_shortfall() {
+        let eth = |milli: u64| U256::from(milli) * U256::from(10u64.pow(15));
+        assert_eq!(shortfall(eth(0), eth(0), eth(5)), eth(5));
+        assert_eq!(shortfall(eth(8), eth(0), eth(5)), U256::ZERO);
+        assert_eq!(shortfall(eth(8), eth(5), eth(5)), eth(2));
+    }
+
+    #[test]
+    fn releases_reservations() {
+        let deposits = Deposits::default();
+        let mut reserved = HashMap::from([(U256::from(1), U256::from(6))]);
+        reserved.insert(U256::from(2), U256::from(4));
+        *deposits.reserved.lock().unwrap() = reserved;
+        drop(Reservation {
+            deposits: &deposits,
+            request_id: U256::from(2),
+        });
+        assert_eq!(
+            *deposits.reserved.lock().unwrap(),
+            HashMap::from([(U256::from(1), U256::from(6))])
+        );
+    }
+}
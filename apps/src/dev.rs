@@ -0,0 +1,126 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dev mode: the submit flow against a local devnet, with fake receipts instead of proofs from the
+//! Boundless Market.
+//!
+//! The guest is executed locally, and the seal of a fake receipt of the execution is posted to an
+//! EvenNumber contract deployed with a mock verifier, as done by `contracts/scripts/DeployDev.s.sol`.
+
+use std::time::{Duration, Instant};
+
+use alloy::{
+    primitives::U256,
+    providers::{Provider, ProviderBuilder},
+};
+use anyhow::{anyhow, Context, Result};
+use futures_util::future::try_join_all;
+use guests::IS_EVEN_ID;
+use risc0_ethereum_contracts::encode_seal;
+use risc0_zkvm::{sha::Digest, FakeReceipt, InnerReceipt, Receipt, ReceiptClaim};
+
+use crate::{
+    even_number::IEvenNumber::IEvenNumberInstance, failure::Failure, networks, preflight,
+    SubmitArgs,
+};
+
+/// Time spent in each phase of the dev-mode flow, summed over the published numbers.
+#[derive(Debug, Default)]
+pub struct Timings {
+    /// Executing the guest on all the inputs, in parallel.
+    pub preflight: Duration,
+    /// Building the fake receipts and their seals.
+    pub seal: Duration,
+    /// Signing and broadcasting the set transactions.
+    pub send: Duration,
+    /// Waiting for the set transactions to be confirmed.
+    pub confirm: Duration,
+}
+
+/// Publish each number to the EvenNumber contract with the seal of a fake receipt.
+pub async fn submit(args: &SubmitArgs) -> Result<Timings> {
+    let mut timings = Timings::default();
+    let provider = ProviderBuilder::new()
+        .wallet(args.client.private_key.clone())
+        .connect(args.client.rpc_url.as_str())
+        .await
+        .context("failed to connect to the devnet")
+        .context(Failure::Config)?;
+    let [even_number_address] = args.even_number_addresses.as_slice() else {
+        return Err(anyhow!(
+            "dev mode only publishes to a single EvenNumber contract"
+        ))
+        .context(Failure::Config);
+    };
+    let even_number_address = even_number_address
+        .resolve(&provider)
+        .await
+        .context(Failure::Config)?;
+    networks::ensure_contract(&provider, even_number_address, "EvenNumber")
+        .await
+        .context(Failure::Config)?;
+    let history = args.state.open(crate::state_dir::Access::Shared)?;
+    let even_number = IEvenNumberInstance::new(even_number_address, provider.erased());
+
+    // Execute the guest on all the inputs in parallel, then publish the numbers one at a time.
+    let start = Instant::now();
+    let preflights = try_join_all(args.numbers.iter().map(|&number| {
+        let input = guests::encode_input(U256::from(number));
+        let history = &history;
+        async move { preflight::preflight(history, &input, !args.no_preflight_cache).await }
+    }))
+    .await
+    .context(Failure::Preflight)?;
+    timings.preflight = start.elapsed();
+
+    for (&number, preflight) in args.numbers.iter().zip(preflights) {
+        tracing::info!("Number to publish (dev mode): {number}");
+        let start = Instant::now();
+        let journal = preflight.journal;
+        let claim = ReceiptClaim::ok(Digest::from(IS_EVEN_ID), journal.bytes.clone());
+        let receipt = Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal.bytes);
+        let seal = encode_seal(&receipt)?;
+        timings.seal += start.elapsed();
+
+        let start = Instant::now();
+        let call_set = even_number.set(U256::from(number), seal.into());
+        let pending_tx = args
+            .consumer
+            .fees
+            .apply(even_number.provider(), call_set)
+            .await?
+            .send()
+            .await
+            .context("failed to broadcast tx")
+            .context(Failure::TxReverted)?;
+        timings.send += start.elapsed();
+        let start = Instant::now();
+        let receipt = pending_tx
+            .with_timeout(Some(args.consumer.tx_timeout(false)))
+            .get_receipt()
+            .await
+            .context("failed to confirm tx")?;
+        timings.confirm += start.elapsed();
+        if !receipt.status() {
+            return Err(anyhow!("tx {} reverted", receipt.transaction_hash))
+                .context(Failure::TxReverted);
+        }
+        let stored = even_number.get().call().await?;
+        tracing::info!(
+            "Tx {} confirmed, the number is set to {stored}",
+            receipt.transaction_hash
+        );
+    }
+    Ok(timings)
+}
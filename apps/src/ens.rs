@@ -0,0 +1,158 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Address arguments given as ENS names.
+//!
+//! A name is resolved through the RPC endpoint, by looking up its resolver in the ENS registry,
+//! then its address in the resolver. The registry is deployed at the same address on Ethereum
+//! mainnet and its testnets.
+
+use std::{fmt, str::FromStr};
+
+use alloy::{
+    primitives::{address, keccak256, Address, B256},
+    providers::Provider,
+};
+use anyhow::{anyhow, ensure, Context, Result};
+
+alloy::sol!(
+    #[sol(rpc)]
+    interface IEnsRegistry {
+        function resolver(bytes32 node) external view returns (address);
+    }
+
+    #[sol(rpc)]
+    interface IEnsResolver {
+        function addr(bytes32 node) external view returns (address);
+    }
+);
+
+/// Address of the ENS registry.
+const ENS_REGISTRY: Address = address!("0x00000000000C2E074eC69A0dFb2997BA6C7d2e1e");
+
+/// An address, or an ENS name resolving to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NameOrAddress {
+    Name(String),
+    Address(Address),
+}
+
+impl NameOrAddress {
+    /// Resolve the address, looking the name up through the provider if it is one.
+    pub async fn resolve(&self, provider: &impl Provider) -> Result<Address> {
+        match self {
+            Self::Address(address) => Ok(*address),
+            Self::Name(name) => {
+                let address = resolve_name(provider, name)
+                    .await
+                    .with_context(|| format!("failed to resolve the ENS name {name}"))?;
+                tracing::info!("Resolved {name} to {address}");
+                Ok(address)
+            }
+        }
+    }
+}
+
+impl From<Address> for NameOrAddress {
+    fn from(address: Address) -> Self {
+        Self::Address(address)
+    }
+}
+
+impl FromStr for NameOrAddress {
+    type Err = anyhow::Error;
+
+    /// Parse a hex address, or an ENS name. Names are lowercased, but not otherwise normalized.
+    fn from_str(s: &str) -> Result<Self> {
+        if let Ok(address) = s.parse() {
+            return Ok(Self::Address(address));
+        }
+        let name = s.to_lowercase();
+        if name.starts_with("0x") || !name.contains('.') || name.split('.').any(str::is_empty) {
+            return Err(anyhow!("invalid address or ENS name: {s}"));
+        }
+        Ok(Self::Name(name))
+    }
+}
+
+impl fmt::Display for NameOrAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => f.write_str(name),
+            Self::Address(address) => write!(f, "{address}"),
+        }
+    }
+}
+
+async fn resolve_name(provider: &impl Provider, name: &str) -> Result<Address> {
+    ensure!(
+        !provider.get_code_at(ENS_REGISTRY).await?.is_empty(),
+        "no ENS registry on chain {}",
+        provider.get_chain_id().await?
+    );
+    let node = namehash(name);
+    let resolver = IEnsRegistry::new(ENS_REGISTRY, provider)
+        .resolver(node)
+        .call()
+        .await?;
+    ensure!(!resolver.is_zero(), "the name has no resolver");
+    let address = IEnsResolver::new(resolver, provider)
+        .addr(node)
+        .call()
+        .await?;
+    ensure!(!address.is_zero(), "the name has no address");
+    Ok(address)
+}
+
+/// Node of the name in the ENS registry, as defined by EIP-137.
+fn namehash(name: &str) -> B256 {
+    name.rsplit('.').fold(B256::ZERO, |node, label| {
+        keccak256([node.as_slice(), keccak256(label).as_slice()].concat())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::b256;
+
+    use super::*;
+
+    #[test]
+    fn hashes_names() {
+        assert_eq!(
+            namehash("eth"),
+            b256!("0x93cdeb708b7545dc668eb9280176169d1c33cfd8ed6f04690a0bcc88a93fc4ae")
+        );
+        assert_eq!(
+            namehash("foo.eth"),
+            b256!("0xde9b09fd7c5f901e23a3f19fecc54828e9c848539801e86591bd9801b019f84f")
+        );
+    }
+
+    #[test]
+    fn parses_names_and_addresses() {
+        let address = Address::repeat_byte(0x11);
+        assert_eq!(
+            address.to_string().parse::<NameOrAddress>().unwrap(),
+            NameOrAddress::Address(address)
+        );
+        assert_eq!(
+            "MyApp.eth".parse::<NameOrAddress>().unwrap(),
+            NameOrAddress::Name("myapp.eth".to_string())
+        );
+        assert!("myapp".parse::<NameOrAddress>().is_err());
+        assert!("myapp..eth".parse::<NameOrAddress>().is_err());
+        assert!("0x1234".parse::<NameOrAddress>().is_err());
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Environment files, holding the options and secrets of a deployment, e.g. `.env.sepolia`.
+//!
+//! The file loaded is the one of `--env-file`, else `.env.<profile>` if `BOUNDLESS_PROFILE` is
+//! set, else `.env` if it exists. Only one file is loaded, so that the secrets of a profile never
+//! leak into another one, and variables already set in the environment take precedence over it.
+//! The file is loaded before the arguments are parsed, since they may be read from it, so
+//! `--env-file` is looked up in the raw arguments.
+
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+/// Variable selecting the environment file `.env.<profile>`.
+pub const PROFILE_VAR: &str = "BOUNDLESS_PROFILE";
+
+/// Load the environment file selected by the arguments of the command line or by
+/// [PROFILE_VAR]. Returns the path of the file loaded, if any.
+///
+/// A file that is selected explicitly must exist, while a missing `.env` is skipped.
+pub fn load(args: impl IntoIterator<Item = OsString>) -> Result<Option<PathBuf>> {
+    let path = match selected(args, std::env::var_os(PROFILE_VAR)) {
+        Some(path) => path,
+        None if Path::new(".env").exists() => PathBuf::from(".env"),
+        None => return Ok(None),
+    };
+    dotenvy::from_path(&path).with_context(|| format!("failed to load {}", path.display()))?;
+    Ok(Some(path))
+}
+
+/// Environment file given by `--env-file`, or by the profile.
+fn selected(
+    args: impl IntoIterator<Item = OsString>,
+    profile: Option<OsString>,
+) -> Option<PathBuf> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--env-file" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.to_str().and_then(|arg| arg.strip_prefix("--env-file=")) {
+            return Some(PathBuf::from(path));
+        }
+    }
+    let mut path = OsString::from(".env.");
+    path.push(profile.filter(|profile| !profile.is_empty())?);
+    Some(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selected_by(args: &[&str], profile: Option<&str>) -> Option<PathBuf> {
+        selected(args.iter().map(OsString::from), profile.map(OsString::from))
+    }
+
+    #[test]
+    fn selects_env_file() {
+        assert_eq!(
+            selected_by(
+                &["app", "--env-file", ".env.prod", "submit"],
+                Some("sepolia")
+            ),
+            Some(PathBuf::from(".env.prod"))
+        );
+        assert_eq!(
+            selected_by(&["app", "submit", "--env-file=.env.prod"], None),
+            Some(PathBuf::from(".env.prod"))
+        );
+        assert_eq!(
+            selected_by(&["app", "submit"], Some("sepolia")),
+            Some(PathBuf::from(".env.sepolia"))
+        );
+        assert_eq!(selected_by(&["app", "submit"], Some("")), None);
+        assert_eq!(selected_by(&["app", "--", "--env-file", "x"], None), None);
+    }
+}
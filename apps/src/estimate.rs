@@ -0,0 +1,232 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Dry run of a request: what it would cost, without uploading or submitting anything.
+//!
+//! The guest is executed locally, and the request is built with the offer of the client as it
+//! would be submitted, for a program URL that is never fetched. The report gives the cycle count
+//! and journal of the execution, the offer, the price of the auction over time, and the
+//! worst-case cost: the maximum price of the proof, and the gas of the `submitRequest` and `set`
+//! transactions at the current gas price of the chain of the market.
+//!
+//! Like on submission, an input too large to be inlined in the request is uploaded by the
+//! storage provider: the is-even input of a number always fits.
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use anyhow::{Context, Result};
+use boundless_market::Offer;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use risc0_zkvm::sha::Digestible;
+use url::Url;
+
+use crate::{
+    build_request_for_input, costs, expiry, failure::Failure, history::Timings, input, preflight,
+    state_dir, usd, ClientArgs,
+};
+
+/// URL of the program in the request built, which doesn't change its cost.
+const PROGRAM_URL: &str = "https://example.com/is-even";
+
+/// Arguments of the estimate command.
+#[derive(Args, Clone, Debug)]
+pub struct EstimateArgs {
+    /// The number to estimate the request of.
+    #[clap(
+        short,
+        long,
+        required_unless_present_any = ["input_file", "input_hex", "input_stdin"],
+        conflicts_with_all = ["input_file", "input_hex", "input_stdin"]
+    )]
+    number: Option<u32>,
+    /// Estimate a request for a stand-alone Groth16 proof, as for an EvenNumber contract on
+    /// another chain than the market.
+    #[clap(long)]
+    groth16: bool,
+    /// Time at which the request expires, e.g. 2025-07-01T12:00:00Z, instead of the timeout of
+    /// the offer.
+    #[clap(long)]
+    expires_at: Option<DateTime<Utc>>,
+    #[clap(flatten)]
+    state: state_dir::StateArgs,
+    /// Execute the program even if the same input was executed before.
+    #[clap(long)]
+    no_preflight_cache: bool,
+    #[clap(flatten)]
+    client: ClientArgs,
+    #[clap(flatten, next_help_heading = "Guest Input")]
+    input: input::InputArgs,
+    #[clap(flatten, next_help_heading = "USD Prices")]
+    prices: usd::PriceArgs,
+}
+
+/// Execute the guest, build the request, and print what it would cost.
+pub async fn run(args: EstimateArgs) -> Result<()> {
+    let input = match args.input.read().context(Failure::Config)? {
+        Some(input) => input,
+        None => {
+            let number = args.number.context("a number or an input is required")?;
+            guests::encode_input(U256::from(number))
+        }
+    };
+    let client = args.client.build_client().await?;
+    let history = args.state.open(state_dir::Access::Read)?;
+    // Executed ahead of the request builder for its cycle count, then cached for the builder.
+    let preflight = preflight::preflight(&history, &input, !args.no_preflight_cache)
+        .await
+        .context(Failure::Preflight)?;
+    let (mut request, journal) = build_request_for_input(
+        &client,
+        &history,
+        &Url::parse(PROGRAM_URL)?,
+        input,
+        args.groth16,
+        true,
+        &mut Timings::default(),
+    )
+    .await?;
+    if let Some(deadline) = args.expires_at {
+        expiry::expire_at(&mut request, deadline)?;
+    }
+
+    let eth_usd = args.prices.try_eth_usd(Some(&args.client.rpc_url)).await;
+    let eth = |wei| usd::format_eth(wei, eth_usd);
+    let offer = &request.offer;
+    println!(
+        "Cycles:          {}, priced as {:.2} Mcycles",
+        preflight.cycles,
+        preflight::mcycles(preflight.cycles)
+    );
+    match &preflight.segments {
+        Some(segments) => println!("{}", indent(&preflight::SegmentTable(segments).to_string())),
+        None => println!("  (segments not cached, pass --no-preflight-cache to list them)"),
+    }
+    println!("Journal digest:  0x{}", preflight.journal.digest());
+    println!(
+        "Number:          {}, committed by the guest",
+        journal.number
+    );
+    println!("Offer:");
+    println!(
+        "  price:         {} to {}",
+        eth(U256::from(offer.minPrice))?,
+        eth(U256::from(offer.maxPrice))?
+    );
+    let bidding_start =
+        DateTime::from_timestamp(offer.biddingStart as i64, 0).context("invalid bidding start")?;
+    println!("  bidding start: {bidding_start}");
+    println!("  ramp-up:       {}s", offer.rampUpPeriod);
+    println!("  lock timeout:  {}s", offer.lockTimeout);
+    println!("  timeout:       {}s", offer.timeout);
+    println!(
+        "  lock stake:    {} units of the stake token",
+        offer.lockStake
+    );
+    println!("Price after the start of the bidding:");
+    for (offset, price) in price_curve(offer)? {
+        println!("  {:>7}  {}", format!("+{offset}s"), eth(price)?);
+    }
+
+    // The size of the set transaction, and so its L1 data fee, doesn't depend on the address.
+    let provider = client.provider();
+    let estimate =
+        costs::CostEstimate::estimate(&provider, Address::ZERO, &request, args.groth16).await?;
+    let gas_price = U256::from(provider.get_gas_price().await?);
+    let set_gas = costs::set_gas(args.groth16);
+    let submit_cost = gas_price * U256::from(costs::SUBMIT_GAS);
+    let set_cost = gas_price * U256::from(set_gas);
+    let l1_data_fee = estimate.l1_data_fee.unwrap_or_default();
+    println!("Worst-case cost:");
+    println!("  proof:         {}", eth(estimate.max_price)?);
+    println!(
+        "  submit tx:     {} (~{} gas)",
+        eth(submit_cost)?,
+        costs::SUBMIT_GAS
+    );
+    println!("  set tx:        {} (~{set_gas} gas)", eth(set_cost)?);
+    if estimate.l1_data_fee.is_some() {
+        println!("  L1 data fee:   {}", eth(l1_data_fee)?);
+    }
+    println!(
+        "  total:         {}",
+        eth(estimate.max_price + submit_cost + set_cost + l1_data_fee)?
+    );
+    Ok(())
+}
+
+/// Indent every line of the text, to nest it under a heading.
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("  {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Price of the offer at points of the auction, by their offset from the start of the bidding:
+/// through the ramp-up, until the lock timeout, and after it, when the proof is paid nothing.
+fn price_curve(offer: &Offer) -> Result<Vec<(u64, U256)>> {
+    let ramp_up = u64::from(offer.rampUpPeriod);
+    let lock_timeout = u64::from(offer.lockTimeout);
+    let mut offsets: Vec<u64> = (0..=4).map(|i| ramp_up * i / 4).collect();
+    offsets.extend([lock_timeout, lock_timeout + 1]);
+    offsets.dedup();
+    offsets
+        .into_iter()
+        .map(|offset| {
+            let price = offer
+                .price_at(offer.biddingStart + offset)
+                .context("invalid offer")?;
+            Ok((offset, price))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_the_auction_over_time() {
+        let offer = Offer::default()
+            .with_bidding_start(1000)
+            .with_min_price(U256::from(100))
+            .with_max_price(U256::from(500))
+            .with_ramp_up_period(40)
+            .with_lock_timeout(600)
+            .with_timeout(1200);
+        let curve: Vec<_> = price_curve(&offer)
+            .unwrap()
+            .into_iter()
+            .map(|(offset, price)| (offset, price.to::<u64>()))
+            .collect();
+        assert_eq!(
+            curve,
+            [
+                (0, 100),
+                (10, 200),
+                (20, 300),
+                (30, 400),
+                (40, 500),
+                (600, 500),
+                (601, 0)
+            ]
+        );
+
+        let offer = offer.with_ramp_up_period(0);
+        assert_eq!(price_curve(&offer).unwrap().len(), 3);
+    }
+}
@@ -0,0 +1,90 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Requests expiring at a deadline, rather than after a timeout.
+//!
+//! The offer of a request expires `timeout` seconds after its bidding starts, and its lock
+//! `lockTimeout` seconds after. A deadline sets the timeout of the request built, so that it
+//! expires at the deadline, and shortens its lock timeout and ramp-up to fit if needed.
+
+use anyhow::{anyhow, Context, Result};
+use boundless_market::ProofRequest;
+use chrono::{DateTime, Utc};
+
+use crate::failure::Failure;
+
+/// Set the timeout of the request so that it expires at `deadline`.
+pub fn expire_at(request: &mut ProofRequest, deadline: DateTime<Utc>) -> Result<()> {
+    let offer = &mut request.offer;
+    let timeout = u64::try_from(deadline.timestamp())
+        .ok()
+        .and_then(|deadline| deadline.checked_sub(offer.biddingStart))
+        .filter(|&timeout| timeout > 0)
+        .ok_or_else(|| {
+            anyhow!(
+                "the deadline {deadline} is not after the start of the bidding, at {}",
+                offer.biddingStart
+            )
+        })
+        .context(Failure::Config)?;
+    offer.timeout = u32::try_from(timeout)
+        .map_err(|_| anyhow!("the deadline {deadline} is too far away"))
+        .context(Failure::Config)?;
+    offer.lockTimeout = offer.lockTimeout.min(offer.timeout);
+    offer.rampUpPeriod = offer.rampUpPeriod.min(offer.lockTimeout);
+    request
+        .validate()
+        .map_err(|e| anyhow!("invalid request expiring at {deadline}: {e}"))
+        .context(Failure::Config)?;
+    tracing::info!(
+        "Request expiring at {deadline}, {}s after the start of the bidding",
+        request.offer.timeout
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+    use boundless_market::Offer;
+
+    use super::*;
+
+    fn new_request() -> ProofRequest {
+        let offer = Offer::default()
+            .with_bidding_start(1000)
+            .with_ramp_up_period(100)
+            .with_lock_timeout(600)
+            .with_timeout(1200)
+            .with_max_price(U256::from(1));
+        crate::wait::tests::new_request(offer)
+    }
+
+    #[test]
+    fn sets_the_timeout_of_the_deadline() {
+        let mut request = new_request();
+        expire_at(&mut request, DateTime::from_timestamp(4000, 0).unwrap()).unwrap();
+        assert_eq!(request.expires_at(), 4000);
+        assert_eq!(request.offer.lockTimeout, 600);
+
+        let mut request = new_request();
+        expire_at(&mut request, DateTime::from_timestamp(1050, 0).unwrap()).unwrap();
+        assert_eq!(request.expires_at(), 1050);
+        assert_eq!(request.lock_expires_at(), 1050);
+        assert_eq!(request.offer.rampUpPeriod, 50);
+
+        let error = expire_at(&mut request, DateTime::from_timestamp(1000, 0).unwrap());
+        assert_eq!(Failure::of(&error.unwrap_err()), Some(Failure::Config));
+    }
+}
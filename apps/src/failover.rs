@@ -0,0 +1,116 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Failover between RPC endpoints, behind the `--fallback-rpc-url` option.
+//!
+//! Each call is sent to all the endpoints at once through the [FallbackLayer] of alloy, and the
+//! first successful response is used, so that calls don't wait on an endpoint that is down.
+//! Transactions are signed by the app, so the endpoints all broadcast the same transaction, which
+//! is only included once. Responses carrying a JSON-RPC error are returned as is: the call reached
+//! a node, which would likely fail the same way on another endpoint.
+//!
+//! When every endpoint refused a call, e.g. by rate limiting it, the call is retried by a
+//! [RetryBackoffLayer], for a few rounds over all the endpoints.
+
+use std::{num::NonZeroUsize, time::Duration};
+
+use alloy::transports::{
+    http::Http,
+    layers::{FallbackLayer, RateLimitRetryPolicy, RetryBackoffLayer, RetryPolicy},
+    BoxTransport, IntoBoxTransport, TransportError,
+};
+use anyhow::{ensure, Context, Result};
+use tower::ServiceBuilder;
+use url::Url;
+
+use crate::{retry, transport};
+
+/// Timeout of a call to an endpoint.
+const CALL_TIMEOUT: Duration = Duration::from_secs(10);
+/// Number of rounds over all the endpoints before a call fails.
+const ROUNDS: u32 = 3;
+/// Delay between two rounds over all the endpoints.
+const ROUND_DELAY: Duration = Duration::from_secs(2);
+
+/// Transport sending each call to all the given RPC endpoints.
+pub fn transport(urls: &[Url]) -> Result<BoxTransport> {
+    ensure!(
+        urls.iter().all(transport::is_http),
+        "fallback RPC endpoints are only supported over HTTP"
+    );
+    let count = NonZeroUsize::new(urls.len()).context("no RPC endpoint to fail over between")?;
+    let client = reqwest::Client::builder().timeout(CALL_TIMEOUT).build()?;
+    let endpoints: Vec<_> = urls
+        .iter()
+        .map(|url| Http::with_client(client.clone(), url.clone()))
+        .collect();
+    let transport = ServiceBuilder::new()
+        .layer(RetryBackoffLayer::new_with_policy(
+            ROUNDS - 1,
+            ROUND_DELAY.as_millis() as u64,
+            // The rounds aren't slowed down by a compute budget.
+            u64::MAX,
+            Refused,
+        ))
+        .layer(FallbackLayer::default().with_active_transport_count(count))
+        .service(endpoints);
+    Ok(transport.into_box_transport())
+}
+
+/// Retry of the calls all the endpoints refused, which none of them processed.
+#[derive(Clone, Copy, Debug)]
+struct Refused;
+
+impl RetryPolicy for Refused {
+    fn should_retry(&self, error: &TransportError) -> bool {
+        retry::is_refused(error)
+    }
+
+    fn backoff_hint(&self, error: &TransportError) -> Option<Duration> {
+        RateLimitRetryPolicy::default().backoff_hint(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{primitives::U64, rpc::client::RpcClient, transports::TransportErrorKind};
+    use http_body_util::Full;
+    use hyper::Response;
+
+    use super::*;
+    use crate::proxy;
+
+    #[tokio::test]
+    async fn answers_from_the_endpoints_that_are_up() {
+        let up = proxy::serve("rpc", "http://localhost/".parse().unwrap(), |_| async {
+            let body = r#"{"jsonrpc":"2.0","id":0,"result":"0x1"}"#;
+            Ok(Response::new(Full::new(body.into())))
+        })
+        .await
+        .unwrap();
+        // Nothing listens on the port 1.
+        let down = "http://127.0.0.1:1/".parse().unwrap();
+        let client = RpcClient::new(transport(&[down, up]).unwrap(), true);
+        let chain_id: U64 = client.request_noparams("eth_chainId").await.unwrap();
+        assert_eq!(chain_id, U64::from(1));
+    }
+
+    #[test]
+    fn only_retries_refused_calls() {
+        let rate_limited = TransportErrorKind::http_error(429, "Too Many Requests".to_string());
+        assert!(Refused.should_retry(&rate_limited));
+        let unavailable = TransportErrorKind::http_error(503, String::new());
+        assert!(!Refused.should_retry(&unavailable));
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kinds of failure, and the process exit codes they map to.
+
+use std::{fmt, process::ExitCode};
+
+/// Kind of failure, attached as context to an error to set the exit code of the process.
+///
+/// Errors without a kind exit with code 1. Invalid arguments are reported by clap, which exits
+/// with code 2 like configuration errors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The configuration, from the arguments or the environment, is invalid.
+    Config,
+    /// The program could not be uploaded.
+    Upload,
+    /// The request could not be built, usually because executing the program failed.
+    Preflight,
+    /// The request expired without being locked.
+    Expired,
+    /// The request was locked, but the prover failed to fulfill it in time and was slashed.
+    Slashed,
+    /// A transaction sent to the market or the EvenNumber contract failed or reverted.
+    TxReverted,
+    /// The journal of the fulfilled request does not encode the number submitted.
+    JournalMismatch,
+}
+
+impl Failure {
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Config => 2,
+            Self::Upload => 3,
+            Self::Preflight => 4,
+            Self::Expired => 5,
+            Self::Slashed => 6,
+            Self::TxReverted => 7,
+            Self::JournalMismatch => 8,
+        }
+    }
+
+    /// Kind of the given error, if it has one.
+    pub fn of(error: &anyhow::Error) -> Option<Self> {
+        error.downcast_ref::<Self>().copied()
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Config => "invalid configuration",
+            Self::Upload => "upload failed",
+            Self::Preflight => "preflight failed",
+            Self::Expired => "request expired",
+            Self::Slashed => "request expired after being locked, the prover was slashed",
+            Self::TxReverted => "transaction failed",
+            Self::JournalMismatch => "the fulfilled journal doesn't match the request",
+        })
+    }
+}
+
+/// Exit code of the process for a command that failed with the given error.
+pub fn exit_code(error: &anyhow::Error) -> ExitCode {
+    ExitCode::from(Failure::of(error).map_or(1, Failure::code))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Context};
+
+    use super::*;
+
+    #[test]
+    fn finds_kind_through_context() {
+        let error = Err::<(), _>(anyhow!("execution reverted"))
+            .context(Failure::TxReverted)
+            .context("failed to broadcast tx")
+            .unwrap_err();
+        assert_eq!(Failure::of(&error), Some(Failure::TxReverted));
+        assert_eq!(Failure::of(&anyhow!("other")), None);
+    }
+}
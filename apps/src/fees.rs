@@ -0,0 +1,264 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Fee parameters of the transactions sent to the EvenNumber contract.
+//!
+//! By default, the fees are filled in by alloy from the EIP-1559 fee history of the chain. Some
+//! L2s and alt-chains don't support EIP-1559 transactions, or report fees the estimator gets
+//! wrong; the fees can then be fixed, estimated from the legacy gas price, or scaled.
+//!
+//! Before a set transaction is sent, its cost is estimated at the fees paid in recent blocks, by
+//! speed: the seal in its calldata makes it more expensive than a plain call.
+
+use std::fmt;
+
+use alloy::{
+    contract::{CallBuilder, CallDecoder},
+    eips::BlockNumberOrTag,
+    primitives::{utils::format_ether, U256},
+    providers::Provider,
+};
+use anyhow::{ensure, Context, Result};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+
+/// Number of recent blocks the priority fees of the cost tiers are sampled from.
+const FEE_HISTORY_BLOCKS: u64 = 20;
+/// Percentiles of the priority fees of recent blocks paid by the slow, normal and fast tiers.
+const TIER_PERCENTILES: [f64; 3] = [10.0, 50.0, 90.0];
+
+/// Arguments setting the fees of the transactions sent to the EvenNumber contract.
+#[derive(Args, Clone, Debug, Default)]
+pub struct FeeArgs {
+    /// Fixed gas price, in wei, of the transactions, sent as legacy transactions.
+    #[clap(long, env, conflicts_with_all = ["legacy", "gas_multiplier"])]
+    gas_price: Option<u128>,
+    /// Send legacy transactions, priced at the gas price of the chain, instead of EIP-1559 ones.
+    #[clap(long, env)]
+    legacy: bool,
+    /// Multiplier applied to the estimated fees, e.g. 1.5 to pay 50% above the estimate.
+    #[clap(long, env, value_parser = parse_multiplier)]
+    gas_multiplier: Option<f64>,
+}
+
+/// Fees of a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Fees {
+    Legacy {
+        gas_price: u128,
+    },
+    Eip1559 {
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    },
+}
+
+impl FeeArgs {
+    /// Set the fees of the call, leaving them to the fillers of the provider when no fee
+    /// parameter is given.
+    pub async fn apply<P: Provider, D: CallDecoder>(
+        &self,
+        provider: &impl Provider,
+        call: CallBuilder<P, D>,
+    ) -> Result<CallBuilder<P, D>> {
+        let fees = self
+            .fees(provider)
+            .await
+            .context("failed to estimate the fees")?;
+        Ok(match fees {
+            None => call,
+            Some(Fees::Legacy { gas_price }) => call.gas_price(gas_price),
+            Some(Fees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            }) => call
+                .max_fee_per_gas(max_fee_per_gas)
+                .max_priority_fee_per_gas(max_priority_fee_per_gas),
+        })
+    }
+
+    /// Highest price per gas the transactions are sent at: the fixed or legacy gas price, the
+    /// maximum fee of EIP-1559 ones, or else the gas price of the chain.
+    pub async fn gas_price(&self, provider: &impl Provider) -> Result<u128> {
+        match self.fees(provider).await? {
+            Some(Fees::Legacy { gas_price }) => Ok(gas_price),
+            Some(Fees::Eip1559 {
+                max_fee_per_gas, ..
+            }) => Ok(max_fee_per_gas),
+            None => Ok(provider.get_gas_price().await?),
+        }
+    }
+
+    async fn fees(&self, provider: &impl Provider) -> Result<Option<Fees>> {
+        if let Some(gas_price) = self.gas_price {
+            return Ok(Some(Fees::Legacy { gas_price }));
+        }
+        if self.legacy {
+            let gas_price = provider.get_gas_price().await?;
+            return Ok(Some(self.scale(Fees::Legacy { gas_price })));
+        }
+        if self.gas_multiplier.is_none() {
+            return Ok(None);
+        }
+        let estimate = provider.estimate_eip1559_fees().await?;
+        Ok(Some(self.scale(Fees::Eip1559 {
+            max_fee_per_gas: estimate.max_fee_per_gas,
+            max_priority_fee_per_gas: estimate.max_priority_fee_per_gas,
+        })))
+    }
+
+    /// Apply the multiplier to the estimated fees.
+    fn scale(&self, fees: Fees) -> Fees {
+        let multiplier = self.gas_multiplier.unwrap_or(1.0);
+        let scale = |fee: u128| (fee as f64 * multiplier) as u128;
+        match fees {
+            Fees::Legacy { gas_price } => Fees::Legacy {
+                gas_price: scale(gas_price),
+            },
+            Fees::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => Fees::Eip1559 {
+                max_fee_per_gas: scale(max_fee_per_gas),
+                max_priority_fee_per_gas: scale(max_priority_fee_per_gas),
+            },
+        }
+    }
+}
+
+/// Cost of a transaction at the base fee of the next block and the priority fees of recent
+/// blocks, by speed, in wei.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostTiers {
+    pub gas: u64,
+    pub slow: U256,
+    pub normal: U256,
+    pub fast: U256,
+}
+
+impl fmt::Display for CostTiers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} gas, slow {} ETH, normal {} ETH, fast {} ETH",
+            self.gas,
+            format_ether(self.slow),
+            format_ether(self.normal),
+            format_ether(self.fast)
+        )
+    }
+}
+
+/// Estimate the gas of the call, and its cost at the fees of recent blocks, from
+/// `eth_feeHistory`.
+pub async fn cost_tiers<P: Provider, D: CallDecoder>(
+    provider: &impl Provider,
+    call: &CallBuilder<P, D>,
+) -> Result<CostTiers> {
+    let gas = call
+        .estimate_gas()
+        .await
+        .context("failed to estimate the gas")?;
+    let history = provider
+        .get_fee_history(
+            FEE_HISTORY_BLOCKS,
+            BlockNumberOrTag::Latest,
+            &TIER_PERCENTILES,
+        )
+        .await
+        .context("failed to get the fee history")?;
+    let base_fee = history
+        .next_block_base_fee()
+        .context("no base fee in the fee history")?;
+    Ok(tiers(gas, base_fee, &history.reward.unwrap_or_default()))
+}
+
+/// Cost tiers of a transaction, given the priority fees of recent blocks at
+/// [TIER_PERCENTILES]. Each tier pays the median of its percentile over the blocks.
+fn tiers(gas: u64, base_fee: u128, rewards: &[Vec<u128>]) -> CostTiers {
+    let cost = |tier: usize| {
+        let mut fees: Vec<u128> = rewards
+            .iter()
+            .filter_map(|r| r.get(tier).copied())
+            .collect();
+        fees.sort_unstable();
+        let priority_fee = fees.get(fees.len() / 2).copied().unwrap_or_default();
+        U256::from(gas) * U256::from(base_fee + priority_fee)
+    };
+    CostTiers {
+        gas,
+        slow: cost(0),
+        normal: cost(1),
+        fast: cost(2),
+    }
+}
+
+fn parse_multiplier(s: &str) -> Result<f64> {
+    let multiplier: f64 = s.parse()?;
+    ensure!(
+        multiplier.is_finite() && multiplier > 0.0,
+        "the multiplier must be positive"
+    );
+    Ok(multiplier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_estimated_fees() {
+        let args = FeeArgs {
+            gas_multiplier: Some(1.5),
+            ..Default::default()
+        };
+        let fees = args.scale(Fees::Eip1559 {
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 10,
+        });
+        assert_eq!(
+            fees,
+            Fees::Eip1559 {
+                max_fee_per_gas: 150,
+                max_priority_fee_per_gas: 15,
+            }
+        );
+        let fees = FeeArgs::default().scale(Fees::Legacy { gas_price: 7 });
+        assert_eq!(fees, Fees::Legacy { gas_price: 7 });
+    }
+
+    #[test]
+    fn prices_tiers_at_the_median_of_their_percentile() {
+        let rewards = vec![vec![1, 2, 9], vec![3, 4, 5], vec![1, 6, 7]];
+        let tiers = tiers(100, 10, &rewards);
+        assert_eq!(
+            tiers,
+            CostTiers {
+                gas: 100,
+                slow: U256::from(1100),
+                normal: U256::from(1400),
+                fast: U256::from(1700),
+            }
+        );
+        assert_eq!(super::tiers(100, 10, &[]).fast, U256::from(1000));
+    }
+
+    #[test]
+    fn rejects_invalid_multipliers() {
+        assert_eq!(parse_multiplier("2").unwrap(), 2.0);
+        assert!(parse_multiplier("0").is_err());
+        assert!(parse_multiplier("-1").is_err());
+        assert!(parse_multiplier("NaN").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Confirmation depth and finality of the transactions a result depends on, behind
+//! `--confirmations` and `--finality`.
+//!
+//! A transaction in the latest block can still be dropped by a reorg, which fast chains see
+//! often. The transaction fulfilling a request, and the `set` transaction posting its result,
+//! only count once their block is `--confirmations` deep, and safe or finalized with
+//! `--finality`: the request isn't recorded as fulfilled, nor its result as posted, before. A
+//! transaction dropped by a reorg meanwhile fails the wait, and the request can be resumed.
+
+use std::time::Duration;
+
+use alloy::{eips::BlockNumberOrTag, primitives::B256, providers::Provider};
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+
+/// Interval between checks of the depth of a transaction.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Options of the depth at which transactions count.
+#[derive(Args, Clone, Debug, Default)]
+pub struct FinalityArgs {
+    /// Number of blocks, counting its own, the fulfillment of a request and the set tx must be
+    /// in the chain before they count.
+    #[clap(long, env, value_name = "BLOCKS", default_value_t = 1)]
+    confirmations: u64,
+    /// Also wait for the block of the fulfillment and of the set tx to be safe, or finalized.
+    #[clap(long, env, value_enum)]
+    finality: Option<Finality>,
+}
+
+/// Block tag a transaction must be at, or below, before it counts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Finality {
+    /// The latest safe block: on an L2, one whose data is posted to the L1.
+    Safe,
+    /// The latest finalized block: on an L2, one whose data is in a finalized block of the L1.
+    Finalized,
+}
+
+impl From<Finality> for BlockNumberOrTag {
+    fn from(finality: Finality) -> Self {
+        match finality {
+            Finality::Safe => Self::Safe,
+            Finality::Finalized => Self::Finalized,
+        }
+    }
+}
+
+impl FinalityArgs {
+    /// Whether a transaction counts as soon as it is in a block.
+    pub fn is_immediate(&self) -> bool {
+        self.confirmations <= 1 && self.finality.is_none()
+    }
+
+    /// Wait until the transaction, included in `block`, is deep enough in the chain of the
+    /// provider, and return its block. Returns right away when a transaction counts as soon as it
+    /// is in a block. Fails if a reorg dropped the transaction meanwhile.
+    pub async fn wait(
+        &self,
+        provider: &impl Provider,
+        tx_hash: B256,
+        mut block: u64,
+        what: &str,
+    ) -> Result<u64> {
+        if self.is_immediate() {
+            return Ok(block);
+        }
+        tracing::info!(
+            "Waiting for the {what} {tx_hash}, in block {block}, to be {}",
+            self.describe()
+        );
+        loop {
+            if self.reached(provider, block).await? {
+                // A reorg may have dropped the transaction, or included it in another block.
+                let Some(included) = provider
+                    .get_transaction_receipt(tx_hash)
+                    .await?
+                    .and_then(|receipt| receipt.block_number)
+                else {
+                    bail!("the {what} {tx_hash} was dropped by a reorg");
+                };
+                if included == block {
+                    return Ok(block);
+                }
+                block = included;
+                continue;
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Whether the block is deep enough in the chain.
+    async fn reached(&self, provider: &impl Provider, block: u64) -> Result<bool> {
+        let latest = provider.get_block_number().await?;
+        if depth(latest, block) < self.confirmations {
+            return Ok(false);
+        }
+        let Some(finality) = self.finality else {
+            return Ok(true);
+        };
+        let tagged = provider
+            .get_block_by_number(finality.into())
+            .await?
+            .with_context(|| format!("the RPC endpoint has no {finality:?} block"))?;
+        Ok(tagged.header.number >= block)
+    }
+
+    fn describe(&self) -> String {
+        let mut conditions = Vec::new();
+        if self.confirmations > 1 {
+            conditions.push(format!("{} blocks deep", self.confirmations));
+        }
+        match self.finality {
+            None => {}
+            Some(Finality::Safe) => conditions.push("safe".to_string()),
+            Some(Finality::Finalized) => conditions.push("finalized".to_string()),
+        }
+        conditions.join(" and ")
+    }
+}
+
+/// Number of blocks, counting its own, a block is deep in the chain.
+fn depth(latest: u64, block: u64) -> u64 {
+    (latest + 1).saturating_sub(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_confirmations() {
+        assert_eq!(depth(10, 10), 1);
+        assert_eq!(depth(12, 10), 3);
+        assert_eq!(depth(9, 10), 0);
+        assert!(FinalityArgs::default().is_immediate());
+        let finalized = FinalityArgs {
+            confirmations: 1,
+            finality: Some(Finality::Finalized),
+        };
+        assert!(!finalized.is_immediate());
+        assert_eq!(finalized.describe(), "finalized");
+        let deep = FinalityArgs {
+            confirmations: 3,
+            finality: Some(Finality::Safe),
+        };
+        assert_eq!(deep.describe(), "3 blocks deep and safe");
+    }
+}
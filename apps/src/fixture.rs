@@ -0,0 +1,120 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recording fulfilled requests as fixtures for the fork tests of the EvenNumber contract.
+
+use std::path::PathBuf;
+
+use alloy::{
+    primitives::{Address, Bytes, U256},
+    providers::Provider,
+};
+use anyhow::{Context, Result};
+use boundless_market::{Client, Deployment};
+use clap::Args;
+use guests::Journal;
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    ens::NameOrAddress,
+    failure::Failure,
+    networks::{self, NetworkArgs},
+    state::ContractState,
+    tui::parse_request_id,
+};
+
+/// Arguments of the fixture command.
+#[derive(Args, Debug)]
+pub struct FixtureArgs {
+    /// ID of the fulfilled request to record, as logged by the submit command.
+    #[clap(long, value_parser = parse_request_id)]
+    request_id: U256,
+    /// Address, or ENS name, of the EvenNumber contract, whose verifier is recorded.
+    #[clap(short, long, env)]
+    even_number_address: NameOrAddress,
+    /// Path of the fixture file to write.
+    #[clap(long, default_value = "contracts/test/fixtures/fork.json")]
+    output: PathBuf,
+    /// URL of the Ethereum RPC endpoint, or path of the IPC socket of a local node.
+    #[clap(short, long, env, value_parser = crate::transport::parse_rpc_url)]
+    rpc_url: Url,
+    /// Deployment of the Boundless contracts and services to use.
+    ///
+    /// Will be automatically resolved from the connected chain ID if unspecified.
+    #[clap(flatten, next_help_heading = "Boundless Market Deployment")]
+    deployment: Option<Deployment>,
+    #[clap(flatten, next_help_heading = "Boundless Market Deployment")]
+    network: NetworkArgs,
+}
+
+/// A fulfilled request, as read by `contracts/test/EvenNumberFork.t.sol`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Fixture {
+    chain_id: u64,
+    /// A block after the fulfillment, at which the seal can be verified.
+    block: u64,
+    verifier: Address,
+    /// The number, in decimal, as it may not fit in a JSON number.
+    number: String,
+    journal: Bytes,
+    seal: Bytes,
+}
+
+/// Write the journal and seal of a fulfilled request to a fixture file.
+pub async fn run(args: FixtureArgs) -> Result<()> {
+    let deployment = args.network.resolve(args.deployment)?;
+    let client = Client::builder()
+        .with_rpc_url(args.rpc_url)
+        .with_deployment(deployment.clone())
+        .build()
+        .await
+        .context("failed to build boundless client")?;
+    networks::ensure_chain(&client.provider(), deployment.as_ref(), &client.deployment)
+        .await
+        .context(Failure::Config)?;
+    let provider = client.provider();
+
+    let (journal, seal) = client
+        .boundless_market
+        .get_request_fulfillment(args.request_id)
+        .await
+        .context("failed to get the fulfillment of the request")?;
+    let number = Journal::decode(&journal)
+        .context("failed to decode the journal")?
+        .number;
+    let even_number_address = args.even_number_address.resolve(&provider).await?;
+    let state = ContractState::read(&provider, even_number_address).await?;
+    let fixture = Fixture {
+        chain_id: provider.get_chain_id().await?,
+        block: provider.get_block_number().await?,
+        verifier: state.verifier,
+        number: number.to_string(),
+        journal,
+        seal,
+    };
+
+    if let Some(dir) = args.output.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&args.output, serde_json::to_string_pretty(&fixture)? + "\n")
+        .with_context(|| format!("failed to write {}", args.output.display()))?;
+    tracing::info!(
+        "Recorded the fulfillment of request {:x} to {}",
+        args.request_id,
+        args.output.display()
+    );
+    Ok(())
+}
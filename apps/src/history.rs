@@ -0,0 +1,956 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local bookkeeping of the requests submitted by this publisher.
+
+use std::{collections::BTreeMap, fmt, path::PathBuf, time::Duration};
+
+use alloy::primitives::{Address, Bytes, B256, U256};
+use anyhow::{bail, Context, Result};
+use boundless_market::ProofRequest;
+use chrono::DateTime;
+use clap::Args;
+use risc0_zkvm::sha::Digest;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    fees::CostTiers,
+    preflight::Segment,
+    state_dir::{Access, StateArgs, StateLock},
+};
+
+/// How long a write waits for the writes of other invocations to the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Schema migrations, applied in order. The index of the next migration to apply is stored in
+/// the `user_version` pragma of the database.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE requests (
+        request_id TEXT PRIMARY KEY,
+        submitted_at INTEGER NOT NULL,
+        expires_at INTEGER NOT NULL,
+        number TEXT NOT NULL,
+        state TEXT NOT NULL,
+        set_tx_hash TEXT
+    );
+    CREATE TABLE tags (
+        request_id TEXT NOT NULL REFERENCES requests (request_id),
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (request_id, key)
+    );",
+    "ALTER TABLE requests ADD COLUMN max_price TEXT NOT NULL DEFAULT '0';
+    ALTER TABLE requests ADD COLUMN price TEXT;
+    ALTER TABLE requests ADD COLUMN set_gas_cost TEXT;",
+    "ALTER TABLE requests ADD COLUMN timings TEXT;",
+    "CREATE TABLE preflights (
+        image_id TEXT NOT NULL,
+        input_digest TEXT NOT NULL,
+        cycles INTEGER NOT NULL,
+        journal BLOB NOT NULL,
+        PRIMARY KEY (image_id, input_digest)
+    );",
+    "ALTER TABLE requests ADD COLUMN set_to TEXT;
+    ALTER TABLE requests ADD COLUMN set_calldata TEXT;",
+    "ALTER TABLE requests ADD COLUMN request TEXT;
+    ALTER TABLE requests ADD COLUMN from_block INTEGER;",
+    "ALTER TABLE preflights ADD COLUMN segments TEXT;",
+    "ALTER TABLE requests ADD COLUMN set_cost_estimate TEXT;",
+    "ALTER TABLE requests ADD COLUMN slash TEXT;",
+    "ALTER TABLE requests ADD COLUMN posts TEXT;",
+    "ALTER TABLE requests ADD COLUMN not_slashable INTEGER NOT NULL DEFAULT 0;",
+];
+
+/// Parse a `key=value` tag.
+pub fn parse_tag(s: &str) -> Result<(String, String)> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => bail!("tag must be in the form key=value"),
+    }
+}
+
+/// Progress of a request through the publisher flow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestState {
+    /// Submitted to the market, and waiting for fulfillment.
+    Submitted,
+    /// Fulfilled by the market, but the result is not posted on-chain yet.
+    Fulfilled,
+    /// The result was posted to the EvenNumber contract.
+    Posted,
+    /// The request expired without being fulfilled.
+    Expired,
+    /// The request expired locked, and its prover was slashed.
+    Slashed,
+}
+
+impl RequestState {
+    const ALL: [Self; 5] = [
+        Self::Submitted,
+        Self::Fulfilled,
+        Self::Posted,
+        Self::Expired,
+        Self::Slashed,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submitted => "submitted",
+            Self::Fulfilled => "fulfilled",
+            Self::Posted => "posted",
+            Self::Expired => "expired",
+            Self::Slashed => "slashed",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|state| state.as_str() == s)
+            .with_context(|| format!("unknown request state {s}"))
+    }
+}
+
+impl fmt::Display for RequestState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Time spent, in seconds, in each phase of the submit flow. Phases that were not run are `None`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Timings {
+    pub upload: Option<f64>,
+    pub preflight: Option<f64>,
+    /// Waiting for a prover to lock the request.
+    pub lock: Option<f64>,
+    /// Waiting for the request to be fulfilled once locked.
+    pub fulfillment: Option<f64>,
+    /// Confirming the transaction setting the number on the EvenNumber contract.
+    pub set_tx: Option<f64>,
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = [
+            ("upload", self.upload),
+            ("preflight", self.preflight),
+            ("wait for lock", self.lock),
+            ("wait for fulfillment", self.fulfillment),
+            ("set tx", self.set_tx),
+        ];
+        for (i, (phase, secs)) in phases.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            match secs {
+                Some(secs) => write!(f, "{phase} {secs:.1}s")?,
+                None => write!(f, "{phase} -")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of the slashing of the prover of a request that expired locked.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Slash {
+    /// Transaction slashing the prover, unless it was slashed by someone else.
+    pub tx_hash: Option<B256>,
+    /// Stake of the prover burned, in units of the stake token.
+    pub stake_burned: U256,
+    /// Stake of the prover transferred to `stake_recipient`, in units of the stake token.
+    pub stake_transferred: U256,
+    /// The market, or the prover that fulfilled the request after the lock expired.
+    pub stake_recipient: Address,
+    /// Price of the lock refunded to the client, in wei, if known.
+    pub refund: Option<U256>,
+}
+
+/// Outcome of posting the result of a request to one of the other EvenNumber contracts of its
+/// run, after the first one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Post {
+    pub target: Address,
+    /// The `set` transaction, once confirmed.
+    pub tx_hash: Option<B256>,
+    /// Gas cost of the `set` transaction, in wei.
+    pub gas_cost: Option<U256>,
+    /// Why the `set` transaction failed, if it did.
+    pub error: Option<String>,
+}
+
+/// A request as recorded in the history.
+#[derive(Debug, Serialize)]
+pub struct RequestRecord {
+    pub request_id: String,
+    pub submitted_at: u64,
+    pub expires_at: u64,
+    pub number: String,
+    pub state: String,
+    pub set_tx_hash: Option<String>,
+    /// Maximum price of the offer, in wei.
+    pub max_price: String,
+    /// Price paid for the proof, in wei.
+    pub price: Option<String>,
+    /// Gas cost of the transaction setting the number on the EvenNumber contract, in wei.
+    pub set_gas_cost: Option<String>,
+    /// Time spent in each phase of the submit flow, once it ended.
+    pub timings: Option<Timings>,
+    /// Addresses of the EvenNumber contracts the `set` transaction is sent to, comma-separated,
+    /// once the request is fulfilled.
+    pub set_to: Option<String>,
+    /// Calldata of the `set` transaction, as hex, once the request is fulfilled.
+    pub set_calldata: Option<String>,
+    /// Cost of the `set` transaction estimated before it was sent, by speed.
+    pub set_cost_estimate: Option<CostTiers>,
+    /// Outcome of the slashing of the prover, if the request expired locked.
+    pub slash: Option<Slash>,
+    /// Outcomes of posting the result to the other EvenNumber contracts of the run.
+    pub posts: Vec<Post>,
+    pub tags: BTreeMap<String, String>,
+}
+
+/// A request of the history that was submitted, but whose result was not posted yet.
+#[derive(Debug)]
+pub struct InFlightRequest {
+    pub request_id: U256,
+    pub submitted_at: u64,
+    pub number: String,
+    /// Either [RequestState::Submitted] or [RequestState::Fulfilled].
+    pub state: RequestState,
+    /// The request, unless it was recorded before requests were.
+    pub request: Option<ProofRequest>,
+    /// Block from which the events of the request are looked up.
+    pub from_block: Option<u64>,
+}
+
+/// A request of the history that expired unfulfilled, or is past its expiry, whose prover wasn't
+/// slashed yet.
+#[derive(Debug)]
+pub struct ExpiredRequest {
+    pub request_id: U256,
+    pub expires_at: u64,
+    /// The request, unless it was recorded before requests were.
+    pub request: Option<ProofRequest>,
+    /// Block from which the events of the request are looked up.
+    pub from_block: Option<u64>,
+}
+
+/// SQLite database holding the history of submitted requests.
+pub struct History {
+    conn: Connection,
+    /// Lock on the state directory, held as long as the history is open.
+    _lock: Option<StateLock>,
+}
+
+impl History {
+    /// Open the history database, creating it if needed.
+    pub fn open(path: &PathBuf) -> Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("failed to open history database {}", path.display()))?;
+        // Concurrent invocations wait for the writes of one another, rather than failing.
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // The migrations are applied in a single write transaction, so that concurrent
+        // invocations opening a new database don't apply them twice.
+        let tx =
+            rusqlite::Transaction::new_unchecked(&conn, rusqlite::TransactionBehavior::Immediate)?;
+        let version: usize = tx.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+        }
+        tx.commit()?;
+        Ok(Self { conn, _lock: None })
+    }
+
+    /// Hold the lock on the state directory until the history is dropped.
+    pub(crate) fn with_lock(self, lock: Option<StateLock>) -> Self {
+        Self {
+            _lock: lock,
+            ..self
+        }
+    }
+
+    /// Record a newly submitted request along with its tags.
+    pub fn insert(
+        &self,
+        request_id: U256,
+        submitted_at: u64,
+        expires_at: u64,
+        max_price: U256,
+        number: U256,
+        tags: &[(String, String)],
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        let id = format!("{request_id:x}");
+        tx.execute(
+            "INSERT INTO requests (request_id, submitted_at, expires_at, number, state, max_price) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                id,
+                submitted_at,
+                expires_at,
+                number.to_string(),
+                RequestState::Submitted.as_str(),
+                max_price.to_string()
+            ],
+        )?;
+        for (key, value) in tags {
+            tx.execute(
+                "INSERT OR REPLACE INTO tags (request_id, key, value) VALUES (?1, ?2, ?3)",
+                params![id, key, value],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Record the request submitted, and the block from which its events are looked up, so that
+    /// the flow can be resumed.
+    pub fn set_request(
+        &self,
+        request_id: U256,
+        request: &ProofRequest,
+        from_block: u64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET request = ?2, from_block = ?3 WHERE request_id = ?1",
+            params![
+                format!("{request_id:x}"),
+                serde_json::to_string(request)?,
+                from_block
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Whether a request with the ID was recorded.
+    pub fn contains(&self, request_id: U256) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM requests WHERE request_id = ?1)",
+            params![format!("{request_id:x}")],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Update the state of a recorded request.
+    pub fn set_state(&self, request_id: U256, state: RequestState) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET state = ?2 WHERE request_id = ?1",
+            params![format!("{request_id:x}"), state.as_str()],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a request as fulfilled, with the price paid for the proof if it is known.
+    pub fn set_fulfilled(&self, request_id: U256, price: Option<U256>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET state = ?2, price = ?3 WHERE request_id = ?1",
+            params![
+                format!("{request_id:x}"),
+                RequestState::Fulfilled.as_str(),
+                price.map(|price| price.to_string())
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Mark a request as posted to the EvenNumber contract in the given transaction.
+    pub fn set_posted(&self, request_id: U256, tx_hash: B256, gas_cost: U256) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET state = ?2, set_tx_hash = ?3, set_gas_cost = ?4 \
+             WHERE request_id = ?1",
+            params![
+                format!("{request_id:x}"),
+                RequestState::Posted.as_str(),
+                tx_hash.to_string(),
+                gas_cost.to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record the `set` transaction publishing the result of a fulfilled request to each of the
+    /// contracts, the same for all of them, so that it can be sent without the app.
+    pub fn set_calldata(&self, request_id: U256, to: &[Address], calldata: &Bytes) -> Result<()> {
+        let to: Vec<_> = to.iter().map(Address::to_string).collect();
+        self.conn.execute(
+            "UPDATE requests SET set_to = ?2, set_calldata = ?3 WHERE request_id = ?1",
+            params![
+                format!("{request_id:x}"),
+                to.join(","),
+                calldata.to_string()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record the cost of the `set` transaction of a request estimated before sending it.
+    pub fn set_cost_estimate(&self, request_id: U256, estimate: &CostTiers) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET set_cost_estimate = ?2 WHERE request_id = ?1",
+            params![format!("{request_id:x}"), serde_json::to_string(estimate)?],
+        )?;
+        Ok(())
+    }
+
+    /// Record the outcome of the slashing of the prover of a request. A request that expired
+    /// unfulfilled is marked as slashed, while one fulfilled late by another prover keeps its
+    /// state.
+    pub fn set_slashed(&self, request_id: U256, slash: &Slash) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET slash = ?3, \
+             state = CASE WHEN state IN (?4, ?5) THEN ?2 ELSE state END WHERE request_id = ?1",
+            params![
+                format!("{request_id:x}"),
+                RequestState::Slashed.as_str(),
+                serde_json::to_string(slash)?,
+                RequestState::Submitted.as_str(),
+                RequestState::Expired.as_str()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record that a request past its expiry has no stake to slash, as no prover locked it or its
+    /// locker fulfilled it, so that it isn't checked again.
+    pub fn set_not_slashable(&self, request_id: U256) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET not_slashable = 1 WHERE request_id = ?1",
+            params![format!("{request_id:x}")],
+        )?;
+        Ok(())
+    }
+
+    /// Record the outcomes of posting the result of a request to the other EvenNumber contracts
+    /// of its run.
+    pub fn set_posts(&self, request_id: U256, posts: &[Post]) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET posts = ?2 WHERE request_id = ?1",
+            params![format!("{request_id:x}"), serde_json::to_string(posts)?],
+        )?;
+        Ok(())
+    }
+
+    /// Record the time spent in each phase of the submit flow of a request.
+    pub fn set_timings(&self, request_id: U256, timings: &Timings) -> Result<()> {
+        self.conn.execute(
+            "UPDATE requests SET timings = ?2 WHERE request_id = ?1",
+            params![format!("{request_id:x}"), serde_json::to_string(timings)?],
+        )?;
+        Ok(())
+    }
+
+    /// Cycle count and journal of a previous execution of the program on the input, if any.
+    pub fn cached_preflight(
+        &self,
+        image_id: &Digest,
+        input_digest: &Digest,
+    ) -> Result<Option<(u64, Vec<u8>)>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT cycles, journal FROM preflights WHERE image_id = ?1 AND input_digest = ?2",
+                params![image_id.to_string(), input_digest.to_string()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?)
+    }
+
+    /// Record the cycle count and journal of an execution of the program on the input.
+    pub fn cache_preflight(
+        &self,
+        image_id: &Digest,
+        input_digest: &Digest,
+        cycles: u64,
+        journal: &[u8],
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO preflights (image_id, input_digest, cycles, journal) \
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                image_id.to_string(),
+                input_digest.to_string(),
+                cycles,
+                journal
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Segments of a previous execution of the program on the input, unless it was cached before
+    /// segments were.
+    pub fn cached_segments(
+        &self,
+        image_id: &Digest,
+        input_digest: &Digest,
+    ) -> Result<Option<Vec<Segment>>> {
+        let segments: Option<Option<String>> = self
+            .conn
+            .query_row(
+                "SELECT segments FROM preflights WHERE image_id = ?1 AND input_digest = ?2",
+                params![image_id.to_string(), input_digest.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        segments
+            .flatten()
+            .map(|segments| serde_json::from_str(&segments).context("invalid cached segments"))
+            .transpose()
+    }
+
+    /// Record the segments of an execution of the program on the input, once it is cached.
+    pub fn cache_segments(
+        &self,
+        image_id: &Digest,
+        input_digest: &Digest,
+        segments: &[Segment],
+    ) -> Result<()> {
+        self.conn.execute(
+            "UPDATE preflights SET segments = ?3 WHERE image_id = ?1 AND input_digest = ?2",
+            params![
+                image_id.to_string(),
+                input_digest.to_string(),
+                serde_json::to_string(segments)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// List the most recent requests that have all the given tags.
+    pub fn list(&self, tags: &[(String, String)], limit: u32) -> Result<Vec<RequestRecord>> {
+        Ok(self
+            .submitted_between(0, i64::MAX)?
+            .into_iter()
+            .filter(|record| {
+                tags.iter()
+                    .all(|(key, value)| record.tags.get(key) == Some(value))
+            })
+            .take(limit as usize)
+            .collect())
+    }
+
+    /// List the requests waiting for their fulfillment or for their result to be posted, oldest
+    /// first.
+    pub fn in_flight(&self) -> Result<Vec<InFlightRequest>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT request_id, submitted_at, number, state, request, from_block FROM requests \
+             WHERE state IN (?1, ?2) ORDER BY submitted_at",
+        )?;
+        let mut rows = stmt.query([
+            RequestState::Submitted.as_str(),
+            RequestState::Fulfilled.as_str(),
+        ])?;
+        let mut requests = Vec::new();
+        while let Some(row) = rows.next()? {
+            let request_id: String = row.get(0)?;
+            let state: String = row.get(3)?;
+            let request: Option<String> = row.get(4)?;
+            requests.push(InFlightRequest {
+                request_id: U256::from_str_radix(&request_id, 16)
+                    .with_context(|| format!("invalid request ID {request_id}"))?,
+                submitted_at: row.get(1)?,
+                number: row.get(2)?,
+                state: RequestState::parse(&state)?,
+                request: request
+                    .map(|request| serde_json::from_str(&request))
+                    .transpose()?,
+                from_block: row.get(5)?,
+            });
+        }
+        Ok(requests)
+    }
+
+    /// List the requests that expired unfulfilled, or are past their expiry at `now`, fulfilled
+    /// or not, whose prover wasn't slashed yet and that may have a stake to slash, oldest first.
+    pub fn expired(&self, now: u64) -> Result<Vec<ExpiredRequest>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT request_id, expires_at, request, from_block FROM requests \
+             WHERE slash IS NULL AND NOT not_slashable AND (state = ?1 OR expires_at <= ?2) \
+             ORDER BY submitted_at",
+        )?;
+        let mut rows = stmt.query(params![RequestState::Expired.as_str(), now])?;
+        let mut requests = Vec::new();
+        while let Some(row) = rows.next()? {
+            let request_id: String = row.get(0)?;
+            let request: Option<String> = row.get(2)?;
+            requests.push(ExpiredRequest {
+                request_id: U256::from_str_radix(&request_id, 16)
+                    .with_context(|| format!("invalid request ID {request_id}"))?,
+                expires_at: row.get(1)?,
+                request: request
+                    .map(|request| serde_json::from_str(&request))
+                    .transpose()?,
+                from_block: row.get(3)?,
+            });
+        }
+        Ok(requests)
+    }
+
+    /// List the requests submitted in the given range of UNIX timestamps, most recent first.
+    pub fn submitted_between(&self, since: i64, until: i64) -> Result<Vec<RequestRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT request_id, submitted_at, expires_at, number, state, set_tx_hash, \
+             max_price, price, set_gas_cost, timings, set_to, set_calldata, \
+             set_cost_estimate, slash, posts FROM requests WHERE submitted_at >= ?1 AND submitted_at < ?2 \
+             ORDER BY submitted_at DESC",
+        )?;
+        let mut tag_stmt = self
+            .conn
+            .prepare("SELECT key, value FROM tags WHERE request_id = ?1")?;
+        let mut records = Vec::new();
+        let mut rows = stmt.query([since, until])?;
+        while let Some(row) = rows.next()? {
+            let request_id: String = row.get(0)?;
+            let timings: Option<String> = row.get(9)?;
+            let set_cost_estimate: Option<String> = row.get(12)?;
+            let slash: Option<String> = row.get(13)?;
+            let posts: Option<String> = row.get(14)?;
+            let tags = tag_stmt
+                .query_map([&request_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()?;
+            records.push(RequestRecord {
+                request_id,
+                submitted_at: row.get(1)?,
+                expires_at: row.get(2)?,
+                number: row.get(3)?,
+                state: row.get(4)?,
+                set_tx_hash: row.get(5)?,
+                max_price: row.get(6)?,
+                price: row.get(7)?,
+                set_gas_cost: row.get(8)?,
+                timings: timings
+                    .map(|timings| serde_json::from_str(&timings))
+                    .transpose()?,
+                set_to: row.get(10)?,
+                set_calldata: row.get(11)?,
+                set_cost_estimate: set_cost_estimate
+                    .map(|estimate| serde_json::from_str(&estimate))
+                    .transpose()?,
+                slash: slash
+                    .map(|slash| serde_json::from_str(&slash))
+                    .transpose()?,
+                posts: posts
+                    .map(|posts| serde_json::from_str(&posts))
+                    .transpose()?
+                    .unwrap_or_default(),
+                tags,
+            });
+        }
+        Ok(records)
+    }
+}
+
+/// Arguments of the history command.
+#[derive(Args, Debug)]
+pub struct HistoryArgs {
+    /// Only list requests with the given `key=value` tag. Can be repeated.
+    #[clap(long = "tag", value_parser = parse_tag)]
+    tags: Vec<(String, String)>,
+    /// Maximum number of requests to list.
+    #[clap(long, default_value_t = 20)]
+    limit: u32,
+    /// Print one JSON object per request, for export to other tools.
+    #[clap(long)]
+    json: bool,
+    #[clap(flatten)]
+    state: StateArgs,
+}
+
+pub fn run(args: HistoryArgs) -> Result<()> {
+    let history = args.state.open(Access::Read)?;
+    for record in history.list(&args.tags, args.limit)? {
+        if args.json {
+            println!("{}", serde_json::to_string(&record)?);
+            continue;
+        }
+        let submitted_at = DateTime::from_timestamp(record.submitted_at as i64, 0)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        let tags: Vec<_> = record
+            .tags
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect();
+        println!(
+            "{}\t{submitted_at}\t{}\t{}\t{}",
+            record.request_id,
+            record.state,
+            record.number,
+            tags.join(",")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_tags() {
+        let history = History::open(&PathBuf::from(":memory:")).unwrap();
+        let tags = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        history
+            .insert(
+                U256::from(1),
+                10,
+                100,
+                U256::from(50),
+                U256::from(2),
+                &tags(&[("env", "prod"), ("job", "a")]),
+            )
+            .unwrap();
+        history
+            .insert(
+                U256::from(2),
+                20,
+                100,
+                U256::from(50),
+                U256::from(4),
+                &tags(&[("env", "dev")]),
+            )
+            .unwrap();
+        history
+            .set_state(U256::from(2), RequestState::Expired)
+            .unwrap();
+
+        let all = history.list(&[], 10).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].state, "expired");
+        assert_eq!(all[1].expires_at, 100);
+
+        let prod = history.list(&tags(&[("env", "prod")]), 10).unwrap();
+        assert_eq!(prod.len(), 1);
+        assert_eq!(prod[0].request_id, "1");
+        assert!(history
+            .list(&tags(&[("env", "prod"), ("job", "b")]), 10)
+            .unwrap()
+            .is_empty());
+        assert_eq!(history.submitted_between(15, 25).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn records_timings() {
+        let history = History::open(&PathBuf::from(":memory:")).unwrap();
+        history
+            .insert(U256::from(1), 10, 100, U256::ZERO, U256::from(2), &[])
+            .unwrap();
+        assert!(history.list(&[], 1).unwrap()[0].timings.is_none());
+
+        let timings = Timings {
+            preflight: Some(2.0),
+            lock: Some(12.5),
+            ..Default::default()
+        };
+        history.set_timings(U256::from(1), &timings).unwrap();
+        let recorded = history.list(&[], 1).unwrap().remove(0).timings.unwrap();
+        assert_eq!(recorded.lock, Some(12.5));
+        assert_eq!(
+            recorded.to_string(),
+            "upload -, preflight 2.0s, wait for lock 12.5s, wait for fulfillment -, set tx -"
+        );
+    }
+
+    #[test]
+    fn records_calldata() {
+        let history = History::open(&PathBuf::from(":memory:")).unwrap();
+        history
+            .insert(U256::from(1), 10, 100, U256::ZERO, U256::from(2), &[])
+            .unwrap();
+        let to = [Address::repeat_byte(0xee), Address::repeat_byte(0xff)];
+        history
+            .set_calldata(U256::from(1), &to, &Bytes::from_static(&[0xab, 0xcd]))
+            .unwrap();
+        let record = history.list(&[], 1).unwrap().remove(0);
+        assert_eq!(record.set_to, Some(format!("{},{}", to[0], to[1])));
+        assert_eq!(record.set_calldata.as_deref(), Some("0xabcd"));
+
+        let estimate = CostTiers {
+            gas: 300_000,
+            slow: U256::from(1),
+            normal: U256::from(2),
+            fast: U256::from(3),
+        };
+        history.set_cost_estimate(U256::from(1), &estimate).unwrap();
+        let record = history.list(&[], 1).unwrap().remove(0);
+        assert_eq!(record.set_cost_estimate, Some(estimate));
+        assert!(record.posts.is_empty());
+
+        let posts = vec![
+            Post {
+                target: Address::repeat_byte(0xaa),
+                tx_hash: Some(B256::repeat_byte(1)),
+                gas_cost: Some(U256::from(7)),
+                error: None,
+            },
+            Post {
+                target: Address::repeat_byte(0xbb),
+                tx_hash: None,
+                gas_cost: None,
+                error: Some("tx reverted".to_string()),
+            },
+        ];
+        history.set_posts(U256::from(1), &posts).unwrap();
+        assert_eq!(history.list(&[], 1).unwrap().remove(0).posts, posts);
+    }
+
+    #[test]
+    fn lists_requests_in_flight() {
+        use boundless_market::{Offer, RequestId};
+
+        let history = History::open(&PathBuf::from(":memory:")).unwrap();
+        for (id, submitted_at) in [(1, 30), (2, 10), (3, 20)] {
+            history
+                .insert(
+                    U256::from(id),
+                    submitted_at,
+                    100,
+                    U256::ZERO,
+                    U256::from(2),
+                    &[],
+                )
+                .unwrap();
+        }
+        let request = ProofRequest {
+            id: RequestId::new(Address::repeat_byte(1), 2).into(),
+            ..crate::wait::tests::new_request(Offer::default())
+        };
+        history.set_request(U256::from(2), &request, 42).unwrap();
+        assert!(history.contains(U256::from(2)).unwrap());
+        assert!(!history.contains(U256::from(4)).unwrap());
+        history.set_fulfilled(U256::from(3), None).unwrap();
+        history
+            .set_posted(U256::from(1), B256::ZERO, U256::ZERO)
+            .unwrap();
+
+        let in_flight = history.in_flight().unwrap();
+        assert_eq!(in_flight.len(), 2);
+        assert_eq!(in_flight[0].request_id, U256::from(2));
+        assert_eq!(in_flight[0].state, RequestState::Submitted);
+        assert_eq!(in_flight[0].request.as_ref(), Some(&request));
+        assert_eq!(in_flight[0].from_block, Some(42));
+        assert_eq!(in_flight[1].state, RequestState::Fulfilled);
+        assert!(in_flight[1].request.is_none());
+    }
+
+    #[test]
+    fn records_slashes() {
+        let history = History::open(&PathBuf::from(":memory:")).unwrap();
+        for (id, expires_at) in [(1u64, 100), (2, 200), (3, 300)] {
+            history
+                .insert(
+                    U256::from(id),
+                    id * 10,
+                    expires_at,
+                    U256::ZERO,
+                    U256::from(2),
+                    &[],
+                )
+                .unwrap();
+        }
+        history
+            .set_state(U256::from(1), RequestState::Expired)
+            .unwrap();
+        let expired = |now| -> Vec<_> {
+            history
+                .expired(now)
+                .unwrap()
+                .into_iter()
+                .map(|request| request.request_id)
+                .collect()
+        };
+        assert_eq!(expired(150), [U256::from(1)]);
+        assert_eq!(expired(250), [U256::from(1), U256::from(2)]);
+
+        let slash = Slash {
+            tx_hash: Some(B256::repeat_byte(1)),
+            stake_burned: U256::from(3),
+            stake_transferred: U256::from(1),
+            stake_recipient: Address::repeat_byte(2),
+            refund: Some(U256::from(50)),
+        };
+        history.set_slashed(U256::from(1), &slash).unwrap();
+        assert_eq!(expired(250), [U256::from(2)]);
+        let record = history.list(&[], 3).unwrap().pop().unwrap();
+        assert_eq!(record.state, "slashed");
+        assert_eq!(record.slash, Some(slash.clone()));
+
+        // A request fulfilled late by another prover can still have its locker slashed.
+        history.set_fulfilled(U256::from(3), None).unwrap();
+        assert_eq!(expired(350), [U256::from(2), U256::from(3)]);
+        history.set_slashed(U256::from(3), &slash).unwrap();
+        assert_eq!(expired(350), [U256::from(2)]);
+        let record = history.list(&[], 3).unwrap().remove(0);
+        assert_eq!(record.state, "fulfilled");
+        assert_eq!(record.slash, Some(slash));
+
+        // A request without a stake to slash is checked once.
+        history.set_not_slashable(U256::from(2)).unwrap();
+        assert!(expired(350).is_empty());
+    }
+
+    #[test]
+    fn caches_preflights() {
+        let history = History::open(&PathBuf::from(":memory:")).unwrap();
+        let (image_id, input) = (Digest::from([1u32; 8]), Digest::from([2u32; 8]));
+        assert!(history
+            .cached_preflight(&image_id, &input)
+            .unwrap()
+            .is_none());
+
+        history
+            .cache_preflight(&image_id, &input, 1 << 20, b"journal")
+            .unwrap();
+        assert_eq!(
+            history.cached_preflight(&image_id, &input).unwrap(),
+            Some((1 << 20, b"journal".to_vec()))
+        );
+        assert!(history
+            .cached_preflight(&Digest::from([3u32; 8]), &input)
+            .unwrap()
+            .is_none());
+
+        assert_eq!(history.cached_segments(&image_id, &input).unwrap(), None);
+        let segments = [Segment {
+            po2: 20,
+            user_cycles: 700_000,
+        }];
+        history
+            .cache_segments(&image_id, &input, &segments)
+            .unwrap();
+        assert_eq!(
+            history.cached_segments(&image_id, &input).unwrap(),
+            Some(segments.to_vec())
+        );
+    }
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!(
+            parse_tag("team=infra").unwrap(),
+            ("team".into(), "infra".into())
+        );
+        assert_eq!(parse_tag("url=a=b").unwrap(), ("url".into(), "a=b".into()));
+        assert!(parse_tag("novalue").is_err());
+        assert!(parse_tag("=value").is_err());
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Calls to the storage provider and the order stream through the HTTP(S) proxy of `--proxy`.
+//!
+//! The HTTP clients of the app and of the Boundless SDK already honor `HTTPS_PROXY`, `HTTP_PROXY`
+//! and `NO_PROXY`. An explicit proxy is given to the order stream client, which the app builds,
+//! while the Pinata client, built by the SDK, is pointed at a local proxy forwarding its calls
+//! through it. The S3 client has no proxy support.
+
+use anyhow::{Context, Result};
+use http_body_util::BodyExt;
+use url::Url;
+
+use crate::proxy;
+
+/// Builder of an HTTP client sending its calls through `proxy`, if any, and else through the
+/// proxies of the environment.
+pub fn builder(proxy: Option<&Url>) -> Result<reqwest::ClientBuilder> {
+    let builder = reqwest::Client::builder();
+    match proxy {
+        Some(proxy) => {
+            let proxy = reqwest::Proxy::all(proxy.as_str()).context("invalid proxy URL")?;
+            Ok(builder.proxy(proxy))
+        }
+        None => Ok(builder),
+    }
+}
+
+/// HTTP client sending its calls through `proxy`, like [builder].
+pub fn client(proxy: Option<&Url>) -> Result<reqwest::Client> {
+    builder(proxy)?
+        .build()
+        .context("failed to build the HTTP client")
+}
+
+/// Start a local proxy forwarding the calls to the origin of `upstream` through `proxy`.
+///
+/// Returns the URL to use in place of `upstream`: the same URL, on the local proxy.
+pub async fn forward(name: &'static str, upstream: Url, proxy: &Url) -> Result<Url> {
+    let client = client(Some(proxy))?;
+    let origin = upstream.clone();
+    let url = proxy::serve(name, upstream, move |request| {
+        let (client, origin) = (client.clone(), origin.clone());
+        async move {
+            let (parts, body) = request.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map(|body| body.to_bytes())
+                .unwrap_or_default();
+            Ok(proxy::respond(
+                proxy::forward(&client, origin, parts, body).await,
+            ))
+        }
+    })
+    .await?;
+    tracing::debug!("Sending {name} calls through the proxy");
+    Ok(url)
+}
@@ -0,0 +1,99 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Raw inputs of the guest, given as a file, hex, or on stdin.
+//!
+//! The bytes are written as they are to the stdin of the guest, so that a guest with a richer
+//! input than a number can be driven without changing the arguments of the CLI.
+
+use std::{io::Read, path::PathBuf};
+
+use alloy::primitives::Bytes;
+use anyhow::{Context, Result};
+use clap::Args;
+
+/// Arguments giving the raw input of the guest. At most one of them can be given.
+#[derive(Args, Clone, Debug, Default)]
+#[group(multiple = false)]
+pub struct InputArgs {
+    /// File holding the raw input of the guest.
+    #[clap(long, value_name = "PATH")]
+    input_file: Option<PathBuf>,
+    /// Raw input of the guest, as hex.
+    #[clap(long, value_name = "HEX")]
+    input_hex: Option<Bytes>,
+    /// Read the raw input of the guest from stdin.
+    #[clap(long)]
+    input_stdin: bool,
+}
+
+impl InputArgs {
+    /// The raw input given by the arguments, if any.
+    pub fn read(&self) -> Result<Option<Vec<u8>>> {
+        if let Some(path) = &self.input_file {
+            let input = std::fs::read(path)
+                .with_context(|| format!("failed to read the input file {}", path.display()))?;
+            return Ok(Some(input));
+        }
+        if let Some(input) = &self.input_hex {
+            return Ok(Some(input.to_vec()));
+        }
+        if self.input_stdin {
+            let mut input = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut input)
+                .context("failed to read the input from stdin")?;
+            return Ok(Some(input));
+        }
+        Ok(None)
+    }
+
+    /// Whether a raw input is given.
+    pub fn is_given(&self) -> bool {
+        self.input_file.is_some() || self.input_hex.is_some() || self.input_stdin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser, Debug)]
+    struct Cli {
+        #[clap(flatten)]
+        input: InputArgs,
+    }
+
+    #[test]
+    fn reads_file_and_hex_inputs() {
+        let cli = Cli::try_parse_from(["app", "--input-hex", "0x0102ff"]).unwrap();
+        assert_eq!(cli.input.read().unwrap(), Some(vec![1, 2, 255]));
+        let cli = Cli::try_parse_from(["app", "--input-hex", "0102"]).unwrap();
+        assert_eq!(cli.input.read().unwrap(), Some(vec![1, 2]));
+
+        let path = std::env::temp_dir().join(format!("input-{}", std::process::id()));
+        std::fs::write(&path, b"input").unwrap();
+        let cli = Cli::try_parse_from(["app", "--input-file", path.to_str().unwrap()]).unwrap();
+        assert_eq!(cli.input.read().unwrap(), Some(b"input".to_vec()));
+        std::fs::remove_file(path).unwrap();
+
+        let cli = Cli::try_parse_from(["app"]).unwrap();
+        assert!(!cli.input.is_given());
+        assert_eq!(cli.input.read().unwrap(), None);
+        assert!(Cli::try_parse_from(["app", "--input-hex", "0x01", "--input-stdin"]).is_err());
+        assert!(Cli::try_parse_from(["app", "--input-hex", "zz"]).is_err());
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cross-check of the journal of a fulfilled request, before its seal is posted.
+//!
+//! The EvenNumber contract is called with the number submitted, and only accepts a seal of a
+//! journal encoding it. A prover fulfilling the request with another journal would then make
+//! the `set` transaction revert, after paying its gas, with an error that doesn't say why. The
+//! journal is decoded and compared with the number, the predicate of the request, and the input
+//! of the request when it is inline, so that a mismatch fails before sending anything.
+
+use alloy::primitives::U256;
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{contracts::RequestInputType, GuestEnv, ProofRequest};
+use guests::Journal;
+
+use crate::failure::Failure;
+
+/// Check that the journal of the fulfilled request encodes exactly `number`, and that the
+/// request was for that number. The request is `None` for requests recorded without it, whose
+/// journal is only compared with the number.
+pub fn check(request: Option<&ProofRequest>, journal: &[u8], number: u32) -> Result<()> {
+    check_journal(request, journal, number)
+        .with_context(|| {
+            format!(
+                "the fulfilled journal 0x{} doesn't match the request for {number}",
+                alloy::hex::encode(journal)
+            )
+        })
+        .context(Failure::JournalMismatch)
+}
+
+fn check_journal(request: Option<&ProofRequest>, journal: &[u8], number: u32) -> Result<()> {
+    let expected = Journal::from(U256::from(number)).encode();
+    if journal != expected.as_slice() {
+        let decoded = Journal::decode(journal)
+            .context("the journal does not encode a number")?
+            .number;
+        return Err(anyhow!("the journal encodes {decoded} instead of {number}"));
+    }
+    let Some(request) = request else {
+        return Ok(());
+    };
+    if !request.requirements.predicate.eval(journal) {
+        return Err(anyhow!(
+            "the journal does not satisfy the predicate of the request"
+        ));
+    }
+    match request.input.inputType {
+        RequestInputType::Inline => {
+            let env = GuestEnv::decode(&request.input.data)
+                .context("the inline input of the request can't be decoded")?;
+            if env.stdin != guests::encode_input(U256::from(number)) {
+                return Err(anyhow!(
+                    "the input of the request is not the number {number}"
+                ));
+            }
+        }
+        _ => tracing::debug!(
+            "The input of request {:x} is not inline, it is not compared with the number",
+            request.id
+        ),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use boundless_market::{contracts::Predicate, Offer, RequestInput, Requirements};
+    use guests::IS_EVEN_ID;
+    use risc0_zkvm::sha::{Impl, Sha256};
+
+    use super::*;
+
+    fn new_request(number: u32, input: u32) -> ProofRequest {
+        let journal = Journal::from(U256::from(number)).encode();
+        let input = GuestEnv::from_stdin(guests::encode_input(U256::from(input)));
+        let mut request = crate::wait::tests::new_request(Offer::default());
+        request.requirements = Requirements::new(
+            IS_EVEN_ID,
+            Predicate::digest_match(*Impl::hash_bytes(&journal)),
+        );
+        request.input = RequestInput::inline(input.encode().unwrap());
+        request
+    }
+
+    #[test]
+    fn rejects_journals_of_other_numbers() {
+        let journal = Journal::from(U256::from(4)).encode();
+        assert!(check(None, &journal, 4).is_ok());
+        let error = check(None, &journal, 6).unwrap_err();
+        assert_eq!(Failure::of(&error), Some(Failure::JournalMismatch));
+        assert!(format!("{error:#}").contains("encodes 4 instead of 6"));
+        let error = check(None, b"not a journal", 4).unwrap_err();
+        assert!(format!("{error:#}").contains("does not encode a number"));
+    }
+
+    #[test]
+    fn compares_the_journal_with_the_request() {
+        let journal = Journal::from(U256::from(4)).encode();
+        assert!(check(Some(&new_request(4, 4)), &journal, 4).is_ok());
+        let error = check(Some(&new_request(6, 4)), &journal, 4).unwrap_err();
+        assert!(format!("{error:#}").contains("predicate"));
+        let error = check(Some(&new_request(4, 6)), &journal, 4).unwrap_err();
+        assert!(format!("{error:#}").contains("input of the request"));
+    }
+}
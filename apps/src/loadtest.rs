@@ -0,0 +1,241 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Load generation against the Boundless Market, used to size offers before going live.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use alloy::{primitives::U256, providers::DynProvider};
+use anyhow::{ensure, Context, Result};
+use boundless_market::{contracts::RequestStatus, BoundlessMarketService, ProofRequest};
+use clap::Args;
+use guests::IS_EVEN_ELF;
+use tokio::task::JoinSet;
+use url::Url;
+
+use crate::{
+    usd::{format_eth, EthUsd, PriceArgs},
+    ClientArgs,
+};
+
+/// Arguments of the loadtest command.
+#[derive(Args, Debug)]
+pub struct LoadtestArgs {
+    /// Number of requests to submit.
+    #[clap(long, default_value_t = 10)]
+    count: u32,
+    /// Submission rate, in requests per minute.
+    #[clap(long, default_value_t = 6.0)]
+    rate: f64,
+    /// Interval, in seconds, between status checks of each submitted request.
+    ///
+    /// This is also the resolution of the reported latencies.
+    #[clap(long, default_value_t = 5)]
+    poll_interval: u64,
+    /// URL where provers can download the program to be proven.
+    #[clap(long, env)]
+    program_url: Option<Url>,
+    #[clap(flatten)]
+    client: ClientArgs,
+    #[clap(flatten, next_help_heading = "USD Prices")]
+    prices: PriceArgs,
+}
+
+/// What happened to a single submitted request.
+#[derive(Debug, Default)]
+struct Outcome {
+    locked_after: Option<Duration>,
+    fulfilled_after: Option<Duration>,
+    price: Option<U256>,
+}
+
+pub async fn run(args: LoadtestArgs) -> Result<()> {
+    let period = submission_period(args.rate)?;
+    let client = args.client.build_client().await?;
+    let poll_interval = Duration::from_secs(args.poll_interval);
+
+    // Upload the program once, rather than once per request.
+    let program_url = match args.program_url {
+        Some(program_url) => program_url,
+        None => client
+            .upload_program(IS_EVEN_ELF)
+            .await
+            .context("failed to upload program")?,
+    };
+
+    // Derive the synthetic inputs from the current time so that runs don't repeat requests.
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut ticker = tokio::time::interval(period);
+    let mut monitors = JoinSet::new();
+    let mut failed_submissions = 0;
+    for i in 0..args.count {
+        ticker.tick().await;
+        let number = U256::from((seed + u64::from(i)) * 2);
+        let request = client
+            .new_request()
+            .with_program_url(program_url.clone())?
+            .with_stdin(guests::encode_input(number));
+
+        let request = match client.build_request(request).await {
+            Ok(request) => request,
+            Err(e) => {
+                tracing::error!("Failed to build request {}/{}: {e}", i + 1, args.count);
+                failed_submissions += 1;
+                continue;
+            }
+        };
+        let submitted_at = Instant::now();
+        if let Err(e) = client.submit_request_onchain(&request).await {
+            tracing::error!("Failed to submit request {}/{}: {e}", i + 1, args.count);
+            failed_submissions += 1;
+            continue;
+        }
+        tracing::info!(
+            "Submitted request {}/{}: {:x}",
+            i + 1,
+            args.count,
+            request.id
+        );
+        monitors.spawn(monitor(
+            client.boundless_market.clone(),
+            request,
+            submitted_at,
+            poll_interval,
+        ));
+    }
+
+    let mut outcomes = Vec::new();
+    while let Some(outcome) = monitors.join_next().await {
+        outcomes.push(outcome?);
+    }
+    let eth_usd = args.prices.try_eth_usd(Some(&args.client.rpc_url)).await;
+    print_summary(args.count, failed_submissions, &outcomes, eth_usd)
+}
+
+/// Interval between the submissions at `rate` requests per minute, which must be at least 1ms.
+fn submission_period(rate: f64) -> Result<Duration> {
+    ensure!(
+        rate.is_finite() && rate > 0.0,
+        "the rate must be a positive number of requests per minute"
+    );
+    let period = Duration::try_from_secs_f64(60.0 / rate).unwrap_or_default();
+    ensure!(
+        period >= Duration::from_millis(1),
+        "the rate must be at most 60000 requests per minute"
+    );
+    Ok(period)
+}
+
+/// Poll the status of a request until it is fulfilled or expires.
+async fn monitor(
+    market: BoundlessMarketService<DynProvider>,
+    request: ProofRequest,
+    submitted_at: Instant,
+    poll_interval: Duration,
+) -> Outcome {
+    let mut outcome = Outcome::default();
+    loop {
+        let status = match market
+            .get_status(request.id, Some(request.expires_at()))
+            .await
+        {
+            Ok(status) => status,
+            Err(e) => {
+                tracing::warn!("Failed to get status of request {:x}: {e}", request.id);
+                tokio::time::sleep(poll_interval).await;
+                continue;
+            }
+        };
+        // The price paid is the auction price at the time the request was locked.
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        match status {
+            RequestStatus::Locked if outcome.locked_after.is_none() => {
+                outcome.locked_after = Some(submitted_at.elapsed());
+                outcome.price = request.offer.price_at(now).ok();
+            }
+            RequestStatus::Fulfilled => {
+                outcome.locked_after.get_or_insert(submitted_at.elapsed());
+                outcome.fulfilled_after = Some(submitted_at.elapsed());
+                if outcome.price.is_none() {
+                    outcome.price = request.offer.price_at(now).ok();
+                }
+                tracing::info!("Request {:x} fulfilled", request.id);
+                return outcome;
+            }
+            RequestStatus::Expired => {
+                tracing::info!("Request {:x} expired", request.id);
+                return outcome;
+            }
+            _ => {}
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+fn print_summary(
+    count: u32,
+    failed_submissions: u32,
+    outcomes: &[Outcome],
+    eth_usd: Option<EthUsd>,
+) -> Result<()> {
+    let fulfilled: Vec<_> = outcomes
+        .iter()
+        .filter(|o| o.fulfilled_after.is_some())
+        .collect();
+    let lock_times: Vec<_> = outcomes.iter().filter_map(|o| o.locked_after).collect();
+    let fulfill_times: Vec<_> = fulfilled.iter().filter_map(|o| o.fulfilled_after).collect();
+    let total_cost: U256 = fulfilled.iter().filter_map(|o| o.price).sum();
+
+    println!("Requests:            {count} ({failed_submissions} failed to submit)");
+    println!("Fulfilled:           {}", fulfilled.len());
+    println!("Expired:             {}", outcomes.len() - fulfilled.len());
+    println!("Time to lock:        {}", format_latencies(lock_times));
+    println!("Time to fulfillment: {}", format_latencies(fulfill_times));
+    println!("Total cost:          {}", format_eth(total_cost, eth_usd)?);
+    Ok(())
+}
+
+fn format_latencies(mut latencies: Vec<Duration>) -> String {
+    if latencies.is_empty() {
+        return "n/a".to_string();
+    }
+    latencies.sort();
+    let percentile = |p: f64| latencies[((latencies.len() - 1) as f64 * p).round() as usize];
+    format!(
+        "p50 {:.0?}  p90 {:.0?}  max {:.0?}",
+        percentile(0.5),
+        percentile(0.9),
+        latencies[latencies.len() - 1]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spaces_submissions_by_at_least_a_millisecond() {
+        assert_eq!(submission_period(6.0).unwrap(), Duration::from_secs(10));
+        assert_eq!(
+            submission_period(60_000.0).unwrap(),
+            Duration::from_millis(1)
+        );
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY, 60_001.0, 1e300] {
+            assert!(submission_period(rate).is_err(), "{rate}");
+        }
+    }
+}
@@ -0,0 +1,64 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proving a request locally when the market doesn't fulfill it, behind `--fallback-local-prove`.
+//!
+//! The guest is proven with the default prover of the zkVM: the `r0vm` installed, which proves on
+//! the GPU if it was built with CUDA or Metal support, or Bonsai if `BONSAI_API_KEY` and
+//! `BONSAI_API_URL` are set. The receipt is compressed to a Groth16 seal, which the verifier
+//! router of the EvenNumber contract accepts like a seal from the market. Compressing to Groth16
+//! locally needs Docker, on x86.
+
+use std::rc::Rc;
+
+use alloy::primitives::Bytes;
+use anyhow::{ensure, Context, Result};
+use guests::{IS_EVEN_ELF, IS_EVEN_ID};
+use risc0_ethereum_contracts::encode_seal;
+use risc0_zkvm::{default_prover, ExecutorEnv, Prover, ProverOpts, VerifierContext};
+
+/// Prove the guest on the input, and return the seal of the proof for the EvenNumber contract.
+pub async fn prove(input: Vec<u8>, journal: &[u8]) -> Result<Bytes> {
+    prove_with(default_prover, input, journal).await
+}
+
+/// Prove the guest on the input with the given prover, checking that the proof commits the
+/// journal, and return the seal of the proof for the EvenNumber contract.
+pub async fn prove_with(
+    prover: fn() -> Rc<dyn Prover>,
+    input: Vec<u8>,
+    journal: &[u8],
+) -> Result<Bytes> {
+    let receipt = tokio::task::spawn_blocking(move || {
+        let env = ExecutorEnv::builder().write_slice(&input).build()?;
+        prover()
+            .prove_with_ctx(
+                env,
+                &VerifierContext::default(),
+                IS_EVEN_ELF,
+                &ProverOpts::groth16(),
+            )
+            .map(|info| info.receipt)
+    })
+    .await?
+    .context("failed to prove the guest")?;
+    receipt
+        .verify(IS_EVEN_ID)
+        .context("the proof doesn't verify")?;
+    ensure!(
+        receipt.journal.bytes == journal,
+        "the proof committed another journal than the request"
+    );
+    Ok(encode_seal(&receipt)?.into())
+}
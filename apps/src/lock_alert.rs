@@ -0,0 +1,156 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Alerts on requests that no prover locked within a window of blocks, behind
+//! `--lock-alert-blocks`.
+//!
+//! A request nobody bids on otherwise only fails at its timeout. The alert is a warning, and
+//! optionally a JSON payload posted to a webhook, with the context of the market: the price the
+//! request is at, and how many other requests provers locked over the same blocks. Provers
+//! locking other requests but not this one suggests that its price is too low.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::{
+    primitives::{utils::format_units, U256},
+    providers::Provider,
+};
+use anyhow::{Context, Result};
+use boundless_market::{ProofRequest, StandardClient};
+use clap::Args;
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use url::Url;
+
+/// Options of the alert on requests not locked in time.
+#[derive(Args, Clone, Debug, Default)]
+pub struct LockAlertArgs {
+    /// Warn when no prover locked a request within this number of blocks of its submission.
+    #[clap(long, env, value_name = "BLOCKS")]
+    lock_alert_blocks: Option<u64>,
+    /// URL to post a JSON alert to when no prover locked a request in time.
+    #[clap(long, env, value_name = "URL", requires = "lock_alert_blocks")]
+    lock_alert_webhook: Option<Url>,
+}
+
+impl LockAlertArgs {
+    /// The alert of the options, if enabled.
+    pub fn alert(&self) -> Option<LockAlert> {
+        Some(LockAlert {
+            blocks: self.lock_alert_blocks?,
+            webhook: self.lock_alert_webhook.clone(),
+        })
+    }
+}
+
+/// Alert raised once per request, when no prover locked it within `blocks` of its submission.
+#[derive(Clone, Debug)]
+pub struct LockAlert {
+    pub blocks: u64,
+    pub webhook: Option<Url>,
+}
+
+/// Payload posted to the webhook.
+#[derive(Debug, Serialize)]
+struct Alert {
+    request_id: String,
+    /// Blocks since the submission of the request.
+    blocks: u64,
+    /// Current price of the request, in wei.
+    price: String,
+    min_price: String,
+    max_price: String,
+    /// Timestamp at which the price reaches the maximum.
+    ramp_up_end: u64,
+    expires_at: u64,
+    /// Requests locked by provers over the same blocks.
+    other_locks: usize,
+}
+
+impl LockAlert {
+    /// Raise the alert if no prover locked the request within the window, submitted at
+    /// `from_block`. Returns whether the alert was raised.
+    pub async fn check(
+        &self,
+        client: &StandardClient,
+        request: &ProofRequest,
+        from_block: u64,
+    ) -> bool {
+        let block = match client.provider().get_block_number().await {
+            Ok(block) => block,
+            Err(e) => {
+                tracing::debug!("Failed to get the block number for the lock alert: {e}");
+                return false;
+            }
+        };
+        let blocks = block.saturating_sub(from_block);
+        if blocks < self.blocks {
+            return false;
+        }
+        if let Err(e) = self.raise(client, request, from_block, blocks).await {
+            tracing::warn!("Failed to send the lock alert: {e:#}");
+        }
+        true
+    }
+
+    async fn raise(
+        &self,
+        client: &StandardClient,
+        request: &ProofRequest,
+        from_block: u64,
+        blocks: u64,
+    ) -> Result<()> {
+        let offer = &request.offer;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let price = offer.price_at(now).unwrap_or_default();
+        let locks = client
+            .boundless_market
+            .instance()
+            .RequestLocked_filter()
+            .from_block(from_block)
+            .query()
+            .await
+            .context("failed to query lock events")?;
+        let alert = Alert {
+            request_id: format!("{:x}", request.id),
+            blocks,
+            price: price.to_string(),
+            min_price: offer.minPrice.to_string(),
+            max_price: offer.maxPrice.to_string(),
+            ramp_up_end: offer.biddingStart + u64::from(offer.rampUpPeriod),
+            expires_at: request.expires_at(),
+            other_locks: locks.len(),
+        };
+        tracing::warn!(
+            "No prover locked request {} within {blocks} blocks, its price may be too low: it is \
+             at {} ETH, of a maximum of {} ETH, and provers locked {} other requests meanwhile",
+            alert.request_id,
+            format_units(price, "ether")?,
+            format_units(U256::from(offer.maxPrice), "ether")?,
+            alert.other_locks
+        );
+        if let Some(webhook) = &self.webhook {
+            reqwest::Client::new()
+                .post(webhook.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .body(serde_json::to_vec(&alert)?)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| e.without_url())
+                .context("failed to call the lock alert webhook")?;
+        }
+        Ok(())
+    }
+}
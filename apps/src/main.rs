@@ -12,146 +12,161 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::time::Duration;
+//! Command line interface of the publisher, over the [boundless_app] library.
 
-use crate::even_number::IEvenNumber::IEvenNumberInstance;
-use alloy::{
-    primitives::{Address, U256},
-    signers::local::PrivateKeySigner,
-    sol_types::SolValue,
+use std::{io, path::PathBuf, process::ExitCode};
+
+use anyhow::{anyhow, Context, Result};
+use boundless_app::{
+    auction, bench, config, env_file, estimate, failure, failure::Failure, fixture, history,
+    loadtest, oracle, prove, report, resume, revert, rpc_stdio, schedule, selftest, slash,
+    telemetry, tui, verify_bundle, version,
 };
-use anyhow::{bail, Context, Result};
-use boundless_market::{Client, Deployment, StorageProviderConfig};
-use clap::Parser;
-use guests::IS_EVEN_ELF;
+use clap::{CommandFactory, Parser, Subcommand};
 use url::Url;
 
-/// Timeout for the transaction to be confirmed.
-pub const TX_TIMEOUT: Duration = Duration::from_secs(30);
-
-mod even_number {
-    alloy::sol!(
-        #![sol(rpc, all_derives)]
-        "../contracts/src/IEvenNumber.sol"
-    );
+/// The publisher CLI.
+#[derive(Parser, Debug)]
+#[clap(author, version, long_version = version::long_version(), about, long_about = None)]
+struct Cli {
+    /// Format of the log output.
+    #[clap(long, env, global = true, value_enum, default_value_t)]
+    log_format: telemetry::LogFormat,
+    /// OTLP/HTTP endpoint to export traces to, e.g. http://localhost:4318/v1/traces.
+    #[clap(long, env, global = true)]
+    otlp_endpoint: Option<Url>,
+    /// Sentry DSN to report panics and failed submissions to.
+    #[clap(long, env, global = true)]
+    sentry_dsn: Option<sentry::types::Dsn>,
+    /// Environment file to load instead of .env, e.g. .env.sepolia. Defaults to
+    /// .env.<BOUNDLESS_PROFILE> when BOUNDLESS_PROFILE is set.
+    // Loaded by env_file::load, before the arguments are parsed, and checked against the file
+    // loaded once they are.
+    #[clap(long, global = true, value_name = "PATH")]
+    env_file: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Command,
 }
 
-/// Arguments of the publisher CLI.
-#[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
-struct Args {
-    /// The number to publish to the EvenNumber contract.
-    #[clap(short, long)]
-    number: u32,
-    /// URL of the Ethereum RPC endpoint.
-    #[clap(short, long, env)]
-    rpc_url: Url,
-    /// Private key used to interact with the EvenNumber contract and the Boundless Market.
-    #[clap(long, env)]
-    private_key: PrivateKeySigner,
-    /// Address of the EvenNumber contract.
-    #[clap(short, long, env)]
-    even_number_address: Address,
-    /// URL where provers can download the program to be proven.
-    #[clap(long, env)]
-    program_url: Option<Url>,
-    /// Submit the request offchain via the provided order stream service url.
-    #[clap(short, long, requires = "order_stream_url")]
-    offchain: bool,
-    /// Configuration for the StorageProvider to use for uploading programs and inputs.
-    #[clap(flatten, next_help_heading = "Storage Provider")]
-    storage_config: StorageProviderConfig,
-    /// Deployment of the Boundless contracts and services to use.
-    ///
-    /// Will be automatically resolved from the connected chain ID if unspecified.
-    #[clap(flatten, next_help_heading = "Boundless Market Deployment")]
-    deployment: Option<Deployment>,
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    /// Request a proof that a number is even and publish it to the EvenNumber contract.
+    Submit(boundless_app::SubmitArgs),
+    /// Finish the requests left in flight by an interrupted run: wait for their fulfillment, and
+    /// publish their results.
+    Resume(resume::ResumeArgs),
+    /// Slash the provers of the requests of the history that expired locked, and record the
+    /// stake recovered.
+    Slash(slash::SlashArgs),
+    /// Submit many requests at a fixed rate and report lock and fulfillment latencies.
+    Loadtest(loadtest::LoadtestArgs),
+    /// Measure the throughput of the publishing pipeline against a local devnet, in dev mode.
+    Bench(bench::BenchArgs),
+    /// Repeatedly submit a request on a cron schedule.
+    Schedule(schedule::ScheduleArgs),
+    /// Follow the events of a contract requesting proofs, and publish the number of each.
+    Oracle(oracle::OracleArgs),
+    /// Publish a known-good number end to end, and report the outcome of each phase.
+    Selftest(selftest::SelftestArgs),
+    /// Show a live dashboard of the lifecycle of one or more requests.
+    Tui(tui::TuiArgs),
+    /// List the requests previously submitted from this machine.
+    History(history::HistoryArgs),
+    /// Summarize the spend on proofs and transactions of the requests in the local history.
+    Report(report::ReportArgs),
+    /// Record the journal and seal of a fulfilled request for the fork tests of the contract.
+    Fixture(fixture::FixtureArgs),
+    /// Execute the guest and report what its request would cost, without submitting it.
+    Estimate(estimate::EstimateArgs),
+    /// Print the price of an offer at each block of its auction, from the start of the bidding to
+    /// its timeout.
+    Auction(auction::AuctionArgs),
+    /// Prove any guest on a raw input with the market, and write its journal and seal.
+    Prove(prove::ProveArgs),
+    /// Verify an attestation bundle, or a journal and a seal, offline with the RISC Zero verifier.
+    VerifyBundle(verify_bundle::VerifyBundleArgs),
+    /// Serve line-delimited JSON-RPC requests on stdin, for driving the publisher from another
+    /// process.
+    RpcStdio(rpc_stdio::RpcStdioArgs),
+    /// Print the completion script of a shell, e.g. `app completions bash`.
+    Completions {
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
-
-    match dotenvy::dotenv() {
-        Ok(path) => tracing::debug!("Loaded environment variables from {:?}", path),
-        Err(e) if e.not_found() => tracing::debug!("No .env file found"),
-        Err(e) => bail!("failed to load .env file: {}", e),
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:?}");
+            if let Some(reason) = revert::explain(&e) {
+                eprintln!("\nReverted: {reason}");
+            }
+            failure::exit_code(&e)
+        }
     }
-    let args = Args::parse();
-
-    // Create a Boundless client from the provided parameters.
-    let client = Client::builder()
-        .with_rpc_url(args.rpc_url)
-        .with_deployment(args.deployment)
-        .with_storage_provider_config(&args.storage_config)?
-        .with_private_key(args.private_key)
-        .build()
-        .await
-        .context("failed to build boundless client")?;
+}
 
-    // Encode the input for the guest program
-    tracing::info!("Number to publish: {}", args.number);
-    let input_bytes = U256::from(args.number).abi_encode();
+async fn run() -> Result<()> {
+    // The environment file is loaded before parsing the arguments, which may be read from it.
+    let env_file = env_file::load(std::env::args_os());
+    // So is the project configuration, which sets the variables of the options it gives.
+    let config = config::load().context(Failure::Config)?;
+    let cli = Cli::parse();
+    // The responses of rpc-stdio own stdout.
+    let stderr = matches!(cli.command, Command::RpcStdio(_));
+    let tracer_provider =
+        telemetry::init_tracing(cli.log_format, cli.otlp_endpoint.as_ref(), stderr)?;
+    let _sentry = cli.sentry_dsn.map(telemetry::init_sentry);
+
+    let env_file = env_file.context(Failure::Config)?;
+    match &env_file {
+        Some(path) => tracing::debug!("Loaded environment variables from {:?}", path),
+        None => tracing::debug!("No .env file found"),
+    }
+    // --env-file is looked up in the raw arguments, which can disagree with clap, e.g. when it is
+    // the value of another option.
+    if cli.env_file.is_some() && cli.env_file != env_file {
+        return Err(anyhow!(
+            "the environment file was not loaded, pass it as --env-file <PATH> or \
+             --env-file=<PATH>"
+        ))
+        .context(Failure::Config);
+    }
+    if let Some(path) = config {
+        tracing::debug!("Loaded the project configuration from {:?}", path);
+    }
 
-    // Build the request based on whether program URL is provided
-    let request = if let Some(program_url) = args.program_url {
-        // Use the provided URL
-        client
-            .new_request()
-            .with_program_url(program_url)?
-            .with_stdin(input_bytes.clone())
-    } else {
-        client
-            .new_request()
-            .with_program(IS_EVEN_ELF)
-            .with_stdin(input_bytes)
+    let result = match cli.command {
+        Command::Submit(args) => boundless_app::submit(args).await,
+        Command::Resume(args) => resume::run(args).await,
+        Command::Slash(args) => slash::run(args).await,
+        Command::Loadtest(args) => loadtest::run(args).await,
+        Command::Bench(args) => bench::run(args).await,
+        Command::Schedule(args) => schedule::run(args).await,
+        Command::Oracle(args) => oracle::run(args).await,
+        Command::Selftest(args) => selftest::run(args).await,
+        Command::Tui(args) => tui::run(args).await,
+        Command::History(args) => history::run(args),
+        Command::Report(args) => report::run(args).await,
+        Command::Fixture(args) => fixture::run(args).await,
+        Command::Estimate(args) => estimate::run(args).await,
+        Command::Auction(args) => auction::run(args),
+        Command::Prove(args) => prove::run(args).await,
+        Command::VerifyBundle(args) => verify_bundle::run(args).await,
+        Command::RpcStdio(args) => rpc_stdio::run(args).await,
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "app", &mut io::stdout());
+            Ok(())
+        }
     };
-
-    let (request_id, expires_at) = client.submit_onchain(request).await?;
-
-    // Wait for the request to be fulfilled. The market will return the journal and seal.
-    tracing::info!("Waiting for request {:x} to be fulfilled", request_id);
-    let (_journal, seal) = client
-        .wait_for_request_fulfillment(
-            request_id,
-            Duration::from_secs(5), // check every 5 seconds
-            expires_at,
-        )
-        .await?;
-    tracing::info!("Request {:x} fulfilled", request_id);
-
-    // We interact with the EvenNumber contract by calling the set function with our number and
-    // the seal (i.e. proof) returned by the market.
-    let even_number = IEvenNumberInstance::new(args.even_number_address, client.provider().clone());
-    let call_set = even_number
-        .set(U256::from(args.number), seal)
-        .from(client.caller());
-
-    // By calling the set function, we verify the seal against the published roots
-    // of the SetVerifier contract.
-    tracing::info!("Calling EvenNumber set function");
-    let pending_tx = call_set.send().await.context("failed to broadcast tx")?;
-    tracing::info!("Broadcasting tx {}", pending_tx.tx_hash());
-    let tx_hash = pending_tx
-        .with_timeout(Some(TX_TIMEOUT))
-        .watch()
-        .await
-        .context("failed to confirm tx")?;
-    tracing::info!("Tx {:?} confirmed", tx_hash);
-
-    // Query the value stored at the EvenNumber address to check it was set correctly
-    let number = even_number
-        .get()
-        .call()
-        .await
-        .context("failed to get number from contract")?;
-    tracing::info!(
-        "The number variable for contract at address: {:?} is set to {:?}",
-        args.even_number_address,
-        number
-    );
-
-    Ok(())
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!("Failed to flush traces: {e}");
+        }
+    }
+    result
 }
@@ -0,0 +1,217 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Named networks, resolving to the addresses of the Boundless deployment on them.
+//!
+//! The built-in networks are the deployments known to the Boundless SDK. More can be added, or
+//! the built-in ones overridden, in the `[networks]` of the [project
+//! configuration](crate::config), or in a TOML file with one table per network:
+//!
+//! ```toml
+//! [my-network]
+//! chain-id = 11155111
+//! boundless-market-address = "0x..."
+//! set-verifier-address = "0x..."
+//! verifier-router-address = "0x..."  # optional
+//! stake-token-address = "0x..."      # optional
+//! order-stream-url = "https://..."   # optional
+//! ```
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use alloy::{primitives::Address, providers::Provider};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use boundless_market::{
+    deployments::{NamedChain, BASE, BASE_SEPOLIA, SEPOLIA},
+    Deployment,
+};
+use clap::Args;
+use serde::Deserialize;
+
+use crate::{config, failure::Failure};
+
+/// Arguments selecting a Boundless deployment by the name of its network.
+#[derive(Args, Clone, Debug)]
+pub struct NetworkArgs {
+    /// Network whose Boundless deployment to use: sepolia, base-sepolia, base, or one of
+    /// boundless.toml or of the networks file. Defaults to the network of boundless.toml.
+    #[clap(long, env, conflicts_with = "Deployment")]
+    network: Option<String>,
+    /// TOML file of named networks, adding to or overriding the built-in ones.
+    #[clap(long, env)]
+    networks_file: Option<PathBuf>,
+}
+
+/// A network of the networks file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Network {
+    pub chain_id: u64,
+    pub boundless_market_address: Address,
+    pub set_verifier_address: Address,
+    pub verifier_router_address: Option<Address>,
+    pub stake_token_address: Option<Address>,
+    pub order_stream_url: Option<String>,
+}
+
+impl From<Network> for Deployment {
+    fn from(network: Network) -> Self {
+        let mut deployment = Deployment::builder()
+            .chain_id(network.chain_id)
+            .boundless_market_address(network.boundless_market_address)
+            .set_verifier_address(network.set_verifier_address)
+            .build()
+            .expect("all the required fields are set");
+        deployment.verifier_router_address = network.verifier_router_address;
+        deployment.stake_token_address = network.stake_token_address;
+        deployment.order_stream_url = network.order_stream_url.map(Into::into);
+        deployment
+    }
+}
+
+impl NetworkArgs {
+    /// The `deployment` given by the arguments if any, or else the deployment of the network.
+    pub fn resolve(&self, deployment: Option<Deployment>) -> Result<Option<Deployment>> {
+        match deployment {
+            Some(deployment) => Ok(Some(deployment)),
+            None => self.deployment(),
+        }
+    }
+
+    /// The deployment of the named network, or of the default network of the project
+    /// configuration, or `None` if no network is given.
+    pub fn deployment(&self) -> Result<Option<Deployment>> {
+        let Some(name) = self.network.as_ref().or(config::project().network.as_ref()) else {
+            return Ok(None);
+        };
+        let mut networks = builtin();
+        networks.extend(
+            config::project()
+                .networks
+                .iter()
+                .map(|(name, network)| (name.clone(), network.clone().into())),
+        );
+        if let Some(path) = &self.networks_file {
+            let file = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))
+                .context(Failure::Config)?;
+            let custom: BTreeMap<String, Network> = toml::from_str(&file)
+                .with_context(|| format!("invalid networks file {}", path.display()))
+                .context(Failure::Config)?;
+            networks.extend(
+                custom
+                    .into_iter()
+                    .map(|(name, network)| (name, network.into())),
+            );
+        }
+        match networks.remove(name) {
+            Some(deployment) => Ok(Some(deployment)),
+            None => {
+                let known = networks.keys().cloned().collect::<Vec<_>>().join(", ");
+                Err(anyhow!("unknown network {name}, expected one of: {known}"))
+                    .context(Failure::Config)
+            }
+        }
+    }
+}
+
+/// The networks with a deployment known to the Boundless SDK.
+pub(crate) fn builtin() -> BTreeMap<String, Deployment> {
+    let mut base_sepolia = BASE_SEPOLIA;
+    // The SDK tags its Base Sepolia deployment with the chain ID of Base mainnet.
+    base_sepolia.chain_id = Some(NamedChain::BaseSepolia as u64);
+    [
+        ("sepolia", SEPOLIA),
+        ("base-sepolia", base_sepolia),
+        ("base", BASE),
+    ]
+    .into_iter()
+    .map(|(name, deployment)| (name.to_string(), deployment))
+    .collect()
+}
+
+/// Check that the RPC endpoint is on the chain of the Boundless deployment, before any
+/// transaction is sent to it.
+///
+/// The chain ID of the `configured` deployment, from a network or the arguments, must be the one
+/// of the endpoint, and the market of the `resolved` deployment must be deployed on it. A
+/// deployment resolved from the chain ID of the endpoint has no chain ID to compare.
+pub async fn ensure_chain(
+    provider: &impl Provider,
+    configured: Option<&Deployment>,
+    resolved: &Deployment,
+) -> Result<()> {
+    let chain_id = provider.get_chain_id().await?;
+    if let Some(expected) = configured.and_then(|deployment| deployment.chain_id) {
+        ensure!(
+            chain_id == expected,
+            "the RPC endpoint is on chain {chain_id}, but the deployment is on chain {expected}"
+        );
+    }
+    ensure_contract(
+        provider,
+        resolved.boundless_market_address,
+        "BoundlessMarket",
+    )
+    .await
+}
+
+/// Check that a contract is deployed at `address` on the chain of the RPC endpoint.
+pub async fn ensure_contract(provider: &impl Provider, address: Address, name: &str) -> Result<()> {
+    let code = provider.get_code_at(address).await?;
+    if code.is_empty() {
+        let chain_id = provider.get_chain_id().await?;
+        bail!("no {name} contract at {address} on chain {chain_id}, is the RPC endpoint on the right chain?");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_builtin_and_custom_networks() {
+        let dir = std::env::temp_dir().join(format!("networks-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("networks.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [devnet]
+            chain-id = 31337
+            boundless-market-address = "0x0000000000000000000000000000000000000001"
+            set-verifier-address = "0x0000000000000000000000000000000000000002"
+            "#,
+        )
+        .unwrap();
+        let args = |network: &str| NetworkArgs {
+            network: Some(network.to_string()),
+            networks_file: Some(path.clone()),
+        };
+
+        let sepolia = args("sepolia").deployment().unwrap().unwrap();
+        assert_eq!(sepolia.chain_id, Some(11155111));
+        let base_sepolia = args("base-sepolia").deployment().unwrap().unwrap();
+        assert_eq!(base_sepolia.chain_id, Some(84532));
+        let devnet = args("devnet").deployment().unwrap().unwrap();
+        assert_eq!(devnet.chain_id, Some(31337));
+        assert_eq!(devnet.verifier_router_address, None);
+
+        let error = args("mainnet").deployment().unwrap_err();
+        assert_eq!(Failure::of(&error), Some(Failure::Config));
+        assert!(format!("{error:#}").contains("base, base-sepolia, devnet, sepolia"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,92 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Resending transactions whose nonce conflicted with another transaction of the wallet.
+//!
+//! The provider of the Boundless client sends the transactions of the wallet one at a time, each
+//! with the pending nonce of the account, so that concurrent requests sharing a client get
+//! consecutive nonces. A transaction can still be rejected for its nonce, when the RPC endpoint
+//! lags behind the pending transactions or another process sends from the same wallet. It is
+//! then resent, with the nonce fetched again.
+
+use std::{error::Error, future::Future, time::Duration};
+
+/// Number of times a transaction is resent after a nonce conflict.
+const RETRIES: u32 = 3;
+/// Delay before resending, to let the RPC endpoint catch up with the pending transactions.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Send a transaction with `send`, resending it if it was rejected for its nonce.
+pub async fn retry_on_conflict<T, E, F, Fut>(what: &str, mut send: F) -> Result<T, E>
+where
+    E: Error + 'static,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match send().await {
+            Err(e) if attempt < RETRIES && is_conflict(&e) => {
+                attempt += 1;
+                tracing::warn!(
+                    "Nonce conflict sending {what}, resending ({attempt}/{RETRIES}): {e}"
+                );
+                tokio::time::sleep(RETRY_DELAY).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether the error, or one of its sources, is a rejection of the transaction for its nonce.
+fn is_conflict(error: &(dyn Error + 'static)) -> bool {
+    std::iter::successors(Some(error), |&e| e.source()).any(|e| {
+        let message = e.to_string().to_lowercase();
+        [
+            "nonce too low",
+            "nonce too high",
+            "replacement transaction underpriced",
+        ]
+        .iter()
+        .any(|conflict| message.contains(conflict))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn detects_conflicts_in_sources() {
+        #[derive(Debug)]
+        struct Wrapper(io::Error);
+        impl std::fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("failed to send tx")
+            }
+        }
+        impl Error for Wrapper {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let conflict = Wrapper(io::Error::other("server returned an error: Nonce too low"));
+        assert!(is_conflict(&conflict));
+        let other = Wrapper(io::Error::other("insufficient funds for gas"));
+        assert!(!is_conflict(&other));
+    }
+}
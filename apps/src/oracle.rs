@@ -0,0 +1,346 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proving on demand: a daemon following the events of a source contract.
+//!
+//! Each event emitted by the source contract with the configured signature, e.g.
+//! `NumberRequested(uint256)`, requests the proof of the number it carries: the first argument
+//! of the event, indexed or not. The number is submitted to the market and its result posted to
+//! the EvenNumber contract, as by the submit command, which turns the app into an event-driven
+//! proving oracle. The source contract is read on the chain of the market.
+//!
+//! The requests are tagged with the event that triggered them, so that replaying blocks with
+//! `--from-block` after a restart skips the events already submitted.
+
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    num::{NonZeroU64, NonZeroUsize},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{keccak256, U256},
+    providers::Provider,
+    rpc::types::{Filter, Log},
+};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::StandardClient;
+use clap::Args;
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use url::Url;
+
+use crate::{
+    ens::NameOrAddress,
+    failure::Failure,
+    history::{History, Timings},
+    lock_alert, state_dir, submit_with, upload_program, usd, ClientArgs, Consumer, ConsumerArgs,
+    SubmitArgs,
+};
+
+/// Maximum number of blocks queried for events in a single request.
+const BLOCK_RANGE: u64 = 1000;
+
+/// Key of the tag recording the event that triggered a request.
+const EVENT_TAG: &str = "source-event";
+
+/// Arguments of the oracle command.
+#[derive(Args, Debug)]
+pub struct OracleArgs {
+    /// Address, or ENS name, of the contract emitting the events requesting proofs.
+    #[clap(long, env)]
+    source_address: NameOrAddress,
+    /// Signature of the event requesting a proof, whose first argument is the number to prove.
+    #[clap(long, env, default_value = "NumberRequested(uint256)")]
+    source_event: String,
+    /// Block from which to follow the events, e.g. to catch up after a restart. Defaults to the
+    /// latest block.
+    #[clap(long)]
+    from_block: Option<u64>,
+    /// Interval, in seconds, between queries of new events.
+    #[clap(long, env, default_value_t = NonZeroU64::new(5).unwrap())]
+    event_poll_interval: NonZeroU64,
+    /// Maximum number of requests in flight at once.
+    #[clap(long, default_value_t = NonZeroUsize::new(4).unwrap())]
+    concurrency: NonZeroUsize,
+    /// Address, or ENS name, of the EvenNumber contract. Can be repeated, or comma-separated, to
+    /// post each result to several contracts, see `submit --even-number-address`.
+    #[clap(
+        short,
+        long = "even-number-address",
+        env = "EVEN_NUMBER_ADDRESS",
+        value_name = "EVEN_NUMBER_ADDRESS",
+        value_delimiter = ',',
+        required = true
+    )]
+    even_number_addresses: Vec<NameOrAddress>,
+    /// URL where provers can download the program to be proven. The program is uploaded once at
+    /// startup otherwise.
+    #[clap(long, env)]
+    program_url: Option<Url>,
+    /// Deposit into the market the shortfall of the balance of the client, see `submit
+    /// --auto-deposit`.
+    #[clap(long, env)]
+    auto_deposit: bool,
+    /// Address on which to serve Prometheus metrics at `/metrics`, e.g. 0.0.0.0:9090.
+    #[clap(long, env)]
+    metrics_addr: Option<SocketAddr>,
+    /// Interval, in seconds, between status checks of a request while its status is likely to
+    /// change. The interval backs off up to `--max-poll-interval` otherwise.
+    #[clap(long, env, default_value_t = 5)]
+    poll_interval: u64,
+    /// Maximum interval, in seconds, between status checks of a request.
+    #[clap(long, env, default_value_t = 60)]
+    max_poll_interval: u64,
+    #[clap(flatten)]
+    lock_alert: lock_alert::LockAlertArgs,
+    #[clap(flatten)]
+    state: state_dir::StateArgs,
+    #[clap(flatten)]
+    client: ClientArgs,
+    #[clap(flatten, next_help_heading = "Consumer Chain")]
+    consumer: ConsumerArgs,
+}
+
+/// A proof requested by an event of the source contract.
+#[derive(Debug, PartialEq, Eq)]
+struct Requested {
+    number: u32,
+    /// The event, as `<tx hash>:<log index>`.
+    event: String,
+}
+
+/// Follow the events of the source contract and publish the number of each, until the process
+/// is stopped.
+///
+/// A failed request is logged and does not stop the daemon. The events are queried again from
+/// the same block if their query fails.
+pub async fn run(args: OracleArgs) -> Result<()> {
+    if let Some(addr) = args.metrics_addr {
+        crate::telemetry::serve(addr)?;
+    }
+    let client = args.client.build_client().await.context(Failure::Config)?;
+    let history = args.state.open(state_dir::Access::Shared)?;
+    let source = args
+        .source_address
+        .resolve(&client.provider())
+        .await
+        .context(Failure::Config)?;
+    if !args.source_event.contains('(') {
+        return Err(anyhow!(
+            "invalid event signature {}, expected e.g. NumberRequested(uint256)",
+            args.source_event
+        ))
+        .context(Failure::Config);
+    }
+    let filter = Filter::new()
+        .address(source)
+        .event_signature(keccak256(args.source_event.as_bytes()));
+
+    // Every request runs the submit flow, with the program uploaded once for all of them.
+    let mut template = SubmitArgs {
+        numbers: Vec::new(),
+        input: Default::default(),
+        concurrency: NonZeroUsize::MIN,
+        even_number_addresses: args.even_number_addresses.clone(),
+        program_url: args.program_url.clone(),
+        expires_at: None,
+        print_calldata: false,
+        export_bundle: None,
+        auto_deposit: args.auto_deposit,
+        client_contract: None,
+        no_budget_check: false,
+        fallback_local_prove: false,
+        prover: crate::ProverBackend::Market,
+        lock_alert: args.lock_alert.clone(),
+        offchain: false,
+        request_index: None,
+        tags: Vec::new(),
+        state: args.state.clone(),
+        poll_interval: args.poll_interval,
+        max_poll_interval: args.max_poll_interval,
+        no_preflight_cache: false,
+        dev_mode: false,
+        client: args.client.clone(),
+        consumer: args.consumer.clone(),
+        prices: usd::PriceArgs::default(),
+    };
+    let consumer = template
+        .connect_consumer(client.provider())
+        .await
+        .context(Failure::Config)?;
+    if template.program_url.is_none() {
+        template.program_url = Some(upload_program(&client, &mut Timings::default()).await?);
+    }
+
+    let mut next_block = match args.from_block {
+        Some(block) => block,
+        None => client.provider().get_block_number().await? + 1,
+    };
+    tracing::info!(
+        "Following the {} events of {source} from block {next_block}",
+        args.source_event
+    );
+    let mut pending = VecDeque::new();
+    let mut in_flight = FuturesUnordered::new();
+    let mut ticks = tokio::time::interval(Duration::from_secs(args.event_poll_interval.get()));
+    loop {
+        while in_flight.len() < args.concurrency.get() {
+            let Some(requested) = pending.pop_front() else {
+                break;
+            };
+            in_flight.push(publish(&client, &consumer, &history, &template, requested));
+        }
+        tokio::select! {
+            _ = ticks.tick() => match poll(&client, &filter, next_block).await {
+                Ok((requested, to_block, caught_up)) => {
+                    pending.extend(requested);
+                    next_block = to_block + 1;
+                    if !caught_up {
+                        ticks.reset_immediately();
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to query the events of {source}: {e:#}"),
+            },
+            Some(()) = in_flight.next(), if !in_flight.is_empty() => {}
+        }
+    }
+}
+
+/// Query the events of the blocks from `from_block`, up to [BLOCK_RANGE] of them. Returns the
+/// proofs requested, the last block queried, and whether it is the latest one.
+async fn poll(
+    client: &StandardClient,
+    filter: &Filter,
+    from_block: u64,
+) -> Result<(Vec<Requested>, u64, bool)> {
+    let latest = client.provider().get_block_number().await?;
+    if from_block > latest {
+        return Ok((Vec::new(), from_block - 1, true));
+    }
+    let to_block = latest.min(from_block + BLOCK_RANGE - 1);
+    let logs = client
+        .provider()
+        .get_logs(&filter.clone().from_block(from_block).to_block(to_block))
+        .await
+        .context("failed to query the events")?;
+    let requested = logs
+        .iter()
+        .filter_map(|log| {
+            requested(log)
+                .inspect_err(|e| tracing::warn!("Ignoring an event of the source contract: {e:#}"))
+                .ok()
+        })
+        .collect();
+    Ok((requested, to_block, to_block == latest))
+}
+
+/// The proof requested by the event: of the number in its first argument.
+fn requested(log: &Log) -> Result<Requested> {
+    let event = format!(
+        "{}:{}",
+        log.transaction_hash.unwrap_or_default(),
+        log.log_index.unwrap_or_default()
+    );
+    let word = match log.topics().get(1) {
+        Some(topic) => U256::from_be_bytes(topic.0),
+        None => log
+            .data()
+            .data
+            .get(..32)
+            .map(U256::from_be_slice)
+            .with_context(|| format!("event {event} has no argument"))?,
+    };
+    let number = u32::try_from(word)
+        .map_err(|_| anyhow!("the number {word} requested by event {event} is not a u32"))?;
+    Ok(Requested { number, event })
+}
+
+/// Publish the number requested, unless a request was submitted for the event already.
+async fn publish(
+    client: &StandardClient,
+    consumer: &Consumer,
+    history: &History,
+    template: &SubmitArgs,
+    requested: Requested,
+) {
+    let tag = (EVENT_TAG.to_string(), requested.event.clone());
+    match history.list(std::slice::from_ref(&tag), 1) {
+        Ok(records) if !records.is_empty() => {
+            tracing::info!(
+                "Event {} was already submitted as request {}, skipping it",
+                requested.event,
+                records[0].request_id
+            );
+            return;
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("Failed to look the event up in the history: {e:#}"),
+    }
+    tracing::info!(
+        "Event {} requested a proof for {}",
+        requested.event,
+        requested.number
+    );
+    let mut args = template.clone();
+    args.numbers = vec![requested.number];
+    args.tags.push(tag);
+    if let Err(e) = submit_with(client, consumer, history, &args).await {
+        tracing::error!(
+            "Publishing {} for event {} failed: {e:?}",
+            requested.number,
+            requested.event
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, Bytes, LogData, B256};
+
+    use super::*;
+
+    fn log(topics: Vec<B256>, data: Vec<u8>) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address: Address::ZERO,
+                data: LogData::new_unchecked(topics, Bytes::from(data)),
+            },
+            transaction_hash: Some(B256::repeat_byte(1)),
+            log_index: Some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn reads_the_number_requested() {
+        let signature = keccak256("NumberRequested(uint256)");
+        let event = format!("{}:3", B256::repeat_byte(1));
+        let indexed = log(vec![signature, U256::from(4).into()], Vec::new());
+        assert_eq!(
+            requested(&indexed).unwrap(),
+            Requested {
+                number: 4,
+                event: event.clone()
+            }
+        );
+        let unindexed = log(vec![signature], U256::from(6).to_be_bytes_vec());
+        assert_eq!(
+            requested(&unindexed).unwrap(),
+            Requested { number: 6, event }
+        );
+        assert!(requested(&log(vec![signature], Vec::new())).is_err());
+        assert!(requested(&log(vec![signature, U256::MAX.into()], Vec::new())).is_err());
+    }
+}
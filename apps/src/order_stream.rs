@@ -0,0 +1,97 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authentication of the calls to the order stream.
+//!
+//! Private and rate-limited order streams reject anonymous calls. The API key and the headers
+//! given here are sent with every HTTP call of the order stream client: submitting requests and
+//! fetching orders. The WebSocket feed of orders, only used by provers, is not authenticated.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use url::Url;
+
+use crate::http_proxy;
+
+/// Credentials and headers of the calls to the order stream.
+#[derive(Args, Clone, Debug, Default)]
+pub struct OrderStreamArgs {
+    /// API key of the order stream, sent as a bearer token.
+    #[clap(long, env = "ORDER_STREAM_API_KEY", hide_env_values = true)]
+    order_stream_api_key: Option<String>,
+    /// Header sent with each call to the order stream, as `NAME: VALUE`. Can be repeated.
+    #[clap(
+        long = "order-stream-header",
+        env = "ORDER_STREAM_HEADERS",
+        value_delimiter = ',',
+        value_name = "NAME:VALUE",
+        value_parser = parse_header,
+        hide_env_values = true
+    )]
+    order_stream_headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl OrderStreamArgs {
+    /// Whether any credential or header is given, so that the client of the SDK can't be used.
+    pub fn is_given(&self) -> bool {
+        self.order_stream_api_key.is_some() || !self.order_stream_headers.is_empty()
+    }
+
+    /// HTTP client sending the credentials and headers with each call, through `proxy` if any.
+    pub fn http_client(&self, proxy: Option<&Url>) -> Result<reqwest::Client> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.order_stream_headers {
+            headers.append(name.clone(), value.clone());
+        }
+        if let Some(api_key) = &self.order_stream_api_key {
+            let mut value = HeaderValue::try_from(format!("Bearer {api_key}"))
+                .context("invalid order stream API key")?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        http_proxy::builder(proxy)?
+            .default_headers(headers)
+            .build()
+            .context("failed to build the order stream HTTP client")
+    }
+}
+
+fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue)> {
+    let (name, value) = header
+        .split_once(':')
+        .context("expected a header as `NAME: VALUE`")?;
+    let name = HeaderName::try_from(name.trim()).context("invalid header name")?;
+    let mut value = HeaderValue::try_from(value.trim()).context("invalid header value")?;
+    // Custom headers often carry credentials too, which are kept out of debug output.
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_headers() {
+        let (name, value) = parse_header("X-Api-Key: secret").unwrap();
+        assert_eq!(name, "x-api-key");
+        assert_eq!(value, "secret");
+        assert!(value.is_sensitive());
+        let (_, value) = parse_header("Cookie:a=b:c").unwrap();
+        assert_eq!(value, "a=b:c");
+        assert!(parse_header("X-Api-Key").is_err());
+        assert!(parse_header("Bad Name: value").is_err());
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Preflight of requests: the local execution of the guest giving the journal and cycle count of
+//! a request, cached in the history database so that an input is only executed once.
+//!
+//! The cycle count of a request, which its price is derived from, is the sum of the sizes of its
+//! segments, each padded to a power of two. The segments are kept along with it, to break the
+//! count down into the cycles of the guest and the padding.
+//!
+//! Executions are CPU-bound, so those of concurrent requests run in parallel on blocking threads,
+//! at most one per available CPU.
+
+use std::{fmt, sync::LazyLock, thread};
+
+use anyhow::{Context, Result};
+use guests::{IS_EVEN_ELF, IS_EVEN_ID};
+use risc0_zkvm::{
+    default_executor,
+    sha::{Digest, Impl, Sha256},
+    ExecutorEnv, Journal,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use crate::history::History;
+
+/// Permits to execute the guest, one per available CPU.
+static EXECUTIONS: LazyLock<Semaphore> =
+    LazyLock::new(|| Semaphore::new(thread::available_parallelism().map_or(1, |cpus| cpus.get())));
+
+/// Results of the execution of the guest on an input.
+pub struct Preflight {
+    /// Cycles of the segments, padded, as used to price the request.
+    pub cycles: u64,
+    pub journal: Journal,
+    /// Segments of the execution, unless its results were cached before segments were.
+    pub segments: Option<Vec<Segment>>,
+}
+
+/// A segment of an execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Segment {
+    /// Log2 of the size of the segment, in cycles.
+    pub po2: u32,
+    /// Cycles of the guest in the segment, without the overhead of continuations or the padding.
+    pub user_cycles: u32,
+}
+
+impl Segment {
+    /// Cycles of the segment, padded to its size.
+    pub fn padded_cycles(&self) -> u64 {
+        1 << self.po2
+    }
+}
+
+/// Table of the segments of an execution, with their totals.
+pub struct SegmentTable<'a>(pub &'a [Segment]);
+
+impl fmt::Display for SegmentTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>7}  {:>3}  {:>12}  {:>13}",
+            "segment", "po2", "user cycles", "padded cycles"
+        )?;
+        for (i, segment) in self.0.iter().enumerate() {
+            writeln!(
+                f,
+                "{i:>7}  {:>3}  {:>12}  {:>13}",
+                segment.po2,
+                segment.user_cycles,
+                segment.padded_cycles()
+            )?;
+        }
+        let user: u64 = self.0.iter().map(|s| u64::from(s.user_cycles)).sum();
+        let padded = total_cycles(self.0);
+        writeln!(f, "{:>7}  {:>3}  {user:>12}  {padded:>13}", "total", "")?;
+        let share = if padded == 0 {
+            0.0
+        } else {
+            100.0 * user as f64 / padded as f64
+        };
+        write!(
+            f,
+            "{share:.1}% of the padded cycles are user cycles, priced as {:.2} Mcycles",
+            mcycles(padded)
+        )
+    }
+}
+
+/// Cycles of the segments, padded, as used to price a request.
+pub fn total_cycles(segments: &[Segment]) -> u64 {
+    segments.iter().map(Segment::padded_cycles).sum()
+}
+
+/// Millions of cycles, the unit prices per cycle are usually quoted in.
+pub fn mcycles(cycles: u64) -> f64 {
+    cycles as f64 / 1e6
+}
+
+/// Execute the is-even guest on the input, unless its results for the same input are cached.
+///
+/// The results are keyed by the image ID of the guest built with this crate, which is assumed to
+/// be the program of the request even if it was uploaded ahead of time: the EvenNumber contract
+/// only accepts proofs of it.
+pub async fn preflight(history: &History, input: &[u8], use_cache: bool) -> Result<Preflight> {
+    let image_id = Digest::from(IS_EVEN_ID);
+    let input_digest = *Impl::hash_bytes(input);
+    if use_cache {
+        if let Some((cycles, journal)) = history.cached_preflight(&image_id, &input_digest)? {
+            tracing::info!("Using the cached preflight of input {input_digest}: {cycles} cycles");
+            return Ok(Preflight {
+                cycles,
+                journal: Journal::new(journal),
+                segments: history.cached_segments(&image_id, &input_digest)?,
+            });
+        }
+    }
+
+    let _permit = EXECUTIONS.acquire().await?;
+    let input = input.to_vec();
+    let session = tokio::task::spawn_blocking(move || {
+        let env = ExecutorEnv::builder().write_slice(&input).build()?;
+        default_executor().execute(env, IS_EVEN_ELF)
+    })
+    .await?
+    .context("failed to execute the program")?;
+    let segments: Vec<_> = session
+        .segments
+        .iter()
+        .map(|segment| Segment {
+            po2: segment.po2,
+            user_cycles: segment.cycles,
+        })
+        .collect();
+    let cycles = total_cycles(&segments);
+    tracing::info!(
+        "Executed the guest in {} segments: {cycles} cycles, priced as {:.2} Mcycles",
+        segments.len(),
+        mcycles(cycles)
+    );
+    tracing::debug!("Segments of the execution:\n{}", SegmentTable(&segments));
+    history.cache_preflight(&image_id, &input_digest, cycles, &session.journal.bytes)?;
+    history.cache_segments(&image_id, &input_digest, &segments)?;
+    Ok(Preflight {
+        cycles,
+        journal: session.journal,
+        segments: Some(segments),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breaks_down_the_cycles_of_the_segments() {
+        let segments = [
+            Segment {
+                po2: 20,
+                user_cycles: 1_000_000,
+            },
+            Segment {
+                po2: 18,
+                user_cycles: 65_536,
+            },
+        ];
+        assert_eq!(total_cycles(&segments), 1_310_720);
+        assert_eq!(mcycles(1_000_000), 1.0);
+        assert_eq!(
+            SegmentTable(&segments).to_string(),
+            "\
+segment  po2   user cycles  padded cycles
+      0   20       1000000        1048576
+      1   18         65536         262144
+  total            1065536        1310720
+81.3% of the padded cycles are user cycles, priced as 1.31 Mcycles"
+        );
+    }
+}
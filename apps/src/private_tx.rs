@@ -0,0 +1,128 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sending the `set` transaction through a private relay, such as Flashbots Protect.
+//!
+//! The calldata of the `set` transaction reveals the published number and its seal. Sent to the
+//! public mempool, it can be front-run or sandwiched before it is included. A private relay only
+//! forwards it to block builders. The transaction is filled and signed locally, sent raw to the
+//! relay, and its receipt is then awaited through the RPC endpoint of the chain.
+
+use std::time::Duration;
+
+use alloy::{
+    eips::Encodable2718,
+    network::{Ethereum, EthereumWallet, TransactionBuilder},
+    providers::{DynProvider, PendingTransactionBuilder, Provider, ProviderBuilder, RootProvider},
+    rpc::types::TransactionRequest,
+    signers::local::PrivateKeySigner,
+};
+use anyhow::{Context, Result};
+use clap::Args;
+use url::Url;
+
+/// Timeout for a private transaction to be included. A relay can hold the transaction for a
+/// number of blocks, until a builder includes it.
+pub const PRIVATE_TX_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Arguments routing the `set` transaction through a private relay.
+#[derive(Args, Clone, Debug, Default)]
+pub struct PrivateTxArgs {
+    /// Send the set transaction through a private relay instead of the public mempool, so that
+    /// its calldata isn't revealed before it is included.
+    #[clap(long, env)]
+    private_tx: bool,
+    /// URL of the private relay. Defaults to Flashbots Protect on Ethereum mainnet and Sepolia.
+    #[clap(long, env, requires = "private_tx")]
+    private_relay_url: Option<Url>,
+}
+
+impl PrivateTxArgs {
+    /// Connect to the relay of the chain of the provider, or return `None` if private
+    /// transactions are not enabled.
+    pub async fn relay(
+        &self,
+        provider: &impl Provider,
+        signer: &PrivateKeySigner,
+    ) -> Result<Option<Relay>> {
+        if !self.private_tx {
+            return Ok(None);
+        }
+        let url = match &self.private_relay_url {
+            Some(url) => url.clone(),
+            None => {
+                let chain_id = provider.get_chain_id().await?;
+                default_relay(chain_id).with_context(|| {
+                    format!(
+                        "no default private relay for chain {chain_id}, pass --private-relay-url"
+                    )
+                })?
+            }
+        };
+        tracing::debug!("Sending the set transactions through {url}");
+        Ok(Some(Relay {
+            provider: ProviderBuilder::default().connect_http(url),
+            wallet: signer.clone().into(),
+        }))
+    }
+}
+
+/// Connection to a private relay, with the wallet signing the transactions sent to it.
+#[derive(Clone, Debug)]
+pub struct Relay {
+    provider: RootProvider,
+    wallet: EthereumWallet,
+}
+
+impl Relay {
+    /// Fill in, sign and send the transaction to the relay. The returned pending transaction is
+    /// watched through `provider`.
+    pub async fn send(
+        &self,
+        provider: &DynProvider,
+        mut tx: TransactionRequest,
+    ) -> Result<PendingTransactionBuilder<Ethereum>> {
+        let from = tx.from.context("the transaction has no sender")?;
+        tx.set_nonce(provider.get_transaction_count(from).pending().await?);
+        tx.set_chain_id(provider.get_chain_id().await?);
+        if tx.gas.is_none() {
+            tx.set_gas_limit(provider.estimate_gas(tx.clone()).await?);
+        }
+        if tx.gas_price.is_none() && tx.max_fee_per_gas.is_none() {
+            let fees = provider.estimate_eip1559_fees().await?;
+            tx.set_max_fee_per_gas(fees.max_fee_per_gas);
+            tx.set_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+        }
+        let envelope = tx.build(&self.wallet).await?;
+        let sent = self
+            .provider
+            .send_raw_transaction(&envelope.encoded_2718())
+            .await
+            .context("the private relay rejected the transaction")?;
+        Ok(PendingTransactionBuilder::new(
+            provider.root().clone(),
+            *sent.tx_hash(),
+        ))
+    }
+}
+
+/// Flashbots Protect endpoint of the chain, if it has one.
+fn default_relay(chain_id: u64) -> Option<Url> {
+    let url = match chain_id {
+        1 => "https://rpc.flashbots.net",
+        11155111 => "https://rpc-sepolia.flashbots.net",
+        _ => return None,
+    };
+    Some(url.parse().expect("valid URL"))
+}
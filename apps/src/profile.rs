@@ -0,0 +1,82 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Built-in offer profiles, trading the price of a proof against how soon it is fulfilled.
+//!
+//! A higher price per cycle reached sooner gets a request locked quicker, as more provers find it
+//! profitable early in the auction, while a long ramp-up to a low price lets the request wait for
+//! a prover willing to take it cheaply. A profile overrides the `[offer]` defaults of
+//! `boundless.toml`, except for the lock stake, which it leaves as configured.
+
+use alloy::primitives::U256;
+use clap::ValueEnum;
+
+use crate::config::OfferConfig;
+
+/// Offer profile of the requests.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OfferProfile {
+    /// Locked within a minute or two: a high price reached quickly, and short timeouts.
+    Fast,
+    /// The defaults of the Boundless SDK, fulfilled within minutes.
+    Balanced,
+    /// A low price reached slowly, and timeouts long enough for a prover to take it.
+    Cheap,
+}
+
+impl OfferProfile {
+    /// Offer defaults of the profile, in wei per cycle and seconds.
+    pub fn offer(self) -> OfferConfig {
+        let (min_price, max_price, ramp_up_period, lock_timeout, timeout) = match self {
+            OfferProfile::Fast => (10_000_000, 200_000_000, 30, 300, 600),
+            OfferProfile::Balanced => (0, 100_000_000, 60, 600, 1200),
+            OfferProfile::Cheap => (0, 20_000_000, 600, 1800, 3600),
+        };
+        OfferConfig {
+            min_price_per_cycle: Some(U256::from(min_price)),
+            max_price_per_cycle: Some(U256::from(max_price)),
+            bidding_start_delay: Some(15),
+            ramp_up_period: Some(ramp_up_period),
+            lock_timeout: Some(lock_timeout),
+            timeout: Some(timeout),
+            lock_stake: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use boundless_market::request_builder::OfferLayerConfig;
+
+    use super::*;
+
+    #[test]
+    fn profiles_are_valid_offers() {
+        let defaults = OfferLayerConfig::default();
+        let balanced = OfferProfile::Balanced.offer();
+        assert_eq!(
+            balanced.max_price_per_cycle,
+            Some(defaults.max_price_per_cycle)
+        );
+        assert_eq!(balanced.timeout, Some(defaults.timeout));
+        for profile in OfferProfile::value_variants() {
+            let offer = profile.offer();
+            assert!(offer.min_price_per_cycle <= offer.max_price_per_cycle);
+            // Requests with a ramp-up longer than their lock timeout, or a lock timeout longer
+            // than their timeout, are rejected by the request builder.
+            assert!(offer.ramp_up_period <= offer.lock_timeout, "{profile:?}");
+            assert!(offer.lock_timeout <= offer.timeout, "{profile:?}");
+        }
+    }
+}
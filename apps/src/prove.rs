@@ -0,0 +1,411 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proving any guest with the Boundless Market and posting its proof, in one call.
+//!
+//! [ProveAndPost] runs the whole flow for a program and its input: the request builder of the
+//! client uploads the program, executes it and prices the offer, the request is submitted
+//! onchain and waited for, and its journal and seal are then handed to a closure sending the
+//! transaction that consumes them. Unlike [submit_with](crate::submit_with), it keeps no
+//! history. [ProveAndPost::events] runs the same flow as a stream of [RequestEvent]s, for
+//! applications reacting to each transition of the request, and [ProveAndPost::prove] stops at
+//! the proof, which the `prove` command writes out.
+//!
+//! ```no_run
+//! # async fn example(
+//! #     client: boundless_market::StandardClient,
+//! #     consumer: boundless_app::Consumer,
+//! # ) -> anyhow::Result<()> {
+//! use alloy::primitives::U256;
+//! use boundless_app::{even_number::IEvenNumber, prove::ProveAndPost};
+//! use guests::{encode_input, Journal, IS_EVEN_ELF};
+//!
+//! let even_number = IEvenNumber::new(consumer.even_number_address, &consumer.provider);
+//! let proved = ProveAndPost::new(&client, IS_EVEN_ELF, encode_input(U256::from(4)))
+//!     .run(|journal, seal| async move {
+//!         let journal = Journal::decode(&journal)?;
+//!         let receipt = even_number.set(journal.number, seal).send().await?.get_receipt().await?;
+//!         Ok(receipt.transaction_hash)
+//!     })
+//!     .await?;
+//! println!("Request {:x} posted in {}", proved.request_id, proved.tx_hash);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{borrow::Cow, future::Future, path::PathBuf, time::Duration};
+
+use alloy::{
+    primitives::{Bytes, FixedBytes, B256, U256},
+    providers::Provider,
+};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{
+    contracts::boundless_market::MarketError,
+    request_builder::{OfferParams, RequirementParams},
+    ProofRequest, StandardClient,
+};
+use clap::Args;
+use futures_util::{
+    stream::{self, LocalBoxStream},
+    Stream, StreamExt,
+};
+use risc0_ethereum_contracts::selector::Selector;
+use serde::Serialize;
+
+use crate::{
+    failure::Failure,
+    input::InputArgs,
+    wait::{self, RequestEvent},
+    ClientArgs,
+};
+
+/// Arguments of the prove command.
+#[derive(Args, Clone, Debug)]
+pub struct ProveArgs {
+    /// Path of the ELF of the guest to prove.
+    #[clap(long, value_name = "PATH")]
+    elf: PathBuf,
+    /// Request a stand-alone Groth16 proof, which can be verified on any chain with a RISC Zero
+    /// verifier, instead of a proof of inclusion in a root of the set verifier of the market.
+    #[clap(long)]
+    groth16: bool,
+    /// Path of the JSON file to write the request ID, journal and seal to. Printed to stdout if
+    /// not given.
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// Interval, in seconds, between status checks of the request.
+    #[clap(long, env, default_value_t = 5)]
+    poll_interval: u64,
+    /// Maximum interval, in seconds, between status checks of the request.
+    #[clap(long, env, default_value_t = 60)]
+    max_poll_interval: u64,
+    #[clap(flatten)]
+    client: ClientArgs,
+    #[clap(flatten, next_help_heading = "Guest Input")]
+    input: InputArgs,
+    #[clap(flatten, next_help_heading = "Offer")]
+    offer: OfferParams,
+}
+
+/// Request for the proof of a program on an input, and of the transaction posting it.
+pub struct ProveAndPost<'a> {
+    client: &'a StandardClient,
+    program: Cow<'static, [u8]>,
+    stdin: Vec<u8>,
+    requirements: RequirementParams,
+    offer: OfferParams,
+    schedule: wait::PollSchedule,
+}
+
+/// A request proven by the market, as returned by [ProveAndPost::prove].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Proof {
+    pub request_id: U256,
+    pub journal: Bytes,
+    pub seal: Bytes,
+}
+
+/// A request proven by the market, and posted by the closure given to [ProveAndPost::run].
+#[derive(Clone, Debug)]
+pub struct Proved {
+    pub request_id: U256,
+    pub journal: Bytes,
+    pub seal: Bytes,
+    /// Hash of the transaction returned by the closure.
+    pub tx_hash: B256,
+}
+
+impl<'a> ProveAndPost<'a> {
+    /// Prove the program run on `stdin`, with the requirements and offer defaulted by the request
+    /// builder of the client: a proof verifiable by the set verifier of the market, and an offer
+    /// priced on the cycle count of the program.
+    pub fn new(
+        client: &'a StandardClient,
+        program: impl Into<Cow<'static, [u8]>>,
+        stdin: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            client,
+            program: program.into(),
+            stdin: stdin.into(),
+            requirements: RequirementParams::default(),
+            offer: OfferParams::default(),
+            schedule: wait::PollSchedule::default(),
+        }
+    }
+
+    /// Override the requirements of the request, e.g. to ask for a Groth16 proof.
+    pub fn with_requirements(self, requirements: impl Into<RequirementParams>) -> Self {
+        Self {
+            requirements: requirements.into(),
+            ..self
+        }
+    }
+
+    /// Override the offer of the request, e.g. its maximum price or its timeout.
+    pub fn with_offer(self, offer: impl Into<OfferParams>) -> Self {
+        Self {
+            offer: offer.into(),
+            ..self
+        }
+    }
+
+    /// Set the schedule of the status checks of the request.
+    pub fn with_poll_schedule(self, schedule: wait::PollSchedule) -> Self {
+        Self { schedule, ..self }
+    }
+
+    /// Submit the request and wait for it to be fulfilled, then call `post` with the journal and
+    /// the seal of the proof. `post` returns the hash of the transaction consuming them, once it
+    /// is confirmed.
+    pub async fn run<F, Fut>(self, post: F) -> Result<Proved>
+    where
+        F: FnOnce(Bytes, Bytes) -> Fut + 'a,
+        Fut: Future<Output = Result<B256>> + 'a,
+    {
+        let mut events = std::pin::pin!(self.events(post));
+        let mut proof = None;
+        while let Some(event) = events.next().await {
+            let event = event?;
+            trace(&event)?;
+            match event {
+                RequestEvent::Fulfilled { journal, seal, .. } => proof = Some((journal, seal)),
+                RequestEvent::Posted {
+                    request_id,
+                    tx_hash,
+                } => {
+                    let (journal, seal) = proof.context("request posted before its fulfillment")?;
+                    return Ok(Proved {
+                        request_id,
+                        journal,
+                        seal,
+                        tx_hash,
+                    });
+                }
+                _ => (),
+            }
+        }
+        Err(anyhow!(
+            "the events of the request ended before it was posted"
+        ))
+    }
+
+    /// Submit the request and wait for it to be fulfilled, without posting its proof.
+    pub async fn prove(self) -> Result<Proof> {
+        let (client, schedule) = (self.client, self.schedule);
+        let (request, from_block) = self.submit().await?;
+        trace(&RequestEvent::Submitted {
+            request_id: request.id,
+            expires_at: request.expires_at(),
+        })?;
+        let mut events = std::pin::pin!(wait::events(client, request, from_block, schedule));
+        while let Some(event) = events.next().await {
+            let event = event?;
+            trace(&event)?;
+            if let RequestEvent::Fulfilled {
+                request_id,
+                journal,
+                seal,
+            } = event
+            {
+                return Ok(Proof {
+                    request_id,
+                    journal,
+                    seal,
+                });
+            }
+        }
+        Err(anyhow!(
+            "the events of the request ended before it was fulfilled"
+        ))
+    }
+
+    /// Run the flow of [ProveAndPost::run] as a stream of the events of the request:
+    /// [RequestEvent::Submitted], [RequestEvent::Locked] unless the request is fulfilled between
+    /// two status checks, and [RequestEvent::Fulfilled] then [RequestEvent::Posted], or
+    /// [RequestEvent::Expired]. The stream ends after an error.
+    pub fn events<F, Fut>(self, post: F) -> impl Stream<Item = Result<RequestEvent>> + 'a
+    where
+        F: FnOnce(Bytes, Bytes) -> Fut + 'a,
+        Fut: Future<Output = Result<B256>> + 'a,
+    {
+        enum Step<'a, F> {
+            Submit(ProveAndPost<'a>, F),
+            Wait(LocalBoxStream<'a, Result<RequestEvent>>, F),
+            Post(U256, Bytes, Bytes, F),
+            Done,
+        }
+
+        stream::unfold(Step::Submit(self, post), |step| async move {
+            match step {
+                Step::Submit(this, post) => {
+                    let (client, schedule) = (this.client, this.schedule);
+                    match this.submit().await {
+                        Ok((request, from_block)) => {
+                            let event = RequestEvent::Submitted {
+                                request_id: request.id,
+                                expires_at: request.expires_at(),
+                            };
+                            let events = wait::events(client, request, from_block, schedule);
+                            Some((Ok(event), Step::Wait(events.boxed_local(), post)))
+                        }
+                        Err(e) => Some((Err(e), Step::Done)),
+                    }
+                }
+                Step::Wait(mut events, post) => match events.next().await? {
+                    Ok(RequestEvent::Fulfilled {
+                        request_id,
+                        journal,
+                        seal,
+                    }) => {
+                        let event = RequestEvent::Fulfilled {
+                            request_id,
+                            journal: journal.clone(),
+                            seal: seal.clone(),
+                        };
+                        Some((Ok(event), Step::Post(request_id, journal, seal, post)))
+                    }
+                    Ok(event) => Some((Ok(event), Step::Wait(events, post))),
+                    Err(e) => Some((Err(e), Step::Done)),
+                },
+                Step::Post(request_id, journal, seal, post) => {
+                    let event = post(journal, seal)
+                        .await
+                        .context("failed to post the proof")
+                        .map(|tx_hash| RequestEvent::Posted {
+                            request_id,
+                            tx_hash,
+                        });
+                    Some((event, Step::Done))
+                }
+                Step::Done => None,
+            }
+        })
+    }
+
+    /// Build the request, then submit it onchain. Returns the request, and the block from which
+    /// its events are looked up.
+    async fn submit(self) -> Result<(ProofRequest, u64)> {
+        let client = self.client;
+        let params = client
+            .new_request()
+            .with_program(self.program)
+            .with_stdin(self.stdin)
+            .with_requirements(self.requirements)
+            .with_offer(self.offer);
+        let request = client
+            .build_request(params)
+            .await
+            .context("failed to build the request")
+            .context(Failure::Preflight)?;
+
+        let provider = client.provider();
+        let from_block = provider.get_block_number().await?;
+        client
+            .submit_request_onchain(&request)
+            .await
+            .context("failed to submit request")
+            .context(Failure::TxReverted)?;
+        Ok((request, from_block))
+    }
+}
+
+/// Log an event of the request, or return the error of an expired request.
+fn trace(event: &RequestEvent) -> Result<()> {
+    match event {
+        RequestEvent::Submitted { request_id, .. } => {
+            tracing::info!("Waiting for request {:x} to be fulfilled", request_id)
+        }
+        RequestEvent::Locked { request_id, lock } => match lock {
+            Some(lock) => tracing::info!("Request {request_id:x} locked by {}", lock.prover),
+            None => tracing::info!("Request {request_id:x} locked"),
+        },
+        RequestEvent::Fulfilled { request_id, .. } => {
+            tracing::info!("Request {:x} fulfilled", request_id)
+        }
+        &RequestEvent::Expired {
+            request_id,
+            slashed,
+        } => {
+            let failure = if slashed {
+                Failure::Slashed
+            } else {
+                Failure::Expired
+            };
+            return Err(MarketError::RequestHasExpired(request_id)).context(failure);
+        }
+        RequestEvent::Posted {
+            request_id,
+            tx_hash,
+        } => tracing::info!("Proof of request {request_id:x} posted in {tx_hash}"),
+    }
+    Ok(())
+}
+
+/// Prove any guest on a raw input, and write its journal and seal.
+pub async fn run(args: ProveArgs) -> Result<()> {
+    let schedule = wait::PollSchedule::new(
+        Duration::from_secs(args.poll_interval),
+        Duration::from_secs(args.max_poll_interval),
+    )
+    .context(Failure::Config)?;
+    let program = std::fs::read(&args.elf)
+        .with_context(|| format!("failed to read the ELF {}", args.elf.display()))
+        .context(Failure::Config)?;
+    let input = args.input.read().context(Failure::Config)?;
+    let client = args.client.build_client().await.context(Failure::Config)?;
+    let mut requirements = RequirementParams::default();
+    if args.groth16 {
+        // The selector the request builder sets for a Groth16 proof.
+        requirements.selector = Some(FixedBytes::from(Selector::groth16_latest() as u32));
+    }
+    let proof = ProveAndPost::new(&client, program, input.unwrap_or_default())
+        .with_requirements(requirements)
+        .with_offer(args.offer)
+        .with_poll_schedule(schedule)
+        .prove()
+        .await?;
+
+    let json = serde_json::to_string_pretty(&proof)? + "\n";
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, json)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            tracing::info!(
+                "Wrote the proof of request {:x} to {}",
+                proof.request_id,
+                path.display()
+            );
+        }
+        None => print!("{json}"),
+    }
+    Ok(())
+}
+
+/// Prove the program run on `stdin` with the default requirements and offer, and post the proof
+/// with `post`. See [ProveAndPost] to override the defaults.
+pub async fn prove_and_post<'a, F, Fut>(
+    client: &'a StandardClient,
+    program: impl Into<Cow<'static, [u8]>>,
+    stdin: impl Into<Vec<u8>>,
+    post: F,
+) -> Result<Proved>
+where
+    F: FnOnce(Bytes, Bytes) -> Fut + 'a,
+    Fut: Future<Output = Result<B256>> + 'a,
+{
+    ProveAndPost::new(client, program, stdin).run(post).await
+}
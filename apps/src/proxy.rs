@@ -0,0 +1,120 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Local HTTP proxies standing in for remote services.
+//!
+//! The clients built by the Boundless SDK only take URLs, so behavior is added to the calls to a
+//! remote service by pointing the client at a local proxy that handles each call.
+
+use std::{convert::Infallible, future::Future};
+
+use anyhow::{Context, Result};
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::{CONTENT_LENGTH, HOST, TRANSFER_ENCODING},
+    http::request::Parts,
+    server::conn::http1,
+    service::service_fn,
+    HeaderMap, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Response of a proxy to a call.
+pub type ProxyResponse = Result<Response<Full<Bytes>>, Infallible>;
+
+/// Start a proxy on a local port, serving each call with `handle`.
+///
+/// Returns the URL to use in place of `upstream`: the same URL, on the proxy.
+pub async fn serve<H, F>(name: &'static str, upstream: Url, handle: H) -> Result<Url>
+where
+    H: Fn(Request<Incoming>) -> F + Clone + Send + 'static,
+    F: Future<Output = ProxyResponse> + Send + 'static,
+{
+    let listener = TcpListener::bind(("127.0.0.1", 0))
+        .await
+        .with_context(|| format!("failed to start the {name} proxy"))?;
+    let port = listener.local_addr()?.port();
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    tracing::warn!("Proxy for {name} failed to accept a connection: {e}");
+                    continue;
+                }
+            };
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service_fn(handle))
+                    .await
+                {
+                    tracing::debug!("Proxy connection for {name} closed: {e}");
+                }
+            });
+        }
+    });
+
+    let mut url = upstream;
+    url.set_scheme("http")
+        .and_then(|_| url.set_host(Some("127.0.0.1")).map_err(|_| ()))
+        .and_then(|_| url.set_port(Some(port)))
+        .map_err(|_| anyhow::anyhow!("cannot proxy {name} URL {url}"))?;
+    Ok(url)
+}
+
+/// Send a call made to a proxy to the origin of `upstream` with `client`. Returns the status,
+/// headers and body of the response.
+pub async fn forward(
+    client: &reqwest::Client,
+    mut upstream: Url,
+    mut parts: Parts,
+    body: Bytes,
+) -> reqwest::Result<(StatusCode, HeaderMap, Bytes)> {
+    upstream.set_path(parts.uri.path());
+    upstream.set_query(parts.uri.query());
+    parts.headers.remove(HOST);
+    let response = client
+        .request(parts.method, upstream)
+        .headers(parts.headers)
+        .body(body)
+        .send()
+        .await?;
+    let status = response.status();
+    let mut headers = response.headers().clone();
+    let body = response.bytes().await?;
+    headers.remove(CONTENT_LENGTH);
+    headers.remove(TRANSFER_ENCODING);
+    Ok((status, headers, body))
+}
+
+/// Response of a proxy to a call forwarded with [forward].
+pub fn respond(result: reqwest::Result<(StatusCode, HeaderMap, Bytes)>) -> Response<Full<Bytes>> {
+    match result {
+        Ok((status, headers, body)) => {
+            let mut response = Response::new(Full::new(body));
+            *response.status_mut() = status;
+            *response.headers_mut() = headers;
+            response
+        }
+        Err(e) => {
+            let mut response = Response::new(Full::new(Bytes::from(e.to_string())));
+            *response.status_mut() = StatusCode::BAD_GATEWAY;
+            response
+        }
+    }
+}
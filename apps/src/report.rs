@@ -0,0 +1,207 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Spend report aggregated from the local request history.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use alloy::primitives::{utils::format_units, U256};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::Args;
+
+use crate::{history::RequestRecord, state_dir, usd::PriceArgs};
+
+/// Group name for requests that don't have the tag used for grouping.
+const UNTAGGED: &str = "(none)";
+
+/// Arguments of the report command.
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Only include requests submitted at or after this time, e.g. 2025-01-01T00:00:00Z.
+    #[clap(long)]
+    since: Option<DateTime<Utc>>,
+    /// Only include requests submitted before this time.
+    #[clap(long)]
+    until: Option<DateTime<Utc>>,
+    /// Tag key to group the requests by. All requests are reported together if unspecified.
+    #[clap(long)]
+    group_by: Option<String>,
+    /// Print one JSON object per group, with amounts in ETH.
+    #[clap(long)]
+    json: bool,
+    #[clap(flatten)]
+    state: state_dir::StateArgs,
+    /// Source of the ETH/USD price, to add the costs in USD at the current price.
+    #[clap(flatten, next_help_heading = "USD Prices")]
+    prices: PriceArgs,
+}
+
+/// Spend of a group of requests.
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    requests: u64,
+    fulfilled: u64,
+    expired: u64,
+    /// Total price paid for proofs, in wei.
+    proof_cost: U256,
+    /// Total gas cost of the `set` transactions, in wei.
+    gas_cost: U256,
+    /// Total maximum price of the expired requests, in wei.
+    expired_value: U256,
+}
+
+impl Summary {
+    fn add(&mut self, record: &RequestRecord) {
+        let wei = |value: &Option<String>| {
+            value
+                .as_deref()
+                .and_then(|value| U256::from_str(value).ok())
+                .unwrap_or_default()
+        };
+        self.requests += 1;
+        match record.state.as_str() {
+            "fulfilled" | "posted" => self.fulfilled += 1,
+            "expired" | "slashed" => {
+                self.expired += 1;
+                self.expired_value += U256::from_str(&record.max_price).unwrap_or_default();
+            }
+            _ => {}
+        }
+        self.proof_cost += wei(&record.price);
+        self.gas_cost += wei(&record.set_gas_cost);
+        for post in &record.posts {
+            self.gas_cost += post.gas_cost.unwrap_or_default();
+        }
+    }
+}
+
+fn summarize(records: &[RequestRecord], group_by: Option<&str>) -> BTreeMap<String, Summary> {
+    let mut groups = BTreeMap::<String, Summary>::new();
+    for record in records {
+        let group = match group_by {
+            Some(key) => record.tags.get(key).map(String::as_str).unwrap_or(UNTAGGED),
+            None => "all",
+        };
+        groups.entry(group.to_string()).or_default().add(record);
+    }
+    groups
+}
+
+pub async fn run(args: ReportArgs) -> Result<()> {
+    let history = args.state.open(state_dir::Access::Read)?;
+    let records = history.submitted_between(
+        args.since.map_or(0, |t| t.timestamp()),
+        args.until.map_or(i64::MAX, |t| t.timestamp()),
+    )?;
+
+    let eth_usd = args.prices.eth_usd(None).await?;
+    let eth = |wei: U256| format_units(wei, "ether");
+    if !args.json {
+        print!("group\trequests\tfulfilled\texpired\tproofs (ETH)\tgas (ETH)\texpired value (ETH)");
+        match eth_usd {
+            Some(_) => println!("\tproofs (USD)\tgas (USD)"),
+            None => println!(),
+        }
+    }
+    for (group, summary) in summarize(&records, args.group_by.as_deref()) {
+        if args.json {
+            let mut value = serde_json::json!({
+                "group": group,
+                "requests": summary.requests,
+                "fulfilled": summary.fulfilled,
+                "expired": summary.expired,
+                "proof_cost_eth": eth(summary.proof_cost)?,
+                "gas_cost_eth": eth(summary.gas_cost)?,
+                "expired_value_eth": eth(summary.expired_value)?,
+            });
+            if let Some(eth_usd) = eth_usd {
+                value["proof_cost_usd"] = eth_usd.value(summary.proof_cost)?.into();
+                value["gas_cost_usd"] = eth_usd.value(summary.gas_cost)?.into();
+            }
+            println!("{value}");
+        } else {
+            print!(
+                "{group}\t{}\t{}\t{}\t{}\t{}\t{}",
+                summary.requests,
+                summary.fulfilled,
+                summary.expired,
+                eth(summary.proof_cost)?,
+                eth(summary.gas_cost)?,
+                eth(summary.expired_value)?
+            );
+            match eth_usd {
+                Some(eth_usd) => println!(
+                    "\t{}\t{}",
+                    eth_usd.format(summary.proof_cost)?,
+                    eth_usd.format(summary.gas_cost)?
+                ),
+                None => println!(),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(state: &str, tag: Option<&str>, price: Option<u64>) -> RequestRecord {
+        RequestRecord {
+            request_id: String::new(),
+            submitted_at: 0,
+            expires_at: 0,
+            number: "2".to_string(),
+            state: state.to_string(),
+            set_tx_hash: None,
+            max_price: "50".to_string(),
+            price: price.map(|price| price.to_string()),
+            set_gas_cost: price.map(|_| "7".to_string()),
+            timings: None,
+            set_to: None,
+            set_calldata: None,
+            set_cost_estimate: None,
+            slash: None,
+            posts: Vec::new(),
+            tags: tag
+                .map(|tag| [("customer".to_string(), tag.to_string())].into())
+                .unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn groups_by_tag() {
+        let records = [
+            record("posted", Some("acme"), Some(10)),
+            record("posted", Some("acme"), Some(20)),
+            record("expired", Some("acme"), None),
+            record("fulfilled", None, Some(5)),
+        ];
+        let groups = summarize(&records, Some("customer"));
+        assert_eq!(
+            groups["acme"],
+            Summary {
+                requests: 3,
+                fulfilled: 2,
+                expired: 1,
+                proof_cost: U256::from(30),
+                gas_cost: U256::from(14),
+                expired_value: U256::from(50),
+            }
+        );
+        assert_eq!(groups[UNTAGGED].requests, 1);
+        assert_eq!(summarize(&records, None)["all"].requests, 4);
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Request IDs chosen by the submitter, behind `--request-index`.
+//!
+//! A request ID is the address of the client and an index of 32 bits. The SDK picks a random
+//! index unused by the market. Submitters coordinating the ID space over several processes set
+//! the index instead, and the numbers of a run take consecutive indexes from it. Only one request
+//! of a given ID can ever be fulfilled, so an index is refused if it is in the history, or if the
+//! market already knows a request with its ID: locked, fulfilled, or submitted onchain within the
+//! blocks the SDK searches for submissions.
+
+use alloy::primitives::U256;
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{
+    contracts::{boundless_market::MarketError, RequestStatus},
+    ProofRequest, RequestId, StandardClient,
+};
+
+use crate::{failure::Failure, history::History};
+
+/// Index of the request of the `offset`-th input of a run, from the first index given.
+pub fn index(first: u32, offset: usize) -> Result<u32> {
+    u32::try_from(offset)
+        .ok()
+        .and_then(|offset| first.checked_add(offset))
+        .ok_or_else(|| anyhow!("request index {first} + {offset} is over u32::MAX"))
+        .context(Failure::Config)
+}
+
+/// Give the request the ID of its client with the index, unless a request with that ID exists.
+/// The client, and the flags, of the ID built by the SDK are kept.
+pub async fn assign(
+    client: &StandardClient,
+    history: &History,
+    request: &mut ProofRequest,
+    index: u32,
+) -> Result<()> {
+    let mut request_id = RequestId::from_lossy(request.id);
+    request_id.index = index;
+    let request_id = request_id.into();
+    ensure_unused(client, history, request_id).await?;
+    tracing::debug!("Using request index {index}, ID {request_id:x}");
+    request.id = request_id;
+    Ok(())
+}
+
+/// Fail with a configuration error if a request with the ID was already recorded or submitted.
+async fn ensure_unused(client: &StandardClient, history: &History, request_id: U256) -> Result<()> {
+    let collision = |what: &str| {
+        Err(anyhow!(
+            "request {request_id:x} {what}, pick another --request-index"
+        ))
+        .context(Failure::Config)
+    };
+    if history.contains(request_id)? {
+        return collision("is already in the history");
+    }
+    let market = &client.boundless_market;
+    let status = market
+        .get_status(request_id, None)
+        .await
+        .context("failed to get the status of the request ID")?;
+    if status != RequestStatus::Unknown {
+        return collision(&format!("is already known to the market, {status:?}"));
+    }
+    match market.get_submitted_request(request_id, None).await {
+        Ok(_) => collision("was already submitted"),
+        Err(MarketError::RequestNotFound(_)) => Ok(()),
+        Err(e) => Err(e).context("failed to look up the submissions of the request ID"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_consecutive_indexes() {
+        assert_eq!(index(7, 0).unwrap(), 7);
+        assert_eq!(index(7, 3).unwrap(), 10);
+        let error = index(u32::MAX, 1).unwrap_err();
+        assert_eq!(Failure::of(&error), Some(Failure::Config));
+    }
+}
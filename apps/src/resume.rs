@@ -0,0 +1,281 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Finishing the flow of requests left in flight, e.g. by a crash after their submission.
+//!
+//! The history records each request submitted, and its progress. A request still waiting for
+//! its fulfillment is waited for again, and a fulfilled one gets its seal from the fulfillment
+//! event, then the result of both is posted to the EvenNumber contract. Requests recorded
+//! before the history kept the requests themselves cannot be resumed.
+
+use std::time::Duration;
+
+use alloy::primitives::U256;
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{ProofRequest, StandardClient};
+use clap::Args;
+use futures_util::{stream, StreamExt};
+
+use crate::{
+    ens,
+    failure::Failure,
+    fees,
+    history::{History, InFlightRequest, RequestState, Timings},
+    journal_check, lock_alert, post_result, post_to_others, state_dir, tui, wait,
+    wait_for_fulfillment, ClientArgs, Consumer, ConsumerArgs, SubmittedRequest,
+};
+
+/// Arguments of the resume command.
+#[derive(Args, Clone, Debug)]
+pub struct ResumeArgs {
+    /// Only resume the request with this ID, as logged by the submit command. Can be repeated.
+    #[clap(long = "request-id", value_parser = tui::parse_request_id)]
+    request_ids: Vec<U256>,
+    /// Address, or ENS name, of the EvenNumber contract.
+    #[clap(short, long, env)]
+    even_number_address: ens::NameOrAddress,
+    #[clap(flatten)]
+    state: state_dir::StateArgs,
+    /// Interval, in seconds, between status checks of the requests while their status is likely
+    /// to change. The interval backs off up to `--max-poll-interval` otherwise.
+    #[clap(long, env, default_value_t = 5)]
+    poll_interval: u64,
+    /// Maximum interval, in seconds, between status checks of the requests.
+    #[clap(long, env, default_value_t = 60)]
+    max_poll_interval: u64,
+    #[clap(flatten)]
+    lock_alert: lock_alert::LockAlertArgs,
+    #[clap(flatten)]
+    client: ClientArgs,
+    #[clap(flatten, next_help_heading = "Consumer Chain")]
+    consumer: ConsumerArgs,
+}
+
+/// Finish the flow of every request of the history left in flight, or of the given ones.
+pub async fn run(args: ResumeArgs) -> Result<()> {
+    let schedule = wait::PollSchedule::new(
+        Duration::from_secs(args.poll_interval),
+        Duration::from_secs(args.max_poll_interval),
+    )
+    .context(Failure::Config)?;
+    let history = args.state.open(state_dir::Access::Exclusive)?;
+    let requests = select(history.in_flight()?, &args.request_ids);
+    if requests.is_empty() {
+        tracing::info!("No request to resume");
+        return Ok(());
+    }
+    tracing::info!("Resuming {} requests", requests.len());
+
+    let client = args.client.build_client().await.context(Failure::Config)?;
+    let consumer = args
+        .consumer
+        .connect(&client, &args.client.private_key, &args.even_number_address)
+        .await
+        .context(Failure::Config)?;
+    let alert = args.lock_alert.alert();
+    let count = requests.len();
+    // The requests are all in flight already, so they are waited for at once.
+    let results: Vec<_> = stream::iter(requests)
+        .map(|request| {
+            let request_id = request.request_id;
+            let resumed = resume(
+                &client,
+                &consumer,
+                &history,
+                &args.consumer.fees,
+                request,
+                schedule,
+                alert.as_ref(),
+            );
+            async move {
+                resumed
+                    .await
+                    .inspect_err(|e| tracing::error!("Request {request_id:x} failed: {e:?}"))
+            }
+        })
+        .buffer_unordered(count)
+        .collect()
+        .await;
+    let mut errors = results.into_iter().filter_map(Result::err);
+    match errors.next() {
+        None => Ok(()),
+        Some(e) if count == 1 => Err(e),
+        Some(e) => Err(e).context(format!("{} of {count} requests failed", errors.count() + 1)),
+    }
+}
+
+/// The requests in flight to resume: all of them, or the ones with the given IDs.
+fn select(mut requests: Vec<InFlightRequest>, request_ids: &[U256]) -> Vec<InFlightRequest> {
+    if !request_ids.is_empty() {
+        requests.retain(|request| request_ids.contains(&request.request_id));
+    }
+    requests
+}
+
+/// What resuming a request in flight takes.
+#[derive(Debug)]
+enum Step<'a> {
+    /// Wait for its fulfillment, looking up its events from the block, then post its result.
+    Wait {
+        request: &'a ProofRequest,
+        from_block: u64,
+    },
+    /// Post the result of its fulfillment.
+    Post,
+    /// It was recorded without the request, and can't be waited for.
+    Skip,
+}
+
+fn step(in_flight: &InFlightRequest) -> Step<'_> {
+    match (in_flight.state, &in_flight.request, in_flight.from_block) {
+        (RequestState::Submitted, Some(request), Some(from_block)) => Step::Wait {
+            request,
+            from_block,
+        },
+        (RequestState::Submitted, _, _) => Step::Skip,
+        _ => Step::Post,
+    }
+}
+
+/// Wait for the request to be fulfilled, unless it already is, then post its result.
+async fn resume(
+    client: &StandardClient,
+    consumer: &Consumer,
+    history: &History,
+    fees: &fees::FeeArgs,
+    in_flight: InFlightRequest,
+    schedule: wait::PollSchedule,
+    alert: Option<&lock_alert::LockAlert>,
+) -> Result<()> {
+    let request_id = in_flight.request_id;
+    let number: u32 = in_flight
+        .number
+        .parse()
+        .with_context(|| format!("invalid number {} in the history", in_flight.number))?;
+    let mut timings = Timings::default();
+    let (journal, seal) = match step(&in_flight) {
+        Step::Skip => {
+            return Err(anyhow!(
+                "request {request_id:x} was recorded without the request, and can't be resumed"
+            ))
+            .context(Failure::Config);
+        }
+        Step::Wait {
+            request,
+            from_block,
+        } => {
+            tracing::info!("Resuming request {request_id:x}, for the number {number}");
+            let submitted = SubmittedRequest {
+                request_id,
+                expires_at: request.expires_at(),
+                submitted_at: in_flight.submitted_at,
+                from_block,
+            };
+            let fulfilled = wait_for_fulfillment(
+                client,
+                history,
+                request,
+                &submitted,
+                schedule,
+                alert,
+                &consumer.finality,
+                &mut timings,
+            )
+            .await?;
+            (fulfilled.journal, fulfilled.seal)
+        }
+        Step::Post => {
+            tracing::info!("Posting the result of fulfilled request {request_id:x}");
+            client
+                .boundless_market
+                .get_request_fulfillment(request_id)
+                .await
+                .context("failed to get the fulfillment of the request")?
+        }
+    };
+    journal_check::check(in_flight.request.as_ref(), &journal, number)?;
+    let posted = post_result(
+        consumer,
+        history,
+        fees,
+        request_id,
+        number,
+        seal.clone(),
+        &mut timings,
+    )
+    .await?;
+    tracing::info!(
+        "Request {request_id:x} published {number} in tx {}",
+        posted.tx_hash
+    );
+    post_to_others(consumer, history, fees, request_id, number, &seal).await
+}
+
+#[cfg(test)]
+mod tests {
+    use boundless_market::Offer;
+
+    use super::*;
+
+    fn in_flight(id: u64, state: RequestState, request: bool) -> InFlightRequest {
+        InFlightRequest {
+            request_id: U256::from(id),
+            submitted_at: 10,
+            number: "2".to_string(),
+            state,
+            request: request.then(|| ProofRequest {
+                id: U256::from(id),
+                ..crate::wait::tests::new_request(Offer::default())
+            }),
+            from_block: request.then_some(100),
+        }
+    }
+
+    #[test]
+    fn selects_the_requests_to_resume() {
+        let ids = |requests: Vec<InFlightRequest>| -> Vec<_> {
+            requests.iter().map(|request| request.request_id).collect()
+        };
+        let requests = || {
+            (1..=3)
+                .map(|id| in_flight(id, RequestState::Submitted, true))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(select(requests(), &[])).len(), 3);
+        assert_eq!(
+            ids(select(requests(), &[U256::from(3), U256::from(1)])),
+            [U256::from(1), U256::from(3)]
+        );
+        assert!(select(requests(), &[U256::from(4)]).is_empty());
+    }
+
+    #[test]
+    fn waits_for_submitted_requests_and_posts_fulfilled_ones() {
+        let submitted = in_flight(1, RequestState::Submitted, true);
+        assert!(matches!(
+            step(&submitted),
+            Step::Wait {
+                request,
+                from_block: 100
+            } if request.id == U256::from(1)
+        ));
+        let fulfilled = in_flight(2, RequestState::Fulfilled, true);
+        assert!(matches!(step(&fulfilled), Step::Post));
+        // The seal of a fulfilled request is found without the request.
+        let fulfilled = in_flight(3, RequestState::Fulfilled, false);
+        assert!(matches!(step(&fulfilled), Step::Post));
+        let unrecorded = in_flight(4, RequestState::Submitted, false);
+        assert!(matches!(step(&unrecorded), Step::Skip));
+    }
+}
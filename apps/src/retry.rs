@@ -0,0 +1,419 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retries of the calls to the RPC endpoint and the storage provider failing with a transient
+//! error, e.g. a 429 status or a connection reset, with an exponential backoff.
+//!
+//! Errors are told apart by the HTTP status of the response, the code of the JSON-RPC error, or
+//! the kind of the I/O error of the connection, found in their chain of sources.
+//!
+//! The policy is set by the options of the client when it is built, and held by the
+//! [RetryLayer] of its RPC transport, which retries the calls to the RPC endpoint. Reads and
+//! uploads are retried on any transient error. Calls sending a transaction are only retried when
+//! the endpoint refused them, e.g. by rate limiting them, so that a transaction is never sent
+//! twice.
+
+use std::{
+    error::Error,
+    future::Future,
+    io,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use alloy::{
+    contract,
+    providers::Provider,
+    rpc::json_rpc::{ErrorPayload, RequestPacket, ResponsePacket},
+    transports::{
+        BoxTransport, HttpError, IntoBoxTransport, RpcError, TransportError, TransportErrorKind,
+        TransportFut,
+    },
+};
+use boundless_market::contracts::TxnErr;
+use clap::Args;
+use tower::{Layer, Service};
+
+/// JSON-RPC methods sending a transaction.
+const SEND_METHODS: &[&str] = &["eth_sendRawTransaction", "eth_sendTransaction"];
+
+/// Why a call failed with a transient error.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Transient {
+    /// The endpoint refused the call, e.g. by rate limiting it, without processing it.
+    Refused,
+    /// The call may or may not have been processed, e.g. the connection was reset.
+    Interrupted,
+}
+
+/// Options of the retries of failed calls.
+#[derive(Args, Clone, Debug)]
+pub struct RetryArgs {
+    /// Number of attempts of a call to the RPC endpoint or the storage provider failing with a
+    /// transient error, e.g. a 429 status or a connection reset.
+    #[clap(long, env, default_value_t = RetryPolicy::default().attempts, value_parser = clap::value_parser!(u32).range(1..))]
+    retry_attempts: u32,
+    /// Delay in milliseconds before retrying a failed call, doubled after each retry.
+    #[clap(long, env, default_value_t = 1000)]
+    retry_backoff: u64,
+    /// Maximum delay in milliseconds before retrying a failed call.
+    #[clap(long, env, default_value_t = 30_000)]
+    max_retry_backoff: u64,
+}
+
+impl RetryArgs {
+    /// Retry policy of the options.
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            attempts: self.retry_attempts,
+            backoff: Duration::from_millis(self.retry_backoff),
+            max_backoff: Duration::from_millis(self.max_retry_backoff),
+        }
+    }
+}
+
+/// How many times, and how long after, a call failing with a transient error is retried.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of attempts of the call, including the first one.
+    pub attempts: u32,
+    /// Delay before the first retry, doubled after each retry.
+    pub backoff: Duration,
+    /// Maximum delay before a retry.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// The defaults of the `--retry-*` options.
+    fn default() -> Self {
+        Self {
+            attempts: 4,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy of the RPC transport of the provider, or the default one if it has no
+    /// [RetryLayer].
+    pub fn of(provider: &impl Provider) -> RetryPolicy {
+        provider
+            .client()
+            .transport()
+            .as_any()
+            .downcast_ref::<RetryService>()
+            .map(|service| service.policy)
+            .unwrap_or_default()
+    }
+
+    /// Run a call that can safely be repeated, e.g. an upload, retrying it on transient errors.
+    pub async fn transient<T, E, F, Fut>(&self, what: &str, call: F) -> Result<T, E>
+    where
+        E: Error + 'static,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.run(what, is_transient, call).await
+    }
+
+    /// Delay before the given retry, starting at 1, before jitter.
+    fn delay(&self, retry: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(retry - 1))
+            .min(self.max_backoff)
+    }
+
+    async fn run<T, E, F, Fut>(
+        &self,
+        what: &str,
+        retryable: fn(&(dyn Error + 'static)) -> bool,
+        mut call: F,
+    ) -> Result<T, E>
+    where
+        E: Error + 'static,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match call().await {
+                Err(e) if attempt < self.attempts && retryable(&e) => {
+                    let delay = self.delay(attempt).mul_f64(rand::random_range(0.8..1.2));
+                    tracing::warn!(
+                        "Transient error {what}, retrying in {delay:.1?} ({attempt}/{}): {e}",
+                        self.attempts - 1
+                    );
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Layer of an RPC transport retrying the calls failing with a transient error, with a policy.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryLayer {
+    policy: RetryPolicy,
+}
+
+impl RetryLayer {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self { policy }
+    }
+}
+
+impl<S: IntoBoxTransport> Layer<S> for RetryLayer {
+    type Service = RetryService;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryService {
+            inner: inner.into_box_transport(),
+            policy: self.policy,
+        }
+    }
+}
+
+/// RPC transport retrying the calls sent over the inner transport.
+#[derive(Clone, Debug)]
+pub struct RetryService {
+    inner: BoxTransport,
+    policy: RetryPolicy,
+}
+
+impl Service<RequestPacket> for RetryService {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let (inner, policy) = (self.inner.clone(), self.policy);
+        let retryable = match request.method_names().any(|m| SEND_METHODS.contains(&m)) {
+            true => is_refused,
+            false => is_transient,
+        };
+        let what = format!(
+            "calling {}",
+            request.method_names().collect::<Vec<_>>().join(", ")
+        );
+        Box::pin(async move {
+            policy
+                .run(&what, retryable, || {
+                    let (mut inner, request) = (inner.clone(), request.clone());
+                    async move {
+                        // Errors in the response, e.g. rate limits, are retried as well.
+                        let response = inner.call(request).await?;
+                        match response.as_error() {
+                            Some(payload) => Err(RpcError::ErrorResp(payload.clone())),
+                            None => Ok(response),
+                        }
+                    }
+                })
+                .await
+        })
+    }
+}
+
+/// Whether the error, or one of its sources, is transient.
+fn is_transient(error: &(dyn Error + 'static)) -> bool {
+    cause(error).is_some()
+}
+
+/// Whether the error, or one of its sources, is a refusal of the call by the endpoint.
+pub(crate) fn is_refused(error: &(dyn Error + 'static)) -> bool {
+    cause(error) == Some(Transient::Refused)
+}
+
+/// Why the error is transient, from the first of its sources that tells, if any.
+fn cause(error: &(dyn Error + 'static)) -> Option<Transient> {
+    std::iter::successors(Some(error), |&e| e.source()).find_map(|source| {
+        // The errors of the market and of contract calls hide the transport errors they wrap
+        // from their sources, so those are looked into.
+        if let Some(e) = source.downcast_ref::<TxnErr>() {
+            match e {
+                TxnErr::ContractErr(e) => cause(e),
+                TxnErr::MissingData(code, message) => from_payload(&ErrorPayload {
+                    code: *code,
+                    message: message.clone().into(),
+                    data: None,
+                }),
+                _ => None,
+            }
+        } else if let Some(contract::Error::TransportError(e)) = source.downcast_ref() {
+            from_rpc(e)
+        } else if let Some(e) = source.downcast_ref::<RpcError<TransportErrorKind>>() {
+            from_rpc(e)
+        } else if let Some(e) = source.downcast_ref::<HttpError>() {
+            from_status(e.status)
+        } else if let Some(e) = source.downcast_ref::<reqwest::Error>() {
+            match e.status() {
+                Some(status) => from_status(status.as_u16()),
+                None => e.is_timeout().then_some(Transient::Interrupted),
+            }
+        } else if let Some(e) = source.downcast_ref::<io::Error>() {
+            match e.kind() {
+                io::ErrorKind::ConnectionRefused => Some(Transient::Refused),
+                io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::UnexpectedEof
+                | io::ErrorKind::TimedOut => Some(Transient::Interrupted),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    })
+}
+
+fn from_rpc(error: &RpcError<TransportErrorKind>) -> Option<Transient> {
+    match error {
+        RpcError::ErrorResp(payload) => from_payload(payload),
+        RpcError::Transport(TransportErrorKind::HttpError(e)) => from_status(e.status),
+        RpcError::Transport(TransportErrorKind::Custom(e)) => cause(e.as_ref()),
+        RpcError::Transport(TransportErrorKind::BackendGone) => Some(Transient::Interrupted),
+        _ => None,
+    }
+}
+
+/// A JSON-RPC error is transient when it is one of the rate limits of the RPC providers.
+fn from_payload(payload: &ErrorPayload) -> Option<Transient> {
+    payload.is_retry_err().then_some(Transient::Refused)
+}
+
+fn from_status(status: u16) -> Option<Transient> {
+    match status {
+        429 => Some(Transient::Refused),
+        502..=504 => Some(Transient::Interrupted),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use alloy::{
+        providers::ProviderBuilder,
+        rpc::client::RpcClient,
+        transports::mock::{Asserter, MockTransport},
+    };
+
+    use super::*;
+
+    #[test]
+    fn classifies_errors() {
+        let rate_limited = TransportErrorKind::http_error(429, "Too Many Requests".to_string());
+        assert!(is_transient(&rate_limited) && is_refused(&rate_limited));
+        let unavailable = TransportErrorKind::http_error(503, String::new());
+        assert!(is_transient(&unavailable) && !is_refused(&unavailable));
+        let server_error = TransportErrorKind::http_error(500, String::new());
+        assert!(!is_transient(&server_error));
+        let reset = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert!(is_transient(&reset) && !is_refused(&reset));
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert!(is_refused(&refused));
+        let reverted = io::Error::other("execution reverted: RequestIsLocked");
+        assert!(!is_transient(&reverted));
+        // Numbers in messages are not taken for statuses.
+        let reverted = io::Error::other("execution reverted: insufficient balance 429503");
+        assert!(!is_transient(&reverted));
+    }
+
+    #[test]
+    fn looks_into_contract_errors() {
+        let rate_limited = RpcError::ErrorResp(ErrorPayload {
+            code: -32005,
+            message: "limit exceeded".into(),
+            data: None,
+        });
+        let call = contract::Error::TransportError(rate_limited);
+        assert!(is_refused(&call));
+        let market = TxnErr::ContractErr(call);
+        assert!(is_refused(&market));
+        let market = TxnErr::MissingData(429, "Too Many Requests".to_string());
+        assert!(is_refused(&market));
+        let custom = TransportErrorKind::custom(io::Error::from(io::ErrorKind::TimedOut));
+        assert!(is_transient(&custom) && !is_refused(&custom));
+        let reverted = TxnErr::MissingData(3, "execution reverted".to_string());
+        assert!(!is_transient(&reverted));
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let policy = RetryPolicy::default();
+        let delays: Vec<_> = (1..=7).map(|retry| policy.delay(retry).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let policy = RetryPolicy {
+            attempts: 3,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let calls = AtomicU32::new(0);
+        let result = policy
+            .run("calling", is_transient, || async {
+                match calls.fetch_add(1, Ordering::Relaxed) {
+                    0 => Err(TransportErrorKind::http_error(503, String::new())),
+                    n => Ok(n),
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 1);
+
+        calls.store(0, Ordering::Relaxed);
+        let result: Result<(), _> = policy
+            .run("calling", is_transient, || async {
+                calls.fetch_add(1, Ordering::Relaxed);
+                Err(io::Error::from(io::ErrorKind::ConnectionReset))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn retries_the_calls_of_each_client_with_its_policy() {
+        let policy = RetryPolicy {
+            attempts: 2,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+        };
+        let asserter = Asserter::new();
+        asserter.push_failure(ErrorPayload {
+            code: 429,
+            message: "Too Many Requests".into(),
+            data: None,
+        });
+        asserter.push_success(&"0x1");
+        let client = RpcClient::builder()
+            .layer(RetryLayer::new(policy))
+            .transport(MockTransport::new(asserter), true);
+        let provider = ProviderBuilder::new().connect_client(client);
+        assert_eq!(provider.get_chain_id().await.unwrap(), 1);
+        assert_eq!(RetryPolicy::of(&provider), policy);
+
+        let other = ProviderBuilder::new().connect_client(RpcClient::mocked(Asserter::new()));
+        assert_eq!(RetryPolicy::of(&other), RetryPolicy::default());
+    }
+}
@@ -0,0 +1,261 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Readable messages for the reverts of the market, the verifiers and the EvenNumber contract.
+//!
+//! A reverted call or gas estimation returns the ABI-encoded custom error of the contract, which
+//! the RPC error only shows as `0x...` data. The errors of the contracts called by the app are
+//! declared here, and decoded by their selector into a message saying what went wrong and what
+//! to do about it. `EvenNumber.set` has no errors of its own: its reverts are those of the
+//! verifier router and the verifier the seal is routed to.
+
+use alloy::{
+    contract,
+    primitives::{Bytes, U256},
+    sol_types::{GenericContractError, SolInterface},
+    transports::{RpcError, TransportErrorKind},
+};
+use boundless_market::contracts::TxnErr;
+
+alloy::sol!(
+    /// Errors of the Boundless Market contract.
+    interface IMarketErrors {
+        error RequestIsLocked(uint256 requestId);
+        error RequestIsNotLockedOrPriced(uint256 requestId);
+        error RequestIsNotLocked(uint256 requestId);
+        error RequestIsFulfilled(uint256 requestId);
+        error RequestIsSlashed(uint256 requestId);
+        error RequestLockIsExpired(uint256 requestId, uint64 lockDeadline);
+        error RequestIsExpired(uint256 requestId, uint64 deadline);
+        error RequestIsNotExpired(uint256 requestId, uint64 deadline);
+        error InsufficientBalance(address account);
+        error InvalidSignature();
+        error InvalidRequest();
+        error TransferFailed();
+        error BatchSizeExceedsLimit(uint256 batchSize, uint256 limit);
+    }
+
+    /// Errors of the verifiers: the set verifier of the market, and the Groth16 verifier. The
+    /// market reverts with the same `SelectorMismatch` when a fulfillment has the wrong selector.
+    interface IVerifierErrors {
+        error VerificationFailed();
+        error SelectorMismatch(bytes4 received, bytes4 expected);
+    }
+
+    /// Errors of the verifier router.
+    interface IVerifierRouterErrors {
+        error SelectorUnknown(bytes4 selector);
+        error SelectorInUse(bytes4 selector);
+        error SelectorRemoved(bytes4 selector);
+    }
+);
+
+use IMarketErrors::IMarketErrorsErrors as MarketError;
+use IVerifierErrors::IVerifierErrorsErrors as VerifierError;
+use IVerifierRouterErrors::IVerifierRouterErrorsErrors as RouterError;
+
+/// Message for the first revert found in the chain of the error, if its data decodes to a known
+/// error, or to a revert reason string or a panic.
+pub fn explain(error: &anyhow::Error) -> Option<String> {
+    error
+        .chain()
+        .find_map(|cause| {
+            if let Some(e) = cause.downcast_ref::<TxnErr>() {
+                match e {
+                    TxnErr::BoundlessMarketErr(e) => Some(e.abi_encode().into()),
+                    TxnErr::SetVerifierErr(e) => Some(e.abi_encode().into()),
+                    TxnErr::ContractErr(e) => e.as_revert_data(),
+                    TxnErr::DecodeErr(_, data) => Some(data.clone()),
+                    _ => None,
+                }
+            } else if let Some(e) = cause.downcast_ref::<contract::Error>() {
+                e.as_revert_data()
+            } else if let Some(e) = cause.downcast_ref::<RpcError<TransportErrorKind>>() {
+                e.as_error_resp().and_then(|resp| resp.as_revert_data())
+            } else {
+                None
+            }
+        })
+        .and_then(|data| decode(&data))
+}
+
+/// Message for the revert data of a call.
+pub fn decode(data: &Bytes) -> Option<String> {
+    if let Ok(error) = VerifierError::abi_decode(data) {
+        return Some(match error {
+            VerifierError::VerificationFailed(_) => "the seal does not verify: it proves another \
+                image ID or journal, e.g. the image ID of the contract is not the one of the \
+                guest built, or the number is not the one committed by the request"
+                .to_string(),
+            VerifierError::SelectorMismatch(e) => format!(
+                "the seal has selector {} where {} is expected: it is for another verifier, e.g. \
+                 a Groth16 seal where the set verifier is called",
+                e.received, e.expected
+            ),
+        });
+    }
+    if let Ok(error) = RouterError::abi_decode(data) {
+        return Some(match error {
+            RouterError::SelectorUnknown(e) => format!(
+                "no verifier is registered for selector {} on the router: the seal is for a \
+                 version of the zkVM or a proof system the deployment does not support",
+                e.selector
+            ),
+            RouterError::SelectorInUse(e) => {
+                format!(
+                    "a verifier is already registered for selector {}",
+                    e.selector
+                )
+            }
+            RouterError::SelectorRemoved(e) => format!(
+                "the verifier of selector {} was removed from the router, request a proof \
+                 from a supported version of the zkVM",
+                e.selector
+            ),
+        });
+    }
+    if let Ok(error) = MarketError::abi_decode(data) {
+        return Some(match error {
+            MarketError::RequestIsLocked(e) => {
+                format!("request {} is already locked by a prover", id(e.requestId))
+            }
+            MarketError::RequestIsNotLockedOrPriced(e) => {
+                format!("request {} is neither locked nor priced", id(e.requestId))
+            }
+            MarketError::RequestIsNotLocked(e) => {
+                format!("request {} is not locked", id(e.requestId))
+            }
+            MarketError::RequestIsFulfilled(e) => {
+                format!("request {} is already fulfilled", id(e.requestId))
+            }
+            MarketError::RequestIsSlashed(e) => format!(
+                "request {} expired after being locked, and its prover was slashed",
+                id(e.requestId)
+            ),
+            MarketError::RequestLockIsExpired(e) => format!(
+                "the lock of request {} expired at {}",
+                id(e.requestId),
+                e.lockDeadline
+            ),
+            MarketError::RequestIsExpired(e) => format!(
+                "request expired: request {} expired at {}, submit a new one",
+                id(e.requestId),
+                e.deadline
+            ),
+            MarketError::RequestIsNotExpired(e) => format!(
+                "request {} does not expire before {}",
+                id(e.requestId),
+                e.deadline
+            ),
+            MarketError::InsufficientBalance(e) => format!(
+                "insufficient deposit: the balance of {} in the market does not cover the \
+                 maximum price of the request, deposit funds or lower the price",
+                e.account
+            ),
+            MarketError::InvalidSignature(_) => {
+                "invalid signature: the request is not signed by its client".to_string()
+            }
+            MarketError::InvalidRequest(_) => "invalid request: check that its ID, offer and \
+                timeouts are consistent, e.g. that the lock timeout is not after the timeout"
+                .to_string(),
+            MarketError::TransferFailed(_) => "the transfer of funds failed".to_string(),
+            MarketError::BatchSizeExceedsLimit(e) => format!(
+                "batch of {} requests over the limit of {}",
+                e.batchSize, e.limit
+            ),
+        });
+    }
+    GenericContractError::abi_decode(data)
+        .ok()
+        .map(|error| error.to_string())
+}
+
+/// Request ID, formatted like in the logs.
+fn id(request_id: U256) -> String {
+    format!("{request_id:x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{address, fixed_bytes},
+        sol_types::{SolError, SolValue},
+    };
+    use anyhow::Context;
+    use boundless_market::contracts::{IBoundlessMarket, IRiscZeroSetVerifier};
+
+    use super::*;
+
+    #[test]
+    fn selectors_match_the_sdk() {
+        assert_eq!(
+            IMarketErrors::InsufficientBalance::SELECTOR,
+            IBoundlessMarket::InsufficientBalance::SELECTOR
+        );
+        assert_eq!(
+            IMarketErrors::RequestIsExpired::SELECTOR,
+            IBoundlessMarket::RequestIsExpired::SELECTOR
+        );
+        assert_eq!(
+            IMarketErrors::RequestIsLocked::SELECTOR,
+            IBoundlessMarket::RequestIsLocked::SELECTOR
+        );
+        assert_eq!(
+            IVerifierErrors::VerificationFailed::SELECTOR,
+            IRiscZeroSetVerifier::VerificationFailed::SELECTOR
+        );
+    }
+
+    #[test]
+    fn decodes_known_errors() {
+        let data = IMarketErrors::InsufficientBalance {
+            account: address!("0x0000000000000000000000000000000000000001"),
+        }
+        .abi_encode();
+        let message = decode(&data.into()).unwrap();
+        assert!(message.starts_with("insufficient deposit"), "{message}");
+
+        let data = IVerifierErrors::VerificationFailed {}.abi_encode();
+        assert!(decode(&data.into()).unwrap().contains("image ID"));
+
+        let data = IVerifierRouterErrors::SelectorUnknown {
+            selector: fixed_bytes!("0xdeadbeef"),
+        }
+        .abi_encode();
+        assert!(decode(&data.into()).unwrap().contains("0xdeadbeef"));
+
+        let reason = [&[0x08, 0xc3, 0x79, 0xa0][..], &"not even".abi_encode()].concat();
+        assert_eq!(decode(&reason.into()).unwrap(), "revert: not even");
+        assert_eq!(decode(&Bytes::from_static(&[1, 2, 3, 4])), None);
+    }
+
+    #[test]
+    fn finds_revert_in_chain() {
+        let error = Err::<(), _>(TxnErr::BoundlessMarketErr(
+            IBoundlessMarket::IBoundlessMarketErrors::RequestIsExpired(
+                IBoundlessMarket::RequestIsExpired {
+                    requestId: U256::from(0xa),
+                    deadline: 100,
+                },
+            ),
+        ))
+        .context("failed to submit request")
+        .unwrap_err();
+        assert_eq!(
+            explain(&error).unwrap(),
+            "request expired: request a expired at 100, submit a new one"
+        );
+        assert_eq!(explain(&anyhow::anyhow!("other")), None);
+    }
+}
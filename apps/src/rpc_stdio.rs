@@ -0,0 +1,446 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A JSON-RPC 2.0 server on stdin and stdout, for orchestrators in other languages driving the
+//! publisher as a long-lived child process.
+//!
+//! Each line of stdin is a request, and each line of stdout a response or a notification. The
+//! methods mirror those of the Python bindings: `submit` a number, get the `status` of a request,
+//! and `wait` for its fulfillment. Requests are handled concurrently, so a response may come
+//! before that of an earlier request: they are matched by their `id`. While waiting, the events of
+//! the request are sent as `event` notifications. Logs go to stderr.
+//!
+//! Errors of the methods have the exit code of their failure as code, e.g. 5 for an expired
+//! request, or 1 if it has none.
+
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    time::Duration,
+};
+
+use alloy::{primitives::U256, providers::Provider};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::StandardClient;
+use clap::Args;
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    sync::OnceCell,
+};
+use url::Url;
+
+use crate::{
+    build_request,
+    failure::Failure,
+    history::{History, Timings},
+    state_dir, submit_request, tui, upload_program,
+    wait::{self, RequestEvent},
+    ClientArgs,
+};
+
+/// Number of blocks before the current one in which the lock of a request is looked up.
+const LOCK_EVENT_LOOKBACK: u64 = 10_000;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Arguments of the rpc-stdio command.
+#[derive(Args, Clone, Debug)]
+pub struct RpcStdioArgs {
+    #[clap(flatten)]
+    state: state_dir::StateArgs,
+    #[clap(flatten)]
+    client: ClientArgs,
+}
+
+/// A request read from stdin. Without an `id`, it is a notification, which gets no response.
+#[derive(Debug, Deserialize)]
+struct Call {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SubmitParams {
+    number: u32,
+    #[serde(default)]
+    groth16: bool,
+    #[serde(default = "default_preflight_cache")]
+    preflight_cache: bool,
+    /// Tags recorded with the request in the history.
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+}
+
+fn default_preflight_cache() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RequestParams {
+    request_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WaitParams {
+    request_id: String,
+    /// Interval, in seconds, between status checks of the request while its status is likely to
+    /// change, as `--poll-interval`.
+    #[serde(default = "default_poll_interval")]
+    poll_interval: u64,
+    /// Maximum interval, in seconds, between status checks of the request.
+    #[serde(default = "default_max_poll_interval")]
+    max_poll_interval: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    wait::PollSchedule::default().min().as_secs()
+}
+
+fn default_max_poll_interval() -> u64 {
+    wait::PollSchedule::default().max().as_secs()
+}
+
+/// Error of a call, sent in its response.
+#[derive(Debug, PartialEq)]
+struct CallError {
+    code: i64,
+    message: String,
+}
+
+impl CallError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for CallError {
+    fn from(e: anyhow::Error) -> Self {
+        let code = Failure::of(&e).map_or(1, Failure::code);
+        Self::new(code.into(), format!("{e:#}"))
+    }
+}
+
+/// State shared by the calls.
+struct Server {
+    client: StandardClient,
+    history: History,
+    /// URL of the program, uploaded by the first submission.
+    program_url: OnceCell<Url>,
+}
+
+/// Serve the requests of stdin until it is closed, then finish the calls in progress.
+pub async fn run(args: RpcStdioArgs) -> Result<()> {
+    let history = args.state.open(state_dir::Access::Shared)?;
+    let client = args.client.build_client().await.context(Failure::Config)?;
+    let server = Server {
+        client,
+        history,
+        program_url: OnceCell::new(),
+    };
+    tracing::info!("Reading JSON-RPC requests on stdin");
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut open = true;
+    // The calls are polled by this task, so that they can share the history.
+    let mut calls = FuturesUnordered::new();
+    loop {
+        tokio::select! {
+            line = lines.next_line(), if open => match line.context("failed to read stdin")? {
+                Some(line) if line.trim().is_empty() => (),
+                Some(line) => match parse(&line) {
+                    Ok(call) => calls.push(server.handle(call)),
+                    Err(response) => send(&response)?,
+                },
+                None => open = false,
+            },
+            Some(response) = calls.next(), if !calls.is_empty() => {
+                if let Some(response) = response {
+                    send(&response)?;
+                }
+            }
+            else => break,
+        }
+    }
+    Ok(())
+}
+
+/// Parse a line into a call, or the error response to send for it.
+fn parse(line: &str) -> Result<Call, Value> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| error_response(Value::Null, &CallError::new(PARSE_ERROR, e.to_string())))?;
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    let call: Call = serde_json::from_value(value)
+        .map_err(|e| error_response(id.clone(), &CallError::new(INVALID_REQUEST, e.to_string())))?;
+    if call.jsonrpc != "2.0" {
+        return Err(error_response(
+            id,
+            &CallError::new(
+                INVALID_REQUEST,
+                "unsupported JSON-RPC version, expected 2.0",
+            ),
+        ));
+    }
+    Ok(call)
+}
+
+impl Server {
+    /// Run the call, and return its response unless it is a notification.
+    async fn handle(&self, call: Call) -> Option<Value> {
+        let result = match call.method.as_str() {
+            "submit" => match params(call.params) {
+                Ok(params) => self.submit(params).await,
+                Err(e) => Err(e),
+            },
+            "status" => match params(call.params) {
+                Ok(params) => self.status(params).await,
+                Err(e) => Err(e),
+            },
+            "wait" => match params(call.params) {
+                Ok(params) => self.wait(params).await,
+                Err(e) => Err(e),
+            },
+            method => Err(CallError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {method}"),
+            )),
+        };
+        if let Err(e) = &result {
+            tracing::warn!("Call to {} failed: {}", call.method, e.message);
+        }
+        let id = call.id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, &e),
+        })
+    }
+
+    /// Submit a request for the number, and return its ID and expiry.
+    async fn submit(&self, params: SubmitParams) -> Result<Value, CallError> {
+        let mut timings = Timings::default();
+        let program_url = self
+            .program_url
+            .get_or_try_init(|| upload_program(&self.client, &mut timings))
+            .await?;
+        let request = build_request(
+            &self.client,
+            &self.history,
+            program_url,
+            params.number,
+            params.groth16,
+            params.preflight_cache,
+            &mut timings,
+        )
+        .await?;
+        let tags: Vec<_> = params.tags.into_iter().collect();
+        let submitted =
+            submit_request(&self.client, &self.history, &request, params.number, &tags).await?;
+        self.history.set_timings(submitted.request_id, &timings)?;
+        Ok(json!({
+            "request_id": format!("{:#x}", submitted.request_id),
+            "expires_at": submitted.expires_at,
+        }))
+    }
+
+    /// Status of the request, as the `status` of the Python bindings.
+    async fn status(&self, params: RequestParams) -> Result<Value, CallError> {
+        let request_id = request_id(&params.request_id)?;
+        let expires_at = self
+            .client
+            .fetch_order(request_id, None, None)
+            .await
+            .ok()
+            .map(|order| order.request.expires_at());
+        let status = self
+            .client
+            .boundless_market
+            .get_status(request_id, expires_at)
+            .await
+            .map_err(anyhow::Error::from)?;
+        Ok(json!({ "status": format!("{status:?}") }))
+    }
+
+    /// Wait for the request to be fulfilled, sending its events as notifications, and return its
+    /// journal and seal.
+    async fn wait(&self, params: WaitParams) -> Result<Value, CallError> {
+        let request_id = request_id(&params.request_id)?;
+        let schedule = wait::PollSchedule::new(
+            Duration::from_secs(params.poll_interval),
+            Duration::from_secs(params.max_poll_interval),
+        )?;
+        let order = self
+            .client
+            .fetch_order(request_id, None, None)
+            .await
+            .context("request not found onchain or in the order stream")?;
+        let latest = self
+            .client
+            .provider()
+            .get_block_number()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let from_block = latest.saturating_sub(LOCK_EVENT_LOOKBACK);
+        let events = wait::events(&self.client, order.request, from_block, schedule);
+        let mut events = std::pin::pin!(events);
+        while let Some(event) = events.next().await {
+            let event = event?;
+            send(&notification(&event))?;
+            match event {
+                RequestEvent::Fulfilled { journal, seal, .. } => {
+                    return Ok(json!({
+                        "request_id": format!("{request_id:#x}"),
+                        "journal": journal,
+                        "seal": seal,
+                    }))
+                }
+                RequestEvent::Expired { slashed, .. } => {
+                    let failure = if slashed {
+                        Failure::Slashed
+                    } else {
+                        Failure::Expired
+                    };
+                    return Err(anyhow!("request {request_id:#x} expired")
+                        .context(failure)
+                        .into());
+                }
+                _ => (),
+            }
+        }
+        Err(anyhow!("the events of the request ended before it was fulfilled").into())
+    }
+}
+
+/// Parameters of a call, from its `params` object.
+fn params<T: DeserializeOwned>(params: Value) -> Result<T, CallError> {
+    serde_json::from_value(params).map_err(|e| CallError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn request_id(request_id: &str) -> Result<U256, CallError> {
+    tui::parse_request_id(request_id).map_err(|e| CallError::new(INVALID_PARAMS, format!("{e:#}")))
+}
+
+fn error_response(id: Value, error: &CallError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// The `event` notification of an event of a request waited for.
+fn notification(event: &RequestEvent) -> Value {
+    let params = match event {
+        RequestEvent::Submitted {
+            request_id,
+            expires_at,
+        } => json!({
+            "request_id": format!("{request_id:#x}"),
+            "event": "submitted",
+            "expires_at": expires_at,
+        }),
+        RequestEvent::Locked { request_id, lock } => json!({
+            "request_id": format!("{request_id:#x}"),
+            "event": "locked",
+            "prover": lock.as_ref().map(|lock| lock.prover),
+            "locked_at": lock.as_ref().map(|lock| lock.timestamp),
+        }),
+        RequestEvent::Fulfilled { request_id, .. } => json!({
+            "request_id": format!("{request_id:#x}"),
+            "event": "fulfilled",
+        }),
+        RequestEvent::Expired {
+            request_id,
+            slashed,
+        } => json!({
+            "request_id": format!("{request_id:#x}"),
+            "event": "expired",
+            "slashed": slashed,
+        }),
+        RequestEvent::Posted {
+            request_id,
+            tx_hash,
+        } => json!({
+            "request_id": format!("{request_id:#x}"),
+            "event": "posted",
+            "tx_hash": tx_hash,
+        }),
+    };
+    json!({ "jsonrpc": "2.0", "method": "event", "params": params })
+}
+
+/// Write a message to stdout, on its own line.
+fn send(message: &Value) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer(&mut stdout, message)?;
+    writeln!(stdout)?;
+    stdout.flush().context("failed to write to stdout")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_calls() {
+        let call =
+            parse(r#"{"jsonrpc":"2.0","id":1,"method":"status","params":{"request_id":"0x1"}}"#)
+                .unwrap();
+        assert_eq!((call.id, call.method.as_str()), (Some(json!(1)), "status"));
+        let status: RequestParams = params(call.params).unwrap();
+        assert_eq!(status.request_id, "0x1");
+
+        let call =
+            parse(r#"{"jsonrpc":"2.0","method":"wait","params":{"request_id":"1"}}"#).unwrap();
+        assert_eq!(call.id, None);
+        let wait: WaitParams = params(call.params).unwrap();
+        assert_eq!((wait.poll_interval, wait.max_poll_interval), (5, 60));
+    }
+
+    #[test]
+    fn rejects_invalid_calls() {
+        let code = |line| parse(line).unwrap_err()["error"]["code"].clone();
+        assert_eq!(code("{"), json!(PARSE_ERROR));
+        assert_eq!(code(r#"{"jsonrpc":"2.0","id":1}"#), json!(INVALID_REQUEST));
+        let response = parse(r#"{"jsonrpc":"1.0","id":"a","method":"status"}"#).unwrap_err();
+        assert_eq!(response["id"], json!("a"));
+        assert_eq!(response["error"]["code"], json!(INVALID_REQUEST));
+
+        let error = params::<SubmitParams>(json!({ "number": 4, "price": 1 })).unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS);
+        let error = request_id("not an id").unwrap_err();
+        assert_eq!(error.code, INVALID_PARAMS);
+    }
+
+    #[test]
+    fn maps_failures_to_error_codes() {
+        let error = CallError::from(anyhow!("request expired").context(Failure::Expired));
+        assert_eq!(error.code, 5);
+        assert_eq!(CallError::from(anyhow!("failed")).code, 1);
+    }
+}
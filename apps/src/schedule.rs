@@ -0,0 +1,81 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Recurring submissions on a cron schedule.
+
+use std::net::SocketAddr;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::Args;
+use croner::Cron;
+
+use crate::{failure::Failure, SubmitArgs};
+
+/// Arguments of the schedule command.
+#[derive(Args, Debug)]
+pub struct ScheduleArgs {
+    /// Cron expression, evaluated in UTC, for when to submit a request.
+    ///
+    /// For example, "*/10 * * * *" submits a request every ten minutes.
+    #[clap(long)]
+    schedule: Cron,
+    /// Address on which to serve Prometheus metrics at `/metrics`, e.g. 0.0.0.0:9090.
+    #[clap(long, env)]
+    metrics_addr: Option<SocketAddr>,
+    /// Template for the request submitted on each run of the schedule.
+    #[clap(flatten)]
+    submit: SubmitArgs,
+}
+
+/// Run the submit flow each time the schedule fires, until the process is stopped.
+///
+/// A failed run is logged and does not stop the schedule. Runs never overlap: if a run takes
+/// longer than the interval, the occurrences that elapsed in the meantime are skipped.
+///
+/// The client, with its connections to the RPC endpoint and the storage provider, is built once
+/// and reused by every run.
+pub async fn run(args: ScheduleArgs) -> Result<()> {
+    args.submit.ensure_market("schedule")?;
+    if let Some(addr) = args.metrics_addr {
+        crate::telemetry::serve(addr)?;
+    }
+    let client = args
+        .submit
+        .client
+        .build_client()
+        .await
+        .context(Failure::Config)?;
+    let consumer = args
+        .submit
+        .connect_consumer(client.provider())
+        .await
+        .context(Failure::Config)?;
+    let history = args.submit.state.open(crate::state_dir::Access::Shared)?;
+    tracing::info!("Submitting on schedule \"{}\"", args.schedule.pattern);
+    for run in 1.. {
+        let now = Utc::now();
+        let next = args
+            .schedule
+            .find_next_occurrence(&now, false)
+            .context("failed to compute the next scheduled run")?;
+        tracing::info!("Next submission (run {run}) scheduled at {next}");
+        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+        if let Err(e) = crate::submit_with(&client, &consumer, &history, &args.submit).await {
+            tracing::error!("Scheduled run {run} failed: {e:?}");
+        }
+    }
+    Ok(())
+}
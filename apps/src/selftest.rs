@@ -0,0 +1,237 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! End-to-end smoke test of the configured environment.
+
+use std::{
+    num::NonZeroUsize,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use url::Url;
+
+use crate::{
+    ens::NameOrAddress,
+    failure::Failure,
+    history::{RequestRecord, RequestState},
+    state_dir, ClientArgs, ConsumerArgs, SubmitArgs,
+};
+
+/// Number published by the self-test.
+const NUMBER: u32 = 2;
+
+/// Arguments of the selftest command.
+#[derive(Args, Debug)]
+pub struct SelftestArgs {
+    /// Address, or ENS name, of the EvenNumber contract.
+    #[clap(short, long, env)]
+    even_number_address: NameOrAddress,
+    /// URL where provers can download the program to be proven.
+    #[clap(long, env)]
+    program_url: Option<Url>,
+    #[clap(flatten)]
+    state: state_dir::StateArgs,
+    /// Run the test against a local devnet with fake receipts, see `submit --dev-mode`.
+    #[clap(long)]
+    dev_mode: bool,
+    #[clap(flatten)]
+    client: ClientArgs,
+    #[clap(flatten, next_help_heading = "Consumer Chain")]
+    consumer: ConsumerArgs,
+}
+
+/// Phase of the submit flow, as reported by the self-test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    Config,
+    Upload,
+    Preflight,
+    Submission,
+    Fulfillment,
+    SetTx,
+}
+
+impl Phase {
+    const ALL: [Self; 6] = [
+        Self::Config,
+        Self::Upload,
+        Self::Preflight,
+        Self::Submission,
+        Self::Fulfillment,
+        Self::SetTx,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Config => "configuration",
+            Self::Upload => "upload",
+            Self::Preflight => "preflight",
+            Self::Submission => "submission",
+            Self::Fulfillment => "fulfillment",
+            Self::SetTx => "set tx",
+        }
+    }
+
+    /// What to check first when the phase fails.
+    fn hint(self) -> &'static str {
+        match self {
+            Self::Config => "check RPC_URL, PRIVATE_KEY and the market deployment of the chain",
+            Self::Upload => "check PINATA_JWT, or pass a pre-uploaded --program-url",
+            Self::Preflight => "check that the guest builds and runs with `cargo test`",
+            Self::Submission => "check that the wallet is funded on the chain of RPC_URL",
+            Self::Fulfillment => "no prover fulfilled the request, try again later",
+            Self::SetTx => "check that EVEN_NUMBER_ADDRESS is deployed with the right verifier",
+        }
+    }
+
+    /// Whether the phase is run by the dev-mode flow.
+    fn in_dev_mode(self) -> bool {
+        matches!(self, Self::Config | Self::Preflight | Self::SetTx)
+    }
+}
+
+/// Publish a known-good number through the submit flow, and report the outcome of each phase.
+pub async fn run(args: SelftestArgs) -> Result<()> {
+    let run = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let tags = vec![("selftest".to_string(), run.to_string())];
+    let dev_mode = args.dev_mode;
+    let state = args.state.clone();
+    let submit = SubmitArgs {
+        numbers: vec![NUMBER],
+        input: Default::default(),
+        concurrency: NonZeroUsize::MIN,
+        even_number_addresses: vec![args.even_number_address],
+        program_url: args.program_url,
+        expires_at: None,
+        print_calldata: false,
+        export_bundle: None,
+        auto_deposit: false,
+        client_contract: None,
+        no_budget_check: false,
+        fallback_local_prove: false,
+        prover: crate::ProverBackend::Market,
+        lock_alert: Default::default(),
+        offchain: false,
+        request_index: None,
+        tags: tags.clone(),
+        state: args.state,
+        poll_interval: 5,
+        max_poll_interval: 60,
+        no_preflight_cache: false,
+        dev_mode,
+        client: args.client,
+        consumer: args.consumer,
+        prices: Default::default(),
+    };
+    let result = crate::submit(submit).await;
+
+    let record = state.open(state_dir::Access::Read)?.list(&tags, 1)?.pop();
+    let failed = result
+        .as_ref()
+        .err()
+        .map(|e| failed_phase(e, record.as_ref(), dev_mode));
+    let timings = record.and_then(|record| record.timings).unwrap_or_default();
+    for phase in Phase::ALL {
+        let outcome = match failed {
+            _ if dev_mode && !phase.in_dev_mode() => "skipped (dev mode)".to_string(),
+            Some(failed) if phase > failed => "skipped".to_string(),
+            Some(failed) if phase == failed => format!("FAILED, {}", phase.hint()),
+            _ => {
+                let seconds = match phase {
+                    Phase::Upload => timings.upload,
+                    Phase::Preflight => timings.preflight,
+                    Phase::Fulfillment => timings
+                        .lock
+                        .map(|lock| lock + timings.fulfillment.unwrap_or_default()),
+                    Phase::SetTx => timings.set_tx,
+                    Phase::Config | Phase::Submission => None,
+                };
+                match seconds {
+                    Some(seconds) => format!("ok ({seconds:.1}s)"),
+                    None => "ok".to_string(),
+                }
+            }
+        };
+        println!("{:<14} {outcome}", phase.name());
+    }
+    println!(
+        "Self-test {}",
+        if result.is_ok() { "passed" } else { "failed" }
+    );
+    result.context("self-test failed")
+}
+
+/// Phase in which the self-test failed with the given error.
+///
+/// The kind of the error tells the phases before the submission apart; after it, the state of the
+/// request in the history tells how far it went.
+fn failed_phase(error: &anyhow::Error, record: Option<&RequestRecord>, dev_mode: bool) -> Phase {
+    match (Failure::of(error), record) {
+        (Some(Failure::Config), _) => Phase::Config,
+        (Some(Failure::Upload), _) => Phase::Upload,
+        (Some(Failure::Preflight), _) => Phase::Preflight,
+        (_, None) if dev_mode => Phase::SetTx,
+        (_, None) => Phase::Submission,
+        (_, Some(record)) if record.state == RequestState::Fulfilled.to_string() => Phase::SetTx,
+        (_, Some(_)) => Phase::Fulfillment,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    fn record(state: RequestState) -> RequestRecord {
+        RequestRecord {
+            request_id: "0x1".to_string(),
+            submitted_at: 0,
+            expires_at: 0,
+            number: NUMBER.to_string(),
+            state: state.to_string(),
+            set_tx_hash: None,
+            max_price: "0".to_string(),
+            price: None,
+            set_gas_cost: None,
+            timings: None,
+            set_to: None,
+            set_calldata: None,
+            set_cost_estimate: None,
+            slash: None,
+            posts: Vec::new(),
+            tags: Default::default(),
+        }
+    }
+
+    #[test]
+    fn finds_failed_phase() {
+        let error = |failure: Failure| anyhow!("failed").context(failure);
+        let submitted = record(RequestState::Submitted);
+        let fulfilled = record(RequestState::Fulfilled);
+
+        let phase = failed_phase(&error(Failure::Upload), None, false);
+        assert_eq!(phase, Phase::Upload);
+        let phase = failed_phase(&error(Failure::TxReverted), None, false);
+        assert_eq!(phase, Phase::Submission);
+        let phase = failed_phase(&error(Failure::Expired), Some(&submitted), false);
+        assert_eq!(phase, Phase::Fulfillment);
+        let phase = failed_phase(&error(Failure::TxReverted), Some(&fulfilled), false);
+        assert_eq!(phase, Phase::SetTx);
+        let phase = failed_phase(&error(Failure::TxReverted), None, true);
+        assert_eq!(phase, Phase::SetTx);
+    }
+}
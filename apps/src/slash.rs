@@ -0,0 +1,266 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Slashing the provers of requests that expired locked.
+//!
+//! A prover locking a request stakes on its fulfillment. If the request expires unfulfilled,
+//! anyone can slash the prover: the market refunds the price of the lock to the client, burns
+//! part of the stake, and transfers the rest to the market, or to a prover that fulfilled the
+//! request after the lock expired. The requests of the history that expired, or are past their
+//! expiry, are slashed, and the outcome is confirmed from the `ProverSlashed` event and recorded
+//! in the history. A request fulfilled by another prover after the lock expired is slashed as
+//! well, as its locker still holds the stake. A request a prover never locked, or its locker
+//! fulfilled, has no stake to slash, and is recorded as such so that it isn't checked again.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use alloy::primitives::{utils::format_ether, U256};
+use anyhow::{anyhow, Context, Result};
+use boundless_market::{contracts::IBoundlessMarket, StandardClient};
+use clap::Args;
+
+use crate::{
+    failure::Failure,
+    history::{ExpiredRequest, History, Slash},
+    nonce, state_dir, tui, wait, ClientArgs, TX_TIMEOUT,
+};
+
+/// Arguments of the slash command.
+#[derive(Args, Clone, Debug)]
+pub struct SlashArgs {
+    /// Only slash the prover of the request with this ID. Can be repeated.
+    #[clap(long = "request-id", value_parser = tui::parse_request_id)]
+    request_ids: Vec<U256>,
+    #[clap(flatten)]
+    state: state_dir::StateArgs,
+    #[clap(flatten)]
+    client: ClientArgs,
+}
+
+/// Slash the provers of the expired requests of the history, or of the given ones.
+pub async fn run(args: SlashArgs) -> Result<()> {
+    let history = args.state.open(state_dir::Access::Shared)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let mut requests = history.expired(now)?;
+    if !args.request_ids.is_empty() {
+        requests.retain(|request| args.request_ids.contains(&request.request_id));
+    }
+    if requests.is_empty() {
+        tracing::info!("No expired request to slash");
+        return Ok(());
+    }
+
+    let client = args.client.build_client().await.context(Failure::Config)?;
+    let count = requests.len();
+    let mut errors = Vec::new();
+    // The slash transactions are sent from the same wallet, one at a time.
+    for request in requests {
+        let request_id = request.request_id;
+        if let Err(e) = slash(&client, &history, request, now).await {
+            tracing::error!("Slashing request {request_id:x} failed: {e:?}");
+            errors.push(e);
+        }
+    }
+    let failed = errors.len();
+    match errors.into_iter().next() {
+        None => Ok(()),
+        Some(e) if count == 1 => Err(e),
+        Some(e) => Err(e).context(format!("{failed} of {count} requests failed")),
+    }
+}
+
+/// What to do with a request, given its state in the market.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    /// Its prover was already slashed, by someone else: record the outcome.
+    Record,
+    /// It isn't expired yet.
+    NotExpired,
+    /// No prover locked it, or its locker fulfilled it: there is no stake to slash.
+    NotLocked,
+    /// Its locker didn't fulfill it before it expired: slash the locker.
+    Slash,
+}
+
+/// Decide what to do with a request from the market's lock and slash flags, rather than its
+/// status, which is fulfilled once another prover fulfilled it after the lock expired.
+fn action(slashed: bool, locked: bool, expires_at: u64, now: u64) -> Action {
+    if slashed {
+        Action::Record
+    } else if expires_at > now {
+        Action::NotExpired
+    } else if !locked {
+        Action::NotLocked
+    } else {
+        Action::Slash
+    }
+}
+
+/// Slash the prover of the request if it expired locked, and record the outcome.
+async fn slash(
+    client: &StandardClient,
+    history: &History,
+    request: ExpiredRequest,
+    now: u64,
+) -> Result<()> {
+    let market = &client.boundless_market;
+    let request_id = request.request_id;
+    let from_block = request.from_block.unwrap_or_default();
+    let slashed = market.is_slashed(request_id).await?;
+    let locked = market.is_locked(request_id).await?;
+    match action(slashed, locked, request.expires_at, now) {
+        Action::Record => {
+            tracing::info!("The prover of request {request_id:x} was already slashed");
+            let slash = find_slash(client, &request, from_block).await?;
+            return record(history, request_id, &slash);
+        }
+        Action::NotExpired => {
+            tracing::info!("Request {request_id:x} is not expired, skipping it");
+            return Ok(());
+        }
+        Action::NotLocked => {
+            tracing::info!(
+                "Request {request_id:x} is not locked by a prover that failed it, no prover to \
+                 slash"
+            );
+            return history.set_not_slashable(request_id);
+        }
+        Action::Slash => {}
+    }
+
+    tracing::info!("Slashing the prover of request {request_id:x}");
+    let call = market.instance().slash(request_id).from(client.caller());
+    let pending_tx = nonce::retry_on_conflict("slash tx", || call.send())
+        .await
+        .context("failed to broadcast the slash tx")
+        .context(Failure::TxReverted)?;
+    tracing::info!("Broadcasting tx {}", pending_tx.tx_hash());
+    let receipt = pending_tx
+        .with_timeout(Some(TX_TIMEOUT))
+        .get_receipt()
+        .await
+        .context("failed to confirm the slash tx")?;
+    if !receipt.status() {
+        return Err(anyhow!("tx {} reverted", receipt.transaction_hash))
+            .context(Failure::TxReverted);
+    }
+    let event = receipt
+        .decoded_log::<IBoundlessMarket::ProverSlashed>()
+        .context("no ProverSlashed event in the slash tx")?;
+    let slash = Slash {
+        tx_hash: Some(receipt.transaction_hash),
+        stake_burned: event.stakeBurned,
+        stake_transferred: event.stakeTransferred,
+        stake_recipient: event.stakeRecipient,
+        refund: refund(client, &request, from_block).await,
+    };
+    record(history, request_id, &slash)
+}
+
+/// Outcome of the slashing of the prover of the request by someone else, from its event.
+async fn find_slash(
+    client: &StandardClient,
+    request: &ExpiredRequest,
+    from_block: u64,
+) -> Result<Slash> {
+    let mut event_filter = client.boundless_market.instance().ProverSlashed_filter();
+    event_filter.filter = event_filter
+        .filter
+        .topic1(request.request_id)
+        .from_block(from_block);
+    let logs = event_filter
+        .query()
+        .await
+        .context("failed to query slash events")?;
+    let (event, log) = logs
+        .into_iter()
+        .next()
+        .context("the ProverSlashed event of the request was not found")?;
+    Ok(Slash {
+        tx_hash: log.transaction_hash,
+        stake_burned: event.stakeBurned,
+        stake_transferred: event.stakeTransferred,
+        stake_recipient: event.stakeRecipient,
+        refund: refund(client, request, from_block).await,
+    })
+}
+
+/// Price of the lock of the request, refunded to the client by the slashing, if it can be found.
+async fn refund(
+    client: &StandardClient,
+    request: &ExpiredRequest,
+    from_block: u64,
+) -> Option<U256> {
+    let offer = &request.request.as_ref()?.offer;
+    let lock = wait::find_lock(client, request.request_id, from_block)
+        .await
+        .inspect_err(|e| tracing::warn!("Failed to find the lock of the request: {e:#}"))
+        .ok()??;
+    offer.price_at(lock.timestamp).ok()
+}
+
+fn record(history: &History, request_id: U256, slash: &Slash) -> Result<()> {
+    tracing::info!(
+        "Request {request_id:x}: {} of stake burned, {} transferred to {}, {} refunded",
+        slash.stake_burned,
+        slash.stake_transferred,
+        slash.stake_recipient,
+        match slash.refund {
+            Some(refund) => format!("{} ETH", format_ether(refund)),
+            None => "an unknown price".to_string(),
+        }
+    );
+    history.set_slashed(request_id, slash)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use alloy::primitives::Address;
+
+    use super::*;
+
+    #[test]
+    fn decides_from_the_lock_and_slash_flags() {
+        // Slashed by someone else, whether or not the request is still locked.
+        assert_eq!(action(true, true, 100, 200), Action::Record);
+        assert_eq!(action(true, false, 100, 200), Action::Record);
+        assert_eq!(action(false, true, 300, 200), Action::NotExpired);
+        assert_eq!(action(false, false, 100, 200), Action::NotLocked);
+        // Expired locked, including when fulfilled after the lock expired by another prover.
+        assert_eq!(action(false, true, 100, 200), Action::Slash);
+        assert_eq!(action(false, true, 200, 200), Action::Slash);
+    }
+
+    #[test]
+    fn records_the_outcome() {
+        let history = History::open(&PathBuf::from(":memory:")).unwrap();
+        history
+            .insert(U256::from(1), 10, 100, U256::ZERO, U256::from(2), &[])
+            .unwrap();
+        let slash = Slash {
+            tx_hash: None,
+            stake_burned: U256::from(3),
+            stake_transferred: U256::from(1),
+            stake_recipient: Address::repeat_byte(2),
+            refund: None,
+        };
+        record(&history, U256::from(1), &slash).unwrap();
+        assert!(history.expired(200).unwrap().is_empty());
+        let record = history.list(&[], 1).unwrap().pop().unwrap();
+        assert_eq!(record.state, "slashed");
+        assert_eq!(record.slash, Some(slash));
+    }
+}
@@ -0,0 +1,70 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading the state of the EvenNumber contract in a single `eth_call`.
+//!
+//! The reads are bundled into one call to the Multicall3 contract, which is deployed at the same
+//! address on most chains, saving a round trip per read on high-latency RPC providers.
+
+use alloy::{
+    primitives::{Address, B256, U256},
+    providers::{MulticallItem, Provider},
+};
+use anyhow::{Context, Result};
+
+use crate::even_number::IEvenNumber::IEvenNumberInstance;
+
+alloy::sol!(
+    #[sol(rpc)]
+    interface IEvenNumberState {
+        function verifier() external view returns (address);
+        function paused() external view returns (bool);
+    }
+);
+
+/// State of an EvenNumber contract.
+#[derive(Debug)]
+pub struct ContractState {
+    /// The number stored by the contract.
+    pub number: U256,
+    /// The image ID of the guest the seals are verified against.
+    pub image_id: B256,
+    /// The RISC Zero verifier the seals are verified with.
+    pub verifier: Address,
+    /// Whether the contract is paused, or `None` if it isn't pausable.
+    pub paused: Option<bool>,
+}
+
+impl ContractState {
+    /// Read the state of the EvenNumber contract at `address`.
+    pub async fn read<P: Provider>(provider: &P, address: Address) -> Result<Self> {
+        let even_number = IEvenNumberInstance::new(address, provider);
+        let state = IEvenNumberState::new(address, provider);
+        let (number, image_id, verifier, paused) = provider
+            .multicall()
+            .add(even_number.get())
+            .add(even_number.imageId())
+            .add(state.verifier())
+            .add_call(state.paused().into_call(true))
+            .aggregate3()
+            .await
+            .context("failed to read the EvenNumber contract state")?;
+        Ok(Self {
+            number: number.context("failed to get the number")?,
+            image_id: image_id.context("failed to get the image ID")?,
+            verifier: verifier.context("failed to get the verifier")?,
+            paused: paused.ok(),
+        })
+    }
+}
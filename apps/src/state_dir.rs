@@ -0,0 +1,199 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The state directory of the app, shared by its invocations on a host.
+//!
+//! The directory holds the history database: the requests submitted, the preflight cache, and
+//! the requests left in flight, which `resume` finishes. SQLite serializes the writes of
+//! concurrent invocations, but `resume` posts the result of every request in flight, including
+//! the requests another invocation is still waiting for, whose results would then be posted
+//! twice. The invocations submitting requests hold a shared advisory lock on the directory while
+//! they run, and `resume` an exclusive one.
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Args;
+
+use crate::{failure::Failure, history::History};
+
+/// File name of the history database in the state directory.
+const HISTORY_DB: &str = "history.sqlite";
+
+/// File name of the lock file in the state directory.
+const LOCK_FILE: &str = ".lock";
+
+/// Options locating the state of the app.
+#[derive(Args, Clone, Debug)]
+pub struct StateArgs {
+    /// Directory holding the state of the app: the history of the requests, the preflight cache
+    /// and the requests to resume. Created if missing.
+    #[clap(long, env, default_value = ".")]
+    state_dir: PathBuf,
+    /// Path of the SQLite database recording submitted requests. Defaults to history.sqlite in
+    /// the state directory.
+    #[clap(long, env)]
+    history_db: Option<PathBuf>,
+}
+
+/// How an invocation uses the state directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Only reads the history, or writes caches: no lock is taken.
+    Read,
+    /// Submits requests, and posts their results: concurrent with other submissions.
+    Shared,
+    /// Posts the results of every request in flight: alone.
+    Exclusive,
+}
+
+impl StateArgs {
+    /// State held in memory, for runs that don't keep their requests.
+    pub fn in_memory() -> Self {
+        Self {
+            state_dir: PathBuf::from("."),
+            history_db: Some(PathBuf::from(":memory:")),
+        }
+    }
+
+    /// Path of the history database.
+    pub fn history_db(&self) -> PathBuf {
+        self.history_db
+            .clone()
+            .unwrap_or_else(|| self.state_dir.join(HISTORY_DB))
+    }
+
+    /// Open the history database, creating the state directory if needed, and lock the
+    /// directory for the given access until the history is dropped. A shared lock waits for an
+    /// exclusive one to be released, while an exclusive lock fails if the directory is in use.
+    pub fn open(&self, access: Access) -> Result<History> {
+        let path = self.history_db();
+        if path == Path::new(":memory:") {
+            return History::open(&path);
+        }
+        fs::create_dir_all(&self.state_dir).with_context(|| {
+            format!(
+                "failed to create the state directory {}",
+                self.state_dir.display()
+            )
+        })?;
+        let lock = match access {
+            Access::Read => None,
+            Access::Shared => Some(StateLock::shared(&self.state_dir)?),
+            Access::Exclusive => Some(StateLock::exclusive(&self.state_dir)?),
+        };
+        Ok(History::open(&path)?.with_lock(lock))
+    }
+}
+
+/// Advisory lock on a state directory, released when dropped.
+pub struct StateLock {
+    _file: File,
+}
+
+impl StateLock {
+    fn shared(dir: &Path) -> Result<Self> {
+        let file = open_lock_file(dir)?;
+        if !flock(&file, false, false)? {
+            tracing::info!(
+                "Waiting for the resume using the state directory {} to finish",
+                dir.display()
+            );
+            flock(&file, false, true)?;
+        }
+        Ok(Self { _file: file })
+    }
+
+    fn exclusive(dir: &Path) -> Result<Self> {
+        let file = open_lock_file(dir)?;
+        if !flock(&file, true, false)? {
+            return Err(anyhow!(
+                "another invocation is using the state directory {}, retry once it is done",
+                dir.display()
+            ))
+            .context(Failure::Config);
+        }
+        Ok(Self { _file: file })
+    }
+}
+
+fn open_lock_file(dir: &Path) -> Result<File> {
+    let path = dir.join(LOCK_FILE);
+    File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("failed to open the lock file {}", path.display()))
+}
+
+/// Lock the file, waiting for the lock if `wait` is set. Returns whether the file was locked.
+#[cfg(unix)]
+fn flock(file: &File, exclusive: bool, wait: bool) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+
+    let mut operation = if exclusive {
+        libc::LOCK_EX
+    } else {
+        libc::LOCK_SH
+    };
+    if !wait {
+        operation |= libc::LOCK_NB;
+    }
+    // SAFETY: the descriptor belongs to `file`, which outlives the call.
+    if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+        return Ok(true);
+    }
+    match io::Error::last_os_error() {
+        e if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+        e => Err(e),
+    }
+}
+
+/// Advisory locks are only taken on Unix, other platforms run unlocked.
+#[cfg(not(unix))]
+fn flock(_file: &File, _exclusive: bool, _wait: bool) -> io::Result<bool> {
+    Ok(true)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn excludes_resume_from_shared_state() {
+        let dir = std::env::temp_dir().join(format!("state-dir-{}", std::process::id()));
+        let state = StateArgs {
+            state_dir: dir.clone(),
+            history_db: None,
+        };
+        assert_eq!(state.history_db(), dir.join("history.sqlite"));
+
+        let submit = state.open(Access::Shared).unwrap();
+        let other_submit = state.open(Access::Shared).unwrap();
+        let error = state.open(Access::Exclusive).err().unwrap();
+        assert_eq!(Failure::of(&error), Some(Failure::Config));
+        assert!(state.open(Access::Read).is_ok());
+
+        drop((submit, other_submit));
+        let resume = state.open(Access::Exclusive).unwrap();
+        assert!(state.open(Access::Exclusive).is_err());
+        drop(resume);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Metrics and traces of the submission pipeline.
+//!
+//! The metrics are always recorded, but only exported when a long-running command is given a
+//! metrics address; otherwise recording them is a no-op. Likewise, spans are only exported when
+//! an OTLP endpoint is given, and errors are only reported when a Sentry DSN is given.
+
+use std::net::SocketAddr;
+
+use alloy::primitives::U256;
+use anyhow::{Context, Result};
+use boundless_market::ProofRequest;
+use clap::ValueEnum;
+use metrics::{counter, describe_counter, describe_histogram, Unit};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+use sentry::{protocol::Context as SentryContext, types::Dsn, ClientInitGuard};
+use tracing::Level;
+use tracing_subscriber::{
+    filter::Targets,
+    fmt::{format::FmtSpan, writer::BoxMakeWriter},
+    prelude::*,
+    EnvFilter,
+};
+use url::Url;
+
+pub const REQUESTS_SUBMITTED: &str = "boundless_requests_submitted_total";
+pub const REQUESTS_FULFILLED: &str = "boundless_requests_fulfilled_total";
+pub const REQUESTS_EXPIRED: &str = "boundless_requests_expired_total";
+pub const TX_FAILURES: &str = "boundless_tx_failures_total";
+pub const GWEI_SPENT: &str = "boundless_spent_gwei_total";
+pub const TIME_TO_LOCK: &str = "boundless_time_to_lock_seconds";
+pub const TIME_TO_FULFILLMENT: &str = "boundless_time_to_fulfillment_seconds";
+pub const PREFLIGHT_DURATION: &str = "boundless_preflight_duration_seconds";
+pub const UPLOAD_DURATION: &str = "boundless_upload_duration_seconds";
+
+/// Format of the log output.
+#[derive(Clone, Copy, Debug, Default, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per event, including the fields of the enclosing spans, such as the
+    /// request ID. Spans also emit an event with their duration when they close.
+    Json,
+}
+
+/// Install the global tracing subscriber, logging as configured by `RUST_LOG`.
+///
+/// If an OTLP endpoint is given, the spans of this crate are also exported to it. The returned
+/// provider must then be shut down before exiting, to flush the pending spans. Logs are written
+/// to stdout, or to stderr if `stderr` is set.
+pub fn init_tracing(
+    log_format: LogFormat,
+    otlp_endpoint: Option<&Url>,
+    stderr: bool,
+) -> Result<Option<SdkTracerProvider>> {
+    let writer = if stderr {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let fmt = match log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer().with_writer(writer).boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .with_writer(writer)
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .boxed(),
+    }
+    .with_filter(EnvFilter::from_default_env());
+    let Some(endpoint) = otlp_endpoint else {
+        tracing_subscriber::registry().with(fmt).init();
+        return Ok(None);
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint.as_str())
+        .build()
+        .context("failed to build the OTLP exporter")?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(env!("CARGO_PKG_NAME"))
+                .build(),
+        )
+        .build();
+    let otel = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+        .with_filter(Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::INFO));
+    tracing_subscriber::registry().with(fmt).with(otel).init();
+    Ok(Some(provider))
+}
+
+/// Report panics, and the errors captured explicitly, to Sentry until the guard is dropped.
+pub fn init_sentry(dsn: Dsn) -> ClientInitGuard {
+    sentry::init(sentry::ClientOptions {
+        dsn: Some(dsn),
+        release: sentry::release_name!(),
+        ..Default::default()
+    })
+}
+
+/// Record the phase of the submission pipeline being run, in the context of reported errors.
+pub fn set_phase(phase: &'static str) {
+    sentry::configure_scope(|scope| scope.set_tag("phase", phase));
+}
+
+/// Record the ID and offer of the request being submitted, in the context of reported errors.
+pub fn set_request_context(request: &ProofRequest) {
+    let offer = &request.offer;
+    let fields = [
+        ("min_price", offer.minPrice.to_string()),
+        ("max_price", offer.maxPrice.to_string()),
+        ("bidding_start", offer.biddingStart.to_string()),
+        ("ramp_up_period", offer.rampUpPeriod.to_string()),
+        ("lock_timeout", offer.lockTimeout.to_string()),
+        ("timeout", offer.timeout.to_string()),
+        ("lock_stake", offer.lockStake.to_string()),
+    ];
+    let offer = fields
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.into()))
+        .collect();
+    sentry::configure_scope(|scope| {
+        scope.set_tag("request_id", format!("{:x}", request.id));
+        scope.set_context("offer", SentryContext::Other(offer));
+    });
+}
+
+/// Serve the metrics in the Prometheus text format on `http://<addr>/metrics`.
+pub fn serve(addr: SocketAddr) -> Result<()> {
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .install()
+        .context("failed to start the metrics listener")?;
+
+    describe_counter!(REQUESTS_SUBMITTED, "Requests submitted to the market");
+    describe_counter!(REQUESTS_FULFILLED, "Requests fulfilled by the market");
+    describe_counter!(REQUESTS_EXPIRED, "Requests that expired unfulfilled");
+    describe_counter!(
+        TX_FAILURES,
+        "Transactions, to the market or the EvenNumber contract, that failed"
+    );
+    describe_counter!(
+        GWEI_SPENT,
+        "Gwei spent on proofs and on the EvenNumber set transactions"
+    );
+    describe_histogram!(
+        TIME_TO_LOCK,
+        Unit::Seconds,
+        "Time from submission until a prover locked the request"
+    );
+    describe_histogram!(
+        TIME_TO_FULFILLMENT,
+        Unit::Seconds,
+        "Time from submission until the request was fulfilled"
+    );
+    describe_histogram!(
+        PREFLIGHT_DURATION,
+        Unit::Seconds,
+        "Time spent building the request, including the preflight execution"
+    );
+    describe_histogram!(
+        UPLOAD_DURATION,
+        Unit::Seconds,
+        "Time spent uploading the program"
+    );
+    tracing::info!("Serving metrics on http://{addr}/metrics");
+    Ok(())
+}
+
+/// Add an amount, in wei, to the gwei spent. Fractions of a gwei are left out.
+pub fn record_eth_spent(wei: U256) {
+    let gwei = wei / U256::from(1_000_000_000u64);
+    counter!(GWEI_SPENT).increment(gwei.try_into().unwrap_or(u64::MAX));
+}
@@ -0,0 +1,81 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Transports of the RPC endpoints: HTTP, WebSocket, or the IPC socket of a local node.
+//!
+//! An RPC URL is passed to alloy, which picks the transport from its scheme: `http(s)://`,
+//! `ws(s)://`, or `file://` for a socket. A bare path, e.g. `/tmp/anvil.ipc`, or an `ipc://` URL
+//! is taken as the socket of a local node. Over a WebSocket or IPC endpoint, the app subscribes
+//! to the events of the requests instead of polling for their status.
+
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use url::Url;
+
+/// Parse an RPC URL, or the path of an IPC socket, into the URL connected to.
+pub fn parse_rpc_url(s: &str) -> Result<Url> {
+    let path = match s.split_once("://") {
+        Some(("ipc", path)) => path,
+        Some(_) => {
+            let url = Url::parse(s).context("invalid RPC URL")?;
+            match url.scheme() {
+                "http" | "https" | "ws" | "wss" | "file" => return Ok(url),
+                scheme => bail!(
+                    "unsupported RPC URL scheme {scheme}, expected http, https, ws, wss or ipc"
+                ),
+            }
+        }
+        None => s,
+    };
+    let path = std::path::absolute(Path::new(path)).context("invalid IPC path")?;
+    Url::from_file_path(&path).map_err(|()| anyhow!("invalid IPC path {}", path.display()))
+}
+
+/// Whether the RPC endpoint is reached over HTTP, rather than a WebSocket or an IPC socket.
+pub fn is_http(url: &Url) -> bool {
+    matches!(url.scheme(), "http" | "https")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rpc_urls() {
+        for url in [
+            "http://localhost:8545/",
+            "https://rpc.example.com/key",
+            "ws://localhost:8546/",
+            "wss://rpc.example.com/key",
+            "file:///tmp/anvil.ipc",
+        ] {
+            assert_eq!(parse_rpc_url(url).unwrap().as_str(), url);
+        }
+        assert!(parse_rpc_url("ftp://example.com").is_err());
+        assert!(!is_http(&parse_rpc_url("wss://rpc.example.com").unwrap()));
+    }
+
+    #[test]
+    fn parses_ipc_paths() {
+        let url = parse_rpc_url("/tmp/anvil.ipc").unwrap();
+        assert_eq!(url.as_str(), "file:///tmp/anvil.ipc");
+        assert_eq!(parse_rpc_url("ipc:///tmp/anvil.ipc").unwrap(), url);
+        assert!(!is_http(&url));
+
+        let relative = parse_rpc_url("anvil.ipc").unwrap();
+        let expected = std::env::current_dir().unwrap().join("anvil.ipc");
+        assert_eq!(relative.to_file_path().unwrap(), expected);
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Terminal dashboard following the lifecycle of submitted requests.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use alloy::{
+    primitives::{utils::format_units, Address, U256},
+    providers::{DynProvider, Provider},
+};
+use anyhow::{Context, Result};
+use boundless_market::{contracts::RequestStatus, Client, Deployment, ProofRequest};
+use clap::Args;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode},
+    layout::Constraint,
+    style::{Color, Style, Stylize},
+    widgets::{Block, Row, Table},
+    DefaultTerminal, Frame,
+};
+use url::Url;
+
+use crate::{
+    failure::Failure,
+    networks::{self, NetworkArgs},
+};
+
+/// Number of blocks to search back for the `RequestLocked` event of a locked request.
+const LOCK_EVENT_LOOKBACK: u64 = 1000;
+
+/// Arguments of the tui command.
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    /// ID of a request to follow, as logged by the submit command. Can be repeated.
+    #[clap(long = "request-id", required = true, value_parser = parse_request_id)]
+    request_ids: Vec<U256>,
+    /// URL of the Ethereum RPC endpoint, or path of the IPC socket of a local node.
+    #[clap(short, long, env, value_parser = crate::transport::parse_rpc_url)]
+    rpc_url: Url,
+    /// Interval, in seconds, between refreshes of the request state.
+    #[clap(long, default_value_t = 5)]
+    refresh_interval: u64,
+    /// Deployment of the Boundless contracts and services to use.
+    ///
+    /// Will be automatically resolved from the connected chain ID if unspecified.
+    #[clap(flatten, next_help_heading = "Boundless Market Deployment")]
+    deployment: Option<Deployment>,
+    #[clap(flatten, next_help_heading = "Boundless Market Deployment")]
+    network: NetworkArgs,
+}
+
+/// Parse a request ID given in hex, with or without the `0x` prefix.
+pub fn parse_request_id(s: &str) -> Result<U256> {
+    U256::from_str_radix(s.trim_start_matches("0x"), 16).context("invalid request ID")
+}
+
+/// Last known state of a request shown on the dashboard.
+struct TrackedRequest {
+    id: U256,
+    request: Option<ProofRequest>,
+    status: RequestStatus,
+    locked_by: Option<Address>,
+    error: Option<String>,
+}
+
+impl TrackedRequest {
+    fn new(id: U256) -> Self {
+        Self {
+            id,
+            request: None,
+            status: RequestStatus::Unknown,
+            locked_by: None,
+            error: None,
+        }
+    }
+
+    async fn refresh<St, R, Si>(&mut self, client: &Client<DynProvider, St, R, Si>) {
+        if let Err(e) = self.try_refresh(client).await {
+            self.error = Some(format!("{e:#}"));
+        }
+    }
+
+    async fn try_refresh<St, R, Si>(
+        &mut self,
+        client: &Client<DynProvider, St, R, Si>,
+    ) -> Result<()> {
+        // Terminal states will not change anymore.
+        if matches!(
+            self.status,
+            RequestStatus::Fulfilled | RequestStatus::Expired
+        ) {
+            return Ok(());
+        }
+        if self.request.is_none() {
+            let order = client
+                .fetch_order(self.id, None, None)
+                .await
+                .context("request not found onchain or in the order stream")?;
+            self.request = Some(order.request);
+        }
+        let expires_at = self.request.as_ref().map(ProofRequest::expires_at);
+        self.status = client
+            .boundless_market
+            .get_status(self.id, expires_at)
+            .await?;
+
+        if self.status == RequestStatus::Locked && self.locked_by.is_none() {
+            let latest = client.provider().get_block_number().await?;
+            let mut event_filter = client.boundless_market.instance().RequestLocked_filter();
+            event_filter.filter = event_filter
+                .filter
+                .topic1(self.id)
+                .from_block(latest.saturating_sub(LOCK_EVENT_LOOKBACK))
+                .to_block(latest);
+            let logs = event_filter
+                .query()
+                .await
+                .context("failed to query lock events")?;
+            self.locked_by = logs.first().map(|(event, _)| event.prover);
+        }
+        self.error = None;
+        Ok(())
+    }
+
+    fn row(&self, now: u64) -> Row<'static> {
+        let (price, expires_in) = match &self.request {
+            Some(request) => {
+                let price = request
+                    .offer
+                    .price_at(now)
+                    .ok()
+                    .and_then(|price| format_units(price, "ether").ok())
+                    .unwrap_or_default();
+                (
+                    price,
+                    format_remaining(request.expires_at().saturating_sub(now)),
+                )
+            }
+            None => (String::new(), String::new()),
+        };
+        let (status, color) = match self.status {
+            RequestStatus::Unknown => ("open", Color::White),
+            RequestStatus::Locked => ("locked", Color::Yellow),
+            RequestStatus::Fulfilled => ("fulfilled", Color::Green),
+            RequestStatus::Expired => ("expired", Color::Red),
+        };
+        let progress = match self.status {
+            RequestStatus::Unknown => "[#--] submitted",
+            RequestStatus::Locked => "[##-] proving",
+            RequestStatus::Fulfilled => "[###] done",
+            RequestStatus::Expired => "[---] failed",
+        };
+        Row::new(vec![
+            format!("{:x}", self.id),
+            status.to_string(),
+            price,
+            self.locked_by
+                .map(|prover| prover.to_string())
+                .unwrap_or_default(),
+            expires_in,
+            self.error.clone().unwrap_or_else(|| progress.to_string()),
+        ])
+        .style(Style::new().fg(color))
+    }
+}
+
+/// Run the dashboard until the user quits with `q` or `Esc`.
+pub async fn run(args: TuiArgs) -> Result<()> {
+    let deployment = args.network.resolve(args.deployment)?;
+    let client = Client::builder()
+        .with_rpc_url(args.rpc_url)
+        .with_deployment(deployment.clone())
+        .build()
+        .await
+        .context("failed to build boundless client")?;
+    networks::ensure_chain(&client.provider(), deployment.as_ref(), &client.deployment)
+        .await
+        .context(Failure::Config)?;
+
+    let mut requests: Vec<_> = args
+        .request_ids
+        .into_iter()
+        .map(TrackedRequest::new)
+        .collect();
+    let refresh_interval = Duration::from_secs(args.refresh_interval);
+
+    let mut terminal = ratatui::init();
+    let result = dashboard(&mut terminal, &client, &mut requests, refresh_interval).await;
+    ratatui::restore();
+    result
+}
+
+async fn dashboard<St, R, Si>(
+    terminal: &mut DefaultTerminal,
+    client: &Client<DynProvider, St, R, Si>,
+    requests: &mut [TrackedRequest],
+    refresh_interval: Duration,
+) -> Result<()> {
+    loop {
+        for request in requests.iter_mut() {
+            request.refresh(client).await;
+        }
+
+        // Redraw frequently so the expiry countdown keeps ticking between refreshes.
+        let next_refresh = Instant::now() + refresh_interval;
+        while Instant::now() < next_refresh {
+            terminal.draw(|frame| draw(frame, requests))?;
+            if event::poll(Duration::from_millis(250))? {
+                if let Event::Key(key) = event::read()? {
+                    if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, requests: &[TrackedRequest]) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let header = [
+        "Request ID",
+        "Status",
+        "Price (ETH)",
+        "Locked by",
+        "Expires in",
+        "Progress",
+    ];
+    let widths = [
+        Constraint::Length(66),
+        Constraint::Length(10),
+        Constraint::Length(22),
+        Constraint::Length(44),
+        Constraint::Length(12),
+        Constraint::Fill(1),
+    ];
+    let table = Table::new(requests.iter().map(|request| request.row(now)), widths)
+        .header(Row::new(header).bold())
+        .block(Block::bordered().title(" Boundless requests (q to quit) "));
+    frame.render_widget(table, frame.area());
+}
+
+fn format_remaining(secs: u64) -> String {
+    match secs {
+        0 => "-".to_string(),
+        s if s < 60 => format!("{s}s"),
+        s if s < 3600 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}h {:02}m", s / 3600, (s % 3600) / 60),
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Costs in USD, at the current ETH/USD price of a Chainlink feed or of an HTTP endpoint.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use alloy::{
+    primitives::{utils::format_units, Address, U256},
+    providers::ProviderBuilder,
+};
+use anyhow::{anyhow, ensure, Context, Result};
+use clap::Args;
+use url::Url;
+
+alloy::sol!(
+    #[sol(rpc)]
+    interface IAggregatorV3 {
+        function decimals() external view returns (uint8);
+        function latestRoundData()
+            external
+            view
+            returns (
+                uint80 roundId,
+                int256 answer,
+                uint256 startedAt,
+                uint256 updatedAt,
+                uint80 answeredInRound
+            );
+    }
+);
+
+/// Age after which the answer of a price feed is logged as stale.
+const STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Arguments giving the source of the ETH/USD price, to show costs in USD.
+#[derive(Args, Clone, Debug, Default)]
+pub struct PriceArgs {
+    /// Address of a Chainlink ETH/USD price feed, to show the costs in USD.
+    #[clap(long, env, conflicts_with = "eth_usd_url")]
+    eth_usd_feed: Option<Address>,
+    /// URL of the RPC endpoint of the chain of the price feed, if it isn't the chain of the
+    /// Boundless Market.
+    #[clap(long, env, requires = "eth_usd_feed", value_parser = crate::transport::parse_rpc_url)]
+    eth_usd_rpc_url: Option<Url>,
+    /// URL of an HTTP endpoint returning the ETH/USD price in a JSON document, to show the costs
+    /// in USD, e.g. https://api.coingecko.com/api/v3/simple/price?ids=ethereum&vs_currencies=usd.
+    #[clap(long, env)]
+    eth_usd_url: Option<Url>,
+    /// JSON pointer to the price in the document returned by `--eth-usd-url`.
+    #[clap(long, env, default_value = "/ethereum/usd")]
+    eth_usd_pointer: String,
+}
+
+/// Price of one ETH in USD.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EthUsd(f64);
+
+impl PriceArgs {
+    /// Fetch the ETH/USD price, or return `None` if no source is given. The price feed is read
+    /// through `rpc_url` unless `--eth-usd-rpc-url` is given.
+    pub async fn eth_usd(&self, rpc_url: Option<&Url>) -> Result<Option<EthUsd>> {
+        let price = if let Some(feed) = self.eth_usd_feed {
+            let rpc_url = self
+                .eth_usd_rpc_url
+                .as_ref()
+                .or(rpc_url)
+                .context("the price feed needs --eth-usd-rpc-url")?;
+            read_feed(rpc_url, feed).await?
+        } else if let Some(url) = &self.eth_usd_url {
+            fetch_price(url, &self.eth_usd_pointer).await?
+        } else {
+            return Ok(None);
+        };
+        ensure!(
+            price.is_finite() && price > 0.0,
+            "invalid ETH/USD price {price}"
+        );
+        Ok(Some(EthUsd(price)))
+    }
+
+    /// Fetch the ETH/USD price, logging a failure as a warning.
+    pub async fn try_eth_usd(&self, rpc_url: Option<&Url>) -> Option<EthUsd> {
+        self.eth_usd(rpc_url)
+            .await
+            .inspect_err(|e| tracing::warn!("Failed to fetch the ETH/USD price: {e:#}"))
+            .ok()
+            .flatten()
+    }
+}
+
+impl EthUsd {
+    /// Value of the amount in wei, in USD.
+    pub fn value(self, wei: U256) -> Result<f64> {
+        let eth: f64 = format_units(wei, "ether")?.parse()?;
+        Ok(eth * self.0)
+    }
+
+    /// Format the value of the amount in wei, e.g. `$1.23`.
+    pub fn format(self, wei: U256) -> Result<String> {
+        Ok(format!("${:.2}", self.value(wei)?))
+    }
+}
+
+/// Amount in wei formatted in ETH, followed by its value in USD if the price is known.
+pub fn format_eth(wei: U256, eth_usd: Option<EthUsd>) -> Result<String> {
+    let eth = format_units(wei, "ether")?;
+    Ok(match eth_usd {
+        Some(eth_usd) => format!("{eth} ETH ({})", eth_usd.format(wei)?),
+        None => format!("{eth} ETH"),
+    })
+}
+
+async fn read_feed(rpc_url: &Url, feed: Address) -> Result<f64> {
+    let provider = ProviderBuilder::new().connect(rpc_url.as_str()).await?;
+    let aggregator = IAggregatorV3::new(feed, &provider);
+    let decimals = aggregator.decimals().call().await?;
+    let round = aggregator
+        .latestRoundData()
+        .call()
+        .await
+        .context("failed to read the price feed")?;
+    ensure!(round.answer.is_positive(), "invalid price feed answer");
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let age = now.saturating_sub(round.updatedAt.saturating_to());
+    if age > STALE_AFTER.as_secs() {
+        tracing::warn!("The ETH/USD price feed was last updated {age}s ago");
+    }
+    let answer: f64 = round.answer.to_string().parse()?;
+    Ok(answer / 10f64.powi(decimals.into()))
+}
+
+async fn fetch_price(url: &Url, pointer: &str) -> Result<f64> {
+    let body = reqwest::get(url.clone())
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let document: serde_json::Value =
+        serde_json::from_slice(&body).context("invalid JSON document")?;
+    parse_price(&document, pointer)
+}
+
+/// Price at the JSON pointer of the document, given as a number or a string.
+fn parse_price(document: &serde_json::Value, pointer: &str) -> Result<f64> {
+    let value = document
+        .pointer(pointer)
+        .ok_or_else(|| anyhow!("no value at {pointer} in the price document"))?;
+    match value {
+        serde_json::Value::Number(number) => number.as_f64(),
+        serde_json::Value::String(string) => string.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("invalid price {value} at {pointer}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_prices() {
+        let document = json!({ "ethereum": { "usd": 2500.5 }, "data": { "amount": "2400.25" } });
+        assert_eq!(parse_price(&document, "/ethereum/usd").unwrap(), 2500.5);
+        assert_eq!(parse_price(&document, "/data/amount").unwrap(), 2400.25);
+        assert!(parse_price(&document, "/bitcoin/usd").is_err());
+        assert!(parse_price(&document, "/ethereum").is_err());
+    }
+
+    #[test]
+    fn formats_usd() {
+        let wei = U256::from(1_500_000_000_000_000u64);
+        assert_eq!(format_eth(wei, None).unwrap(), "0.001500000000000000 ETH");
+        assert_eq!(
+            format_eth(wei, Some(EthUsd(2000.0))).unwrap(),
+            "0.001500000000000000 ETH ($3.00)"
+        );
+    }
+}
@@ -0,0 +1,348 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Offline verification of attestation bundles, or of a journal and a seal, with the RISC Zero
+//! verifier: no RPC endpoint and no market are involved.
+//!
+//! A Groth16 seal is verified on its own. A seal proving the inclusion of the journal in a set
+//! needs the receipt of the root of the set, which the bundle holds when it was exported, and
+//! the image ID of the set builder, bound to the seal by its selector.
+
+use std::{fs, path::PathBuf};
+
+use alloy::hex;
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use clap::Args;
+use guests::{Journal, IS_EVEN_ELF, IS_EVEN_ID};
+use risc0_aggregation::SetInclusionReceiptVerifierParameters;
+use risc0_ethereum_contracts::receipt::{decode_seal, Receipt};
+use risc0_zkvm::{
+    sha::{Digest, Digestible},
+    InnerReceipt, ReceiptClaim, VerifierContext,
+};
+use sha2::{Digest as _, Sha256};
+
+use crate::bundle::{Bundle, Manifest};
+
+/// Arguments of the verify-bundle command.
+#[derive(Args, Clone, Debug)]
+pub struct VerifyBundleArgs {
+    /// Attestation bundle exported with `--export-bundle`.
+    #[clap(
+        required_unless_present = "seal",
+        conflicts_with_all = ["journal", "seal"]
+    )]
+    bundle: Option<PathBuf>,
+    /// File of the journal to verify instead of a bundle, raw or in 0x-prefixed hex.
+    #[clap(long, value_name = "PATH", requires = "seal")]
+    journal: Option<PathBuf>,
+    /// File of the seal to verify instead of a bundle, raw or in 0x-prefixed hex.
+    #[clap(long, value_name = "PATH", requires = "journal")]
+    seal: Option<PathBuf>,
+    /// Image ID of the guest the journal and seal must be of. Defaults to the one of the built
+    /// guest.
+    #[clap(long, value_parser = parse_digest)]
+    image_id: Option<Digest>,
+}
+
+/// What is verified: the proof, and the context of the bundle it comes from, if any.
+pub struct Evidence {
+    pub journal: Vec<u8>,
+    pub seal: Vec<u8>,
+    /// Image ID the seal is verified against: the one of the manifest, for a bundle.
+    pub image_id: Digest,
+    /// Image ID the evidence must be of.
+    pub expected_image_id: Digest,
+    pub manifest: Option<Manifest>,
+    pub receipt: Option<Receipt>,
+}
+
+impl Evidence {
+    fn from_bundle(bundle: Bundle, expected_image_id: Digest) -> Result<Self> {
+        Ok(Self {
+            image_id: parse_digest(&bundle.manifest.image_id)
+                .context("invalid image ID in the manifest")?,
+            expected_image_id,
+            journal: bundle.journal,
+            seal: bundle.seal,
+            manifest: Some(bundle.manifest),
+            receipt: bundle.receipt,
+        })
+    }
+}
+
+/// Verify the bundle, or the journal and seal, and print the outcome of each check.
+pub async fn run(args: VerifyBundleArgs) -> Result<()> {
+    let expected_image_id = args.image_id.unwrap_or(Digest::from(IS_EVEN_ID));
+    let evidence = match (&args.bundle, &args.journal, &args.seal) {
+        (Some(path), _, _) => {
+            let archive =
+                fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+            let bundle = Bundle::from_zip(&archive)
+                .with_context(|| format!("invalid bundle {}", path.display()))?;
+            println!("{:<10}{}", "Bundle:", path.display());
+            println!("{:<10}{}", "Request:", bundle.manifest.request_id);
+            Evidence::from_bundle(bundle, expected_image_id)?
+        }
+        (None, Some(journal), Some(seal)) => Evidence {
+            journal: read_bytes(journal)?,
+            seal: read_bytes(seal)?,
+            image_id: expected_image_id,
+            expected_image_id,
+            manifest: None,
+            receipt: None,
+        },
+        _ => unreachable!("clap requires a bundle, or a journal and a seal"),
+    };
+    // Receipts are verified on a blocking thread, as they take a while.
+    let checks = tokio::task::spawn_blocking(move || verify(&evidence)).await?;
+    let mut passed = true;
+    for (name, outcome) in &checks {
+        match outcome {
+            Ok(outcome) => println!("{name:<10}{outcome}"),
+            Err(e) => {
+                passed = false;
+                println!("{name:<10}FAILED, {e:#}");
+            }
+        }
+    }
+    println!("Verification {}", if passed { "passed" } else { "failed" });
+    ensure!(passed, "verification failed");
+    Ok(())
+}
+
+/// Outcome of each check of the evidence, by name.
+pub fn verify(evidence: &Evidence) -> Vec<(&'static str, Result<String>)> {
+    vec![
+        ("Image ID:", check_image_id(evidence)),
+        ("Journal:", check_journal(evidence)),
+        ("Seal:", check_seal(evidence)),
+    ]
+}
+
+/// The evidence must be of the expected guest, and a bundle of its ELF. The ELF is only known
+/// for the built guest.
+fn check_image_id(evidence: &Evidence) -> Result<String> {
+    ensure!(
+        evidence.image_id == evidence.expected_image_id,
+        "{}, not the expected {}: pass --image-id to verify the evidence of another guest",
+        evidence.image_id,
+        evidence.expected_image_id
+    );
+    if evidence.expected_image_id != Digest::from(IS_EVEN_ID) {
+        return Ok(format!(
+            "{}, as given with --image-id, ELF not checked",
+            evidence.image_id
+        ));
+    }
+    if let Some(manifest) = &evidence.manifest {
+        ensure!(
+            manifest.elf_sha256 == hex::encode(Sha256::digest(IS_EVEN_ELF)),
+            "the SHA-256 of the ELF in the manifest is not the one of the built is-even guest"
+        );
+    }
+    Ok(format!("{}, the built is-even guest", evidence.image_id))
+}
+
+fn check_journal(evidence: &Evidence) -> Result<String> {
+    if let Some(manifest) = &evidence.manifest {
+        ensure!(
+            hex::encode(Sha256::digest(&evidence.journal)) == manifest.journal_sha256,
+            "its SHA-256 doesn't match the manifest"
+        );
+    }
+    let Ok(journal) = Journal::decode(&evidence.journal) else {
+        return Ok(format!("{} bytes", evidence.journal.len()));
+    };
+    if let Some(manifest) = &evidence.manifest {
+        ensure!(
+            journal.number.to_string() == manifest.number,
+            "it encodes {}, not the number {} of the manifest",
+            journal.number,
+            manifest.number
+        );
+    }
+    Ok(format!("encodes the number {}", journal.number))
+}
+
+fn check_seal(evidence: &Evidence) -> Result<String> {
+    let receipt = decode_seal(
+        evidence.seal.clone().into(),
+        evidence.image_id,
+        evidence.journal.clone(),
+    )
+    .map_err(|e| anyhow!("invalid seal: {e}"))?;
+    match receipt {
+        Receipt::Base(receipt) => {
+            if matches!(receipt.inner, InnerReceipt::Fake(_)) {
+                bail!("fake seal of dev mode, not a proof");
+            }
+            receipt
+                .verify(evidence.image_id)
+                .context("invalid Groth16 proof")?;
+            Ok("Groth16 proof, verified".to_string())
+        }
+        Receipt::SetInclusion(inclusion) => {
+            let Some(Receipt::SetInclusion(receipt)) = &evidence.receipt else {
+                bail!(
+                    "proof of inclusion in a set whose root is only verified onchain, and no \
+                     receipt of the root to verify it offline"
+                );
+            };
+            let set_builder = evidence
+                .manifest
+                .as_ref()
+                .and_then(|manifest| manifest.set_builder_image_id.as_deref())
+                .context("no image ID of the set builder in the manifest")?;
+            let parameters = SetInclusionReceiptVerifierParameters {
+                image_id: parse_digest(set_builder).context("invalid set builder image ID")?,
+            };
+            ensure!(
+                parameters.digest() == inclusion.verifier_parameters,
+                "the set builder {set_builder} of the manifest is not the one of the seal"
+            );
+            let claim = ReceiptClaim::ok(evidence.image_id, evidence.journal.clone());
+            ensure!(
+                receipt.claim.digest() == claim.digest()
+                    && receipt.merkle_path == inclusion.merkle_path,
+                "the receipt of the bundle is not the one of the seal"
+            );
+            receipt
+                .verify_integrity_with_context(&VerifierContext::default(), parameters, None)
+                .map_err(|e| anyhow!("invalid proof: {e}"))?;
+            Ok(format!(
+                "proof of inclusion in a set of {} levels, root verified with Groth16",
+                receipt.merkle_path.len()
+            ))
+        }
+    }
+}
+
+/// Contents of a file, decoded from hex if it is 0x-prefixed hex text.
+fn read_bytes(path: &PathBuf) -> Result<Vec<u8>> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    match std::str::from_utf8(&bytes).map(str::trim) {
+        Ok(text) if text.starts_with("0x") => {
+            hex::decode(text).with_context(|| format!("invalid hex in {}", path.display()))
+        }
+        _ => Ok(bytes),
+    }
+}
+
+fn parse_digest(s: &str) -> Result<Digest> {
+    let bytes = hex::decode(s)?;
+    Digest::try_from(bytes.as_slice()).map_err(|_| anyhow!("a digest is 32 bytes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+    use risc0_aggregation::SetInclusionReceipt;
+    use risc0_ethereum_contracts::{encode_seal, selector::Selector};
+    use risc0_zkvm::FakeReceipt;
+
+    use super::*;
+
+    fn evidence(seal: Vec<u8>) -> Evidence {
+        Evidence {
+            journal: Journal::from(U256::from(4)).encode(),
+            seal,
+            image_id: Digest::from(IS_EVEN_ID),
+            expected_image_id: Digest::from(IS_EVEN_ID),
+            manifest: None,
+            receipt: None,
+        }
+    }
+
+    fn failure(checks: &[(&'static str, Result<String>)], name: &str) -> String {
+        let (_, outcome) = checks.iter().find(|(check, _)| *check == name).unwrap();
+        format!("{:#}", outcome.as_ref().unwrap_err())
+    }
+
+    #[test]
+    fn rejects_fake_seals() {
+        let journal = Journal::from(U256::from(4)).encode();
+        let claim = ReceiptClaim::ok(IS_EVEN_ID, journal.clone());
+        let receipt =
+            risc0_zkvm::Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal.clone());
+        let checks = verify(&evidence(encode_seal(&receipt).unwrap()));
+        assert_eq!(checks[1].1.as_ref().unwrap(), "encodes the number 4");
+        assert_eq!(
+            failure(&checks, "Seal:"),
+            "fake seal of dev mode, not a proof"
+        );
+        assert!(failure(&verify(&evidence(vec![1, 2])), "Seal:").contains("invalid seal"));
+    }
+
+    #[test]
+    fn requires_the_root_of_set_inclusion_seals() {
+        let journal = Journal::from(U256::from(4)).encode();
+        let seal = SetInclusionReceipt::from_path_with_verifier_params(
+            ReceiptClaim::ok(IS_EVEN_ID, journal),
+            vec![],
+            Selector::SetVerifierV0_7
+                .verifier_parameters_digest()
+                .unwrap(),
+        )
+        .abi_encode_seal()
+        .unwrap();
+        let checks = verify(&evidence(seal));
+        assert!(failure(&checks, "Seal:").contains("only verified onchain"));
+    }
+
+    #[test]
+    fn checks_the_guest() {
+        let mut evidence = evidence(vec![]);
+        evidence.manifest = Some(crate::bundle::tests::manifest());
+        assert!(check_image_id(&evidence)
+            .unwrap()
+            .ends_with("the built is-even guest"));
+
+        evidence.manifest = Some(Manifest {
+            elf_sha256: hex::encode(Sha256::digest([0])),
+            ..crate::bundle::tests::manifest()
+        });
+        assert!(check_image_id(&evidence)
+            .unwrap_err()
+            .to_string()
+            .contains("SHA-256 of the ELF"));
+
+        let other = Digest::from([7u32; 8]);
+        evidence.image_id = other;
+        assert!(check_image_id(&evidence)
+            .unwrap_err()
+            .to_string()
+            .contains("pass --image-id"));
+        evidence.expected_image_id = other;
+        assert!(check_image_id(&evidence)
+            .unwrap()
+            .ends_with("ELF not checked"));
+    }
+
+    #[test]
+    fn checks_the_journal_against_the_manifest() {
+        let mut evidence = evidence(vec![]);
+        let manifest = |journal_sha256: String| Manifest {
+            journal_sha256,
+            ..crate::bundle::tests::manifest()
+        };
+        evidence.manifest = Some(manifest(hex::encode(Sha256::digest(&evidence.journal))));
+        assert!(check_journal(&evidence).is_ok());
+        evidence.manifest = Some(manifest(hex::encode(Sha256::digest([0]))));
+        assert_eq!(
+            check_journal(&evidence).unwrap_err().to_string(),
+            "its SHA-256 doesn't match the manifest"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Version of the binary, with what it was built against: the image IDs of the guests, the
+//! version of the zkVM, and the deployments of the built-in networks. Printed by `--version`, so
+//! that a bug report pins down the program a prover was asked to run.
+
+use std::{fmt::Write, sync::OnceLock};
+
+use guests::IS_EVEN_ID;
+use risc0_zkvm::sha::Digest;
+
+use crate::networks;
+
+/// Output of `--version`.
+pub fn long_version() -> &'static str {
+    static VERSION: OnceLock<String> = OnceLock::new();
+    VERSION.get_or_init(|| {
+        let mut version = format!("{}\n", env!("CARGO_PKG_VERSION"));
+        writeln!(version, "risc0-zkvm {}", risc0_zkvm::VERSION).unwrap();
+        writeln!(version, "guests:").unwrap();
+        writeln!(version, "  is-even {}", Digest::from(IS_EVEN_ID)).unwrap();
+        writeln!(version, "networks:").unwrap();
+        for (name, deployment) in networks::builtin() {
+            let chain_id = deployment
+                .chain_id
+                .map_or_else(|| "?".to_string(), |id| id.to_string());
+            writeln!(version, "  {name} (chain {chain_id})").unwrap();
+            writeln!(
+                version,
+                "    market {}",
+                deployment.boundless_market_address
+            )
+            .unwrap();
+            writeln!(
+                version,
+                "    set verifier {}",
+                deployment.set_verifier_address
+            )
+            .unwrap();
+            if let Some(router) = deployment.verifier_router_address {
+                writeln!(version, "    verifier router {router}").unwrap();
+            }
+        }
+        version.trim_end().to_string()
+    })
+}
@@ -0,0 +1,576 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Waiting for a submitted request to be fulfilled, reporting the progress of its auction.
+//!
+//! Over an HTTP RPC endpoint, the status of the request is polled, on a [PollSchedule]. Over a
+//! WebSocket or IPC endpoint, the lock and fulfillment events of the request are subscribed to
+//! instead, and the status is only checked when one is received, or every
+//! [SUBSCRIBED_CHECK_INTERVAL] to report the progress.
+//!
+//! Applications reacting to the lifecycle of a request, rather than waiting for its fulfillment,
+//! can follow the [events] of the request instead.
+
+use std::{
+    pin::Pin,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{utils::format_units, Address, Bytes, B256, U256},
+    providers::{DynProvider, Provider},
+};
+use anyhow::{Context, Result};
+use boundless_market::{
+    contracts::{boundless_market::MarketError, RequestStatus},
+    ProofRequest, StandardClient,
+};
+use futures_util::{stream, Stream, StreamExt};
+use tracing::{info_span, Instrument};
+
+use crate::{failure::Failure, history::Timings, lock_alert::LockAlert};
+
+/// Number of recent blocks used to estimate the block time of the chain.
+const BLOCK_TIME_SAMPLE: u64 = 100;
+
+/// Maximum interval between status checks when subscribed to the events of the request.
+const SUBSCRIBED_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A fulfilled request.
+pub struct Fulfillment {
+    pub journal: Bytes,
+    pub seal: Bytes,
+    /// The lock of the request, if a prover locked it before fulfilling it.
+    pub lock: Option<Lock>,
+}
+
+/// A transition in the lifecycle of a request.
+#[derive(Clone, Debug)]
+pub enum RequestEvent {
+    /// The request was submitted, and expires at the given timestamp.
+    Submitted { request_id: U256, expires_at: u64 },
+    /// A prover locked the request. The lock is `None` if its event couldn't be found.
+    Locked {
+        request_id: U256,
+        lock: Option<Lock>,
+    },
+    /// The request was fulfilled.
+    Fulfilled {
+        request_id: U256,
+        journal: Bytes,
+        seal: Bytes,
+    },
+    /// The request expired before being fulfilled. A prover that locked it is slashed.
+    Expired { request_id: U256, slashed: bool },
+    /// The proof was posted in the given transaction.
+    Posted { request_id: U256, tx_hash: B256 },
+}
+
+/// A prover locking a request.
+#[derive(Clone, Debug)]
+pub struct Lock {
+    pub prover: Address,
+    /// Timestamp of the block in which the request was locked.
+    pub timestamp: u64,
+}
+
+/// Wait for the request to be locked and then fulfilled, logging its progress at each status
+/// check: on the `schedule` when polling, or on each event when subscribed.
+///
+/// While the auction is open, the current price of the request is logged; once locked, the prover
+/// that locked it. Both are logged along with the time and estimated number of blocks remaining
+/// until the request expires. If the request expires, the error is a [Failure::Expired], or a
+/// [Failure::Slashed] if it was locked. The time spent waiting for the lock and for the
+/// fulfillment is recorded in `timings`, even if the request expires.
+pub async fn wait_for_fulfillment(
+    client: &StandardClient,
+    request: &ProofRequest,
+    from_block: u64,
+    schedule: PollSchedule,
+    alert: Option<&LockAlert>,
+    timings: &mut Timings,
+) -> Result<Fulfillment> {
+    let market = &client.boundless_market;
+    let expires_at = request.expires_at();
+    let block_time = estimate_block_time(&client.provider())
+        .await
+        .inspect_err(|e| tracing::warn!("Failed to estimate the block time: {e:#}"))
+        .ok();
+    let mut wakeups = Wakeups::new(client, request.id, schedule).await;
+
+    let start = Instant::now();
+    let status = async {
+        let mut checks = 0;
+        let mut alerted = false;
+        loop {
+            checks += 1;
+            let status = market.get_status(request.id, Some(expires_at)).await?;
+            if status != RequestStatus::Unknown {
+                return anyhow::Ok(status);
+            }
+            let now = now();
+            let price = request
+                .offer
+                .price_at(now)
+                .ok()
+                .and_then(|price| format_units(price, "ether").ok())
+                .unwrap_or_default();
+            tracing::info!(
+                "Request {:x} is open at a price of {price} ETH, expires in {}",
+                request.id,
+                remaining(expires_at, now, block_time)
+            );
+            if let Some(alert) = alert.filter(|_| !alerted) {
+                alerted = alert.check(client, request, from_block).await;
+            }
+            wakeups.next(request, false, checks).await;
+        }
+    }
+    .instrument(info_span!("auction"))
+    .await;
+    timings.lock = Some(start.elapsed().as_secs_f64());
+    let status = status?;
+
+    let lock = match status {
+        RequestStatus::Locked | RequestStatus::Fulfilled => {
+            find_lock(client, request.id, from_block)
+                .await
+                .inspect_err(|e| tracing::warn!("Failed to find the lock of the request: {e:#}"))
+                .ok()
+                .flatten()
+        }
+        _ => None,
+    };
+    if let Some(lock) = &lock {
+        let price = request
+            .offer
+            .price_at(lock.timestamp)
+            .ok()
+            .and_then(|price| format_units(price, "ether").ok())
+            .unwrap_or_default();
+        tracing::info!(
+            "Request {:x} locked by {} at a price of {price} ETH",
+            request.id,
+            lock.prover
+        );
+    }
+
+    let start = Instant::now();
+    let fulfillment = async {
+        let mut checks = 0;
+        loop {
+            checks += 1;
+            let status = market.get_status(request.id, Some(expires_at)).await?;
+            match status {
+                RequestStatus::Fulfilled => break,
+                RequestStatus::Expired => {
+                    let failure = match lock {
+                        Some(_) => Failure::Slashed,
+                        None => Failure::Expired,
+                    };
+                    return Err(MarketError::RequestHasExpired(request.id)).context(failure);
+                }
+                _ => {
+                    let prover = lock.as_ref().map(|lock| lock.prover.to_string());
+                    tracing::info!(
+                        "Request {:x} is being proven by {}, expires in {}",
+                        request.id,
+                        prover.as_deref().unwrap_or("an unknown prover"),
+                        remaining(expires_at, now(), block_time)
+                    );
+                    wakeups.next(request, true, checks).await;
+                }
+            }
+        }
+        Ok(market.get_request_fulfillment(request.id).await?)
+    }
+    .instrument(info_span!("fulfillment"))
+    .await;
+    timings.fulfillment = Some(start.elapsed().as_secs_f64());
+    let (journal, seal) = fulfillment?;
+
+    Ok(Fulfillment {
+        journal,
+        seal,
+        lock,
+    })
+}
+
+/// Stream of the events of a submitted request: [RequestEvent::Locked], then
+/// [RequestEvent::Fulfilled] or [RequestEvent::Expired], after which the stream ends.
+///
+/// The status of the request is checked on the same wake-ups as [wait_for_fulfillment]. The lock
+/// is not reported if the request is fulfilled before a check sees it locked.
+pub fn events(
+    client: &StandardClient,
+    request: ProofRequest,
+    from_block: u64,
+    schedule: PollSchedule,
+) -> impl Stream<Item = Result<RequestEvent>> + '_ {
+    struct State<'a> {
+        client: &'a StandardClient,
+        request: ProofRequest,
+        wakeups: Option<Wakeups>,
+        locked: bool,
+        done: bool,
+    }
+
+    let state = State {
+        client,
+        request,
+        wakeups: None,
+        locked: false,
+        done: false,
+    };
+    stream::unfold(state, move |mut state| async move {
+        if state.done {
+            return None;
+        }
+        let State {
+            client,
+            request,
+            wakeups,
+            locked,
+            ..
+        } = &mut state;
+        let wakeups = match wakeups {
+            Some(wakeups) => wakeups,
+            None => wakeups.insert(Wakeups::new(client, request.id, schedule).await),
+        };
+        let event = next_event(client, request, from_block, wakeups, locked).await;
+        state.done = !matches!(event, Ok(RequestEvent::Locked { .. }));
+        Some((event, state))
+    })
+}
+
+/// Check the status of the request until it changes from the last one reported.
+async fn next_event(
+    client: &StandardClient,
+    request: &ProofRequest,
+    from_block: u64,
+    wakeups: &mut Wakeups,
+    locked: &mut bool,
+) -> Result<RequestEvent> {
+    let market = &client.boundless_market;
+    let request_id = request.id;
+    let mut checks = 0;
+    loop {
+        checks += 1;
+        let status = market
+            .get_status(request_id, Some(request.expires_at()))
+            .await?;
+        match status {
+            RequestStatus::Locked if !*locked => {
+                *locked = true;
+                let lock = find_lock(client, request_id, from_block)
+                    .await
+                    .inspect_err(|e| {
+                        tracing::warn!("Failed to find the lock of the request: {e:#}")
+                    })
+                    .ok()
+                    .flatten();
+                return Ok(RequestEvent::Locked { request_id, lock });
+            }
+            RequestStatus::Fulfilled => {
+                let (journal, seal) = market.get_request_fulfillment(request_id).await?;
+                return Ok(RequestEvent::Fulfilled {
+                    request_id,
+                    journal,
+                    seal,
+                });
+            }
+            RequestStatus::Expired => {
+                return Ok(RequestEvent::Expired {
+                    request_id,
+                    slashed: *locked,
+                })
+            }
+            _ => wakeups.next(request, *locked, checks).await,
+        }
+    }
+}
+
+/// Adaptive schedule of the status checks of a request, when polling.
+///
+/// The request is checked at the `min` interval while its status is likely to change: during the
+/// ramp-up of the auction, when provers usually lock requests, and close to the lock and request
+/// deadlines. Otherwise the interval doubles after each check, up to `max`. Each interval is
+/// jittered by up to 20%, so that publishers started together don't poll in lockstep.
+#[derive(Clone, Copy, Debug)]
+pub struct PollSchedule {
+    min: Duration,
+    max: Duration,
+}
+
+impl Default for PollSchedule {
+    /// The defaults of the `--poll-interval` and `--max-poll-interval` options.
+    fn default() -> Self {
+        Self {
+            min: Duration::from_secs(5),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+impl PollSchedule {
+    /// Schedule between the `min` and `max` intervals, which must be positive and in order.
+    pub fn new(min: Duration, max: Duration) -> Result<Self> {
+        anyhow::ensure!(
+            !min.is_zero() && min <= max,
+            "the poll interval must be positive, and at most the maximum poll interval"
+        );
+        Ok(Self { min, max })
+    }
+
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Interval until the next check, before jitter, after the given number of checks in the
+    /// current state of the request.
+    fn interval(&self, request: &ProofRequest, locked: bool, checks: u32, now: u64) -> Duration {
+        let ramp_up_end = request.offer.biddingStart + u64::from(request.offer.rampUpPeriod);
+        let next_deadline = [request.lock_expires_at(), request.expires_at()]
+            .into_iter()
+            .find(|&deadline| deadline >= now);
+        let near_deadline =
+            next_deadline.is_some_and(|deadline| Duration::from_secs(deadline - now) <= self.max);
+        if (!locked && now < ramp_up_end) || near_deadline {
+            return self.min;
+        }
+        self.min
+            .saturating_mul(2u32.saturating_pow(checks.saturating_sub(1)))
+            .min(self.max)
+    }
+}
+
+/// Wake-ups between status checks of a request.
+enum Wakeups {
+    /// On a polling schedule.
+    Poll(PollSchedule),
+    /// On each lock or fulfillment event of the request, or at least every
+    /// [SUBSCRIBED_CHECK_INTERVAL]. Falls back to polling if the subscription ends.
+    Events(Pin<Box<dyn Stream<Item = ()>>>, PollSchedule),
+}
+
+impl Wakeups {
+    /// Subscribe to the events of the request if the RPC endpoint supports it, or poll otherwise.
+    async fn new(client: &StandardClient, request_id: U256, schedule: PollSchedule) -> Self {
+        if client.provider().client().pubsub_frontend().is_none() {
+            return Self::Poll(schedule);
+        }
+        match subscribe(client, request_id).await {
+            Ok(events) => {
+                tracing::debug!("Subscribed to the events of request {request_id:x}");
+                Self::Events(events, schedule)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to subscribe to the events of the request, polling: {e:#}");
+                Self::Poll(schedule)
+            }
+        }
+    }
+
+    /// Wait until the next check, after the given number of checks in the current state.
+    async fn next(&mut self, request: &ProofRequest, locked: bool, checks: u32) {
+        match self {
+            Self::Poll(schedule) => {
+                let interval = schedule.interval(request, locked, checks, now());
+                tokio::time::sleep(interval.mul_f64(rand::random_range(0.8..1.2))).await
+            }
+            Self::Events(events, schedule) => {
+                // Check again right after the expiry, rather than waiting for the next interval.
+                let timeout = Duration::from_secs(request.expires_at().saturating_sub(now()) + 1)
+                    .min(SUBSCRIBED_CHECK_INTERVAL);
+                if let Ok(None) = tokio::time::timeout(timeout, events.next()).await {
+                    tracing::warn!("Subscription to the events of the request ended, polling");
+                    *self = Self::Poll(*schedule);
+                }
+            }
+        }
+    }
+}
+
+/// Subscribe to the lock and fulfillment events of the request.
+async fn subscribe(
+    client: &StandardClient,
+    request_id: U256,
+) -> Result<Pin<Box<dyn Stream<Item = ()>>>> {
+    let market = client.boundless_market.instance();
+    let locked = market
+        .RequestLocked_filter()
+        .topic1(request_id)
+        .subscribe()
+        .await?
+        .into_stream();
+    let fulfilled = market
+        .RequestFulfilled_filter()
+        .topic1(request_id)
+        .subscribe()
+        .await?
+        .into_stream();
+    Ok(Box::pin(stream::select(
+        locked.map(|_| ()),
+        fulfilled.map(|_| ()),
+    )))
+}
+
+/// Find the transaction fulfilling the request in the blocks since `from_block`, and its block.
+pub(crate) async fn find_fulfillment_tx(
+    client: &StandardClient,
+    request_id: U256,
+    from_block: u64,
+) -> Result<(B256, u64)> {
+    let mut event_filter = client.boundless_market.instance().RequestFulfilled_filter();
+    event_filter.filter = event_filter
+        .filter
+        .topic1(request_id)
+        .from_block(from_block);
+    let logs = event_filter
+        .query()
+        .await
+        .context("failed to query fulfillment events")?;
+    logs.last()
+        .and_then(|(_, log)| log.transaction_hash.zip(log.block_number))
+        .context("the fulfillment event of the request was not found")
+}
+
+/// Find the lock of the request in the blocks since `from_block`.
+pub(crate) async fn find_lock(
+    client: &StandardClient,
+    request_id: U256,
+    from_block: u64,
+) -> Result<Option<Lock>> {
+    let mut event_filter = client.boundless_market.instance().RequestLocked_filter();
+    event_filter.filter = event_filter
+        .filter
+        .topic1(request_id)
+        .from_block(from_block);
+    let logs = event_filter
+        .query()
+        .await
+        .context("failed to query lock events")?;
+    let Some((event, log)) = logs.first() else {
+        return Ok(None);
+    };
+    let block_number = log
+        .block_number
+        .context("lock event without block number")?;
+    let block = client
+        .provider()
+        .get_block_by_number(block_number.into())
+        .await?
+        .context("lock block not found")?;
+    Ok(Some(Lock {
+        prover: event.prover,
+        timestamp: block.header.timestamp,
+    }))
+}
+
+/// Estimate the average block time, in seconds, over the recent blocks.
+async fn estimate_block_time(provider: &DynProvider) -> Result<f64> {
+    let latest = provider.get_block_number().await?;
+    let earlier = latest.saturating_sub(BLOCK_TIME_SAMPLE);
+    let timestamp = |block: u64| async move {
+        anyhow::Ok(
+            provider
+                .get_block_by_number(block.into())
+                .await?
+                .context("block not found")?
+                .header
+                .timestamp,
+        )
+    };
+    let elapsed = timestamp(latest)
+        .await?
+        .saturating_sub(timestamp(earlier).await?);
+    anyhow::ensure!(elapsed > 0, "not enough blocks to estimate the block time");
+    Ok(elapsed as f64 / (latest - earlier) as f64)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Time remaining until the expiry, with the estimated number of blocks when the block time is
+/// known.
+fn remaining(expires_at: u64, now: u64, block_time: Option<f64>) -> String {
+    let secs = expires_at.saturating_sub(now);
+    match block_time {
+        Some(block_time) => format!("{secs}s (~{:.0} blocks)", secs as f64 / block_time),
+        None => format!("{secs}s"),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloy::primitives::Address;
+    use boundless_market::{contracts::Predicate, Offer, RequestId, RequestInput, Requirements};
+    use risc0_zkvm::sha::Digest;
+
+    use super::*;
+
+    /// A request with the offer, of the client `0x0101…01` at index 0, for a program and an input
+    /// the tests don't run. Tests needing other fields set them on the request.
+    pub(crate) fn new_request(offer: Offer) -> ProofRequest {
+        ProofRequest::new(
+            RequestId::new(Address::repeat_byte(1), 0),
+            Requirements::new(Digest::from([1; 8]), Predicate::prefix_match([])),
+            "https://example.com",
+            RequestInput::inline([]),
+            offer,
+        )
+    }
+
+    #[test]
+    fn rejects_schedules_without_a_delay_or_out_of_order() {
+        let secs = Duration::from_secs;
+        assert!(PollSchedule::new(secs(5), secs(5)).is_ok());
+        assert!(PollSchedule::new(Duration::ZERO, secs(60)).is_err());
+        assert!(PollSchedule::new(secs(60), secs(5)).is_err());
+    }
+
+    #[test]
+    fn backs_off_outside_of_the_expected_windows() {
+        let offer = Offer::default()
+            .with_bidding_start(1000)
+            .with_ramp_up_period(100)
+            .with_lock_timeout(1000)
+            .with_timeout(2000);
+        let request = new_request(offer);
+        let schedule = PollSchedule {
+            min: Duration::from_secs(5),
+            max: Duration::from_secs(60),
+        };
+        let interval = |locked, checks, now| schedule.interval(&request, locked, checks, now);
+
+        // During the ramp-up of the auction.
+        assert_eq!(interval(false, 4, 1050), Duration::from_secs(5));
+        // After the ramp-up, backing off up to the maximum.
+        assert_eq!(interval(false, 1, 1200), Duration::from_secs(5));
+        assert_eq!(interval(false, 3, 1200), Duration::from_secs(20));
+        assert_eq!(interval(true, 10, 1200), Duration::from_secs(60));
+        // Close to the lock deadline, and to the expiry.
+        assert_eq!(interval(true, 10, 1980), Duration::from_secs(5));
+        assert_eq!(interval(true, 10, 2500), Duration::from_secs(60));
+        assert_eq!(interval(true, 10, 2950), Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Behavior of the command line, without connecting to any network.
+//!
+//! Snapshots are updated with `INSTA_UPDATE=always cargo test --test cli`, or `cargo insta review`.
+
+use assert_cmd::Command;
+
+/// The app, run in an empty directory with an empty environment, so that neither a `.env` file
+/// nor the variables of the shell leak into the test.
+fn app(dir: &std::path::Path) -> Command {
+    let mut cmd = Command::cargo_bin("app").unwrap();
+    cmd.env_clear().current_dir(dir);
+    cmd
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("app-cli-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+}
+
+fn stderr(cmd: &mut Command, code: i32) -> String {
+    let output = cmd.assert().code(code).get_output().stderr.clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn help() {
+    let dir = temp_dir("help");
+    insta::assert_snapshot!("help", stdout(app(&dir).arg("--help")));
+    insta::assert_snapshot!("submit_help", stdout(app(&dir).args(["submit", "--help"])));
+}
+
+#[test]
+fn prints_completions() {
+    let dir = temp_dir("completions");
+    for shell in ["bash", "zsh", "fish"] {
+        let script = stdout(app(&dir).args(["completions", shell]));
+        assert!(script.contains("even-number-address"), "{shell}: {script}");
+        assert!(script.contains("selftest"), "{shell}: {script}");
+    }
+}
+
+#[test]
+fn prints_version() {
+    let dir = temp_dir("version");
+    let short = stdout(app(&dir).arg("-V"));
+    assert!(short.ends_with(&format!(" {}\n", env!("CARGO_PKG_VERSION"))));
+    let long = stdout(app(&dir).arg("--version"));
+    assert!(long.starts_with(&short), "{long}");
+    for expected in [
+        "risc0-zkvm 2.",
+        "is-even ",
+        "sepolia (chain 11155111)",
+        "market 0x",
+    ] {
+        assert!(long.contains(expected), "{expected}: {long}");
+    }
+}
+
+#[test]
+fn missing_configuration() {
+    let dir = temp_dir("missing");
+    let error = stderr(app(&dir).args(["submit", "--number", "4"]), 2);
+    insta::assert_snapshot!(error);
+}
+
+#[test]
+fn requires_a_number() {
+    let dir = temp_dir("number");
+    let error = stderr(
+        app(&dir)
+            .args(["submit"])
+            .env("RPC_URL", "http://localhost:8545"),
+        2,
+    );
+    assert!(error.contains("--number <NUMBERS>"), "{error}");
+
+    let error = stderr(
+        app(&dir)
+            .args(["submit", "--number", "4", "--input-hex", "0x04"])
+            .env("RPC_URL", "http://localhost:8545"),
+        2,
+    );
+    assert!(error.contains("cannot be used with"), "{error}");
+
+    let error = stderr(
+        app(&dir)
+            .args(["estimate"])
+            .env("RPC_URL", "http://localhost:8545"),
+        2,
+    );
+    assert!(error.contains("--number <NUMBER>"), "{error}");
+}
+
+#[test]
+fn prove_requires_a_readable_elf() {
+    let dir = temp_dir("prove");
+    let error = stderr(
+        app(&dir)
+            .args(["prove", "--elf", "missing.elf", "--input-hex", "0x04"])
+            .env("RPC_URL", "http://localhost:8545")
+            .env("PRIVATE_KEY", format!("0x{}", "11".repeat(32))),
+        2,
+    );
+    assert!(
+        error.contains("failed to read the ELF missing.elf"),
+        "{error}"
+    );
+}
+
+#[test]
+fn simulates_the_auction_of_an_offer() {
+    let dir = temp_dir("auction");
+    let offer = r#"{"minPrice": "100", "maxPrice": "500", "biddingStart": 1750000000,
+        "rampUpPeriod": 20, "lockTimeout": 60, "timeout": 100, "lockStake": "0"}"#;
+    std::fs::write(dir.join("offer.json"), offer).unwrap();
+    let table =
+        stdout(app(&dir).args(["auction", "--request", "offer.json", "--block-time", "10"]));
+    insta::assert_snapshot!(table);
+
+    let error = stderr(
+        app(&dir).args(["auction", "--max-price", "1", "--lock-timeout", "2000"]),
+        2,
+    );
+    assert!(error.contains("the lock timeout by the timeout"), "{error}");
+}
+
+#[test]
+fn resumes_nothing_without_requests_in_flight() {
+    let dir = temp_dir("resume");
+    // Nothing is in flight, so the RPC endpoint is never connected to.
+    let mut cmd = app(&dir);
+    cmd.arg("resume")
+        .env("RPC_URL", "http://localhost:1")
+        .env(
+            "PRIVATE_KEY",
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        )
+        .env(
+            "EVEN_NUMBER_ADDRESS",
+            "0x0000000000000000000000000000000000000001",
+        );
+    assert_eq!(stdout(&mut cmd), "");
+    assert!(dir.join("history.sqlite").exists());
+}
+
+#[test]
+fn requires_the_bonsai_environment() {
+    let dir = temp_dir("bonsai");
+    let mut cmd = app(&dir);
+    cmd.args(["submit", "--number", "4", "--prover", "bonsai"])
+        .env("RPC_URL", "http://localhost:1")
+        .env(
+            "PRIVATE_KEY",
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        )
+        .env(
+            "EVEN_NUMBER_ADDRESS",
+            "0x0000000000000000000000000000000000000001",
+        )
+        .env_remove("BONSAI_API_URL")
+        .env_remove("BONSAI_API_KEY");
+    let error = stderr(&mut cmd, 2);
+    assert!(error.contains("BONSAI_API_URL must be set"), "{error}");
+}
+
+#[test]
+fn schedules_only_market_requests() {
+    let dir = temp_dir("schedule-bonsai");
+    let mut cmd = app(&dir);
+    cmd.args([
+        "schedule",
+        "--schedule",
+        "*/10 * * * *",
+        "--number",
+        "4",
+        "--prover",
+        "bonsai",
+    ])
+    .env("RPC_URL", "http://localhost:1")
+    .env(
+        "PRIVATE_KEY",
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+    )
+    .env(
+        "EVEN_NUMBER_ADDRESS",
+        "0x0000000000000000000000000000000000000001",
+    );
+    let error = stderr(&mut cmd, 2);
+    assert!(
+        error.contains("only supported by the submit command, not by schedule"),
+        "{error}"
+    );
+}
+
+#[test]
+fn dev_mode_publishes_to_a_single_contract() {
+    let dir = temp_dir("dev-targets");
+    let mut cmd = app(&dir);
+    cmd.args(["submit", "--number", "4", "--dev-mode"])
+        .env("RPC_URL", "http://localhost:1")
+        .env(
+            "PRIVATE_KEY",
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        )
+        .env(
+            "EVEN_NUMBER_ADDRESS",
+            "0x0000000000000000000000000000000000000001,0x0000000000000000000000000000000000000002",
+        );
+    let error = stderr(&mut cmd, 2);
+    assert!(error.contains("a single EvenNumber contract"), "{error}");
+}
+
+#[test]
+fn rejects_invalid_values() {
+    let dir = temp_dir("invalid");
+    let error = stderr(app(&dir).args(["history", "--tag", "campaign"]), 2);
+    insta::assert_snapshot!(error);
+
+    let mut cmd = app(&dir);
+    cmd.args(["submit", "--number", "4"])
+        .env("RPC_URL", "http://localhost:8545")
+        .env("EVEN_NUMBER_ADDRESS", "0x1234");
+    let error = stderr(&mut cmd, 2);
+    assert!(error.contains("--even-number-address"), "{error}");
+}
+
+#[test]
+fn client_contracts_are_not_topped_up() {
+    let dir = temp_dir("client-contract");
+    let error = stderr(
+        app(&dir).args([
+            "submit",
+            "--number",
+            "4",
+            "--client-contract",
+            "0x0000000000000000000000000000000000000002",
+            "--auto-deposit",
+        ]),
+        2,
+    );
+    assert!(
+        error.contains("'--client-contract <ADDRESS>' cannot be used with '--auto-deposit'"),
+        "{error}"
+    );
+}
+
+#[test]
+fn reads_options_from_the_environment() {
+    let dir = temp_dir("env");
+    // An empty history is created at the path given by HISTORY_DB rather than the default one.
+    let listed = stdout(
+        app(&dir)
+            .args(["history", "--json"])
+            .env("HISTORY_DB", "from-env.sqlite"),
+    );
+    assert_eq!(listed, "");
+    assert!(dir.join("from-env.sqlite").exists());
+    assert!(!dir.join("history.sqlite").exists());
+}
+
+#[test]
+fn keeps_the_history_in_the_state_directory() {
+    let dir = temp_dir("state-dir");
+    let listed = stdout(app(&dir).args(["history", "--json", "--state-dir", "state"]));
+    assert_eq!(listed, "");
+    assert!(dir.join("state/history.sqlite").exists());
+    assert!(!dir.join("history.sqlite").exists());
+}
+
+#[test]
+fn reads_options_from_a_dotenv_file() {
+    let dir = temp_dir("dotenv");
+    std::fs::write(dir.join(".env"), "HISTORY_DB=from-dotenv.sqlite\n").unwrap();
+    stdout(app(&dir).args(["history", "--json"]));
+    assert!(dir.join("from-dotenv.sqlite").exists());
+}
+
+#[test]
+fn reads_options_from_a_selected_env_file() {
+    let dir = temp_dir("env-file");
+    std::fs::write(dir.join(".env"), "HISTORY_DB=from-dotenv.sqlite\n").unwrap();
+    std::fs::write(dir.join(".env.sepolia"), "HISTORY_DB=from-profile.sqlite\n").unwrap();
+    std::fs::write(dir.join("prod.env"), "HISTORY_DB=from-env-file.sqlite\n").unwrap();
+    stdout(
+        app(&dir)
+            .args(["history", "--json"])
+            .env("BOUNDLESS_PROFILE", "sepolia"),
+    );
+    assert!(dir.join("from-profile.sqlite").exists());
+    stdout(app(&dir).args(["history", "--json", "--env-file", "prod.env"]));
+    assert!(dir.join("from-env-file.sqlite").exists());
+    assert!(!dir.join("from-dotenv.sqlite").exists());
+
+    let error = stderr(app(&dir).args(["history", "--env-file", "missing.env"]), 2);
+    assert!(error.contains("failed to load missing.env"), "{error}");
+}
@@ -0,0 +1,69 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::{path::Path, process::Command};
+
+use alloy::{
+    node_bindings::Anvil,
+    primitives::{Address, U256},
+    providers::ProviderBuilder,
+};
+
+alloy::sol!(
+    #![sol(rpc)]
+    "../contracts/src/IEvenNumber.sol"
+);
+
+// NOTE: Requires Anvil and Forge on the PATH, and the guest to be built.
+#[tokio::test]
+#[ignore = "requires Foundry"]
+async fn publishes_number_in_dev_mode() {
+    let anvil = Anvil::new().spawn();
+    let private_key = format!("0x{}", alloy::hex::encode(anvil.keys()[0].to_bytes()));
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+
+    // Deploy EvenNumber with the mock verifier accepting fake receipts.
+    let output = Command::new("forge")
+        .args(["script", "contracts/scripts/DeployDev.s.sol", "--broadcast"])
+        .args(["--rpc-url", &anvil.endpoint()])
+        .env("PRIVATE_KEY", &private_key)
+        .current_dir(workspace)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "failed to deploy the contracts");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let even_number_address: Address = stdout
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Deployed EvenNumber to "))
+        .expect("no EvenNumber address in the logs of the script")
+        .parse()
+        .unwrap();
+
+    // Run the publisher against the devnet.
+    let history_db = std::env::temp_dir().join(format!("dev-mode-{}.sqlite", anvil.port()));
+    let status = Command::new(env!("CARGO_BIN_EXE_app"))
+        .args(["submit", "--number", "4", "--dev-mode"])
+        .env("RPC_URL", anvil.endpoint())
+        .env("PRIVATE_KEY", &private_key)
+        .env("EVEN_NUMBER_ADDRESS", even_number_address.to_string())
+        .env("HISTORY_DB", &history_db)
+        .status()
+        .unwrap();
+    std::fs::remove_file(&history_db).ok();
+    assert!(status.success());
+
+    let provider = ProviderBuilder::new().connect_http(anvil.endpoint_url());
+    let even_number = IEvenNumber::new(even_number_address, provider);
+    assert_eq!(even_number.get().call().await.unwrap(), U256::from(4));
+}
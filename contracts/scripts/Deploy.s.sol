@@ -22,7 +22,12 @@ contract Deploy is Script {
     function run() external {
         // load ENV variables first
         uint256 key = vm.envUint("PRIVATE_KEY");
-        address verifierAddress = vm.envAddress("VERIFIER_ADDRESS");
+        // VERIFIER_ADDRESS overrides the verifier router of the network in boundless.toml.
+        address verifierAddress = vm.envOr("VERIFIER_ADDRESS", address(0));
+        if (verifierAddress == address(0)) {
+            verifierAddress = configuredVerifier();
+        }
+        console2.log("Using the verifier at", verifierAddress);
         vm.startBroadcast(key);
 
         IRiscZeroVerifier verifier = IRiscZeroVerifier(verifierAddress);
@@ -32,4 +37,23 @@ contract Deploy is Script {
 
         vm.stopBroadcast();
     }
+
+    /// Verifier router of the network of the current chain, in the project configuration.
+    function configuredVerifier() internal view returns (address) {
+        string memory config = vm.readFile(string.concat(vm.projectRoot(), "/boundless.toml"));
+        string[] memory networks = vm.parseTomlKeys(config, ".networks");
+        for (uint256 i = 0; i < networks.length; i++) {
+            string memory network = string.concat(".networks['", networks[i], "']");
+            if (vm.parseTomlUint(config, string.concat(network, "['chain-id']")) != block.chainid) {
+                continue;
+            }
+            string memory router = string.concat(network, "['verifier-router-address']");
+            require(
+                vm.keyExistsToml(config, router),
+                string.concat("no verifier-router-address for ", networks[i], " in boundless.toml")
+            );
+            return vm.parseTomlAddress(config, router);
+        }
+        revert("no network of this chain in boundless.toml, set VERIFIER_ADDRESS");
+    }
 }
@@ -0,0 +1,38 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pragma solidity ^0.8.20;
+
+import {Script, console2} from "forge-std/Script.sol";
+import {RiscZeroMockVerifier} from "risc0/test/RiscZeroMockVerifier.sol";
+import {EvenNumber} from "../src/EvenNumber.sol";
+
+/// @notice Deploys EvenNumber with a mock verifier, accepting the fake receipts of the app's
+///         `--dev-mode`, to a local devnet.
+contract DeployDev is Script {
+    /// @notice Selector of the seals of fake receipts.
+    bytes4 constant FAKE_RECEIPT_SELECTOR = bytes4(0xFFFFFFFF);
+
+    function run() external {
+        uint256 key = vm.envUint("PRIVATE_KEY");
+        vm.startBroadcast(key);
+
+        RiscZeroMockVerifier verifier = new RiscZeroMockVerifier(FAKE_RECEIPT_SELECTOR);
+        EvenNumber evenNumber = new EvenNumber(verifier);
+        console2.log("Deployed RiscZeroMockVerifier to", address(verifier));
+        console2.log("Deployed EvenNumber to", address(evenNumber));
+
+        vm.stopBroadcast();
+    }
+}
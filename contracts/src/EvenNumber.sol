@@ -16,12 +16,13 @@ pragma solidity ^0.8.20;
 
 import {IRiscZeroVerifier} from "risc0/IRiscZeroVerifier.sol";
 import {ImageID} from "./ImageID.sol"; // auto-generated contract after running `cargo build`.
+import {IEvenNumber} from "./IEvenNumber.sol";
 
 /// @title A starter application using RISC Zero.
 /// @notice This basic application holds a number, guaranteed to be even.
 /// @dev This contract demonstrates one pattern for offloading the computation of an expensive
 ///      or difficult to implement function to a RISC Zero guest.
-contract EvenNumber {
+contract EvenNumber is IEvenNumber {
     /// @notice RISC Zero verifier contract address.
     IRiscZeroVerifier public immutable verifier;
     /// @notice Image ID of the only zkVM binary to accept verification from.
@@ -35,6 +36,10 @@ contract EvenNumber {
     ///         It can be set by calling the `set` function.
     uint256 public number;
 
+    /// @notice Roots of the Merkle trees of the batches of numbers proven even, set by the
+    ///         `setRoot` function. A number of a batch is then set by the `claim` function.
+    mapping(bytes32 => bool) public roots;
+
     /// @notice Initialize the contract, binding it to a specified RISC Zero verifier.
     constructor(IRiscZeroVerifier _verifier) {
         verifier = _verifier;
@@ -47,6 +52,34 @@ contract EvenNumber {
         bytes memory journal = abi.encode(x);
         verifier.verify(seal, imageId, sha256(journal));
         number = x;
+        emit NumberSet(x);
+    }
+
+    /// @notice Set the root of the Merkle tree of a batch of numbers. Requires a RISC Zero proof
+    ///         that every number of the batch is even, from the guest in batch mode.
+    function setRoot(bytes32 root, uint256 count, bytes calldata seal) public {
+        bytes memory journal = abi.encode(root, count);
+        verifier.verify(seal, imageId, sha256(journal));
+        roots[root] = true;
+        emit RootSet(root, count);
+    }
+
+    /// @notice Set the even number stored on the contract from a batch whose root was set.
+    ///         Requires the proof of inclusion of the number in the Merkle tree of the batch.
+    function claim(uint256 x, bytes32[] calldata proof) public {
+        // The leaf is hashed twice, so that an inner node can't be claimed as a number.
+        bytes32 hash = keccak256(bytes.concat(keccak256(abi.encode(x))));
+        for (uint256 i = 0; i < proof.length; i++) {
+            bytes32 sibling = proof[i];
+            hash = hash < sibling
+                ? keccak256(abi.encodePacked(hash, sibling))
+                : keccak256(abi.encodePacked(sibling, hash));
+        }
+        if (!roots[hash]) {
+            revert UnknownRoot(hash);
+        }
+        number = x;
+        emit NumberSet(x);
     }
 
     /// @notice Returns the number stored.
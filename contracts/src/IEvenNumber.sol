@@ -15,6 +15,15 @@
 pragma solidity ^0.8.20;
 
 interface IEvenNumber {
+    event NumberSet(uint256 number);
+    event RootSet(bytes32 root, uint256 count);
+
+    error UnknownRoot(bytes32 root);
+
+    function imageId() external view returns (bytes32);
     function set(uint256 x, bytes calldata seal) external;
+    function setRoot(bytes32 root, uint256 count, bytes calldata seal) external;
+    function claim(uint256 x, bytes32[] calldata proof) external;
+    function roots(bytes32 root) external view returns (bool);
     function get() external view returns (uint256);
 }
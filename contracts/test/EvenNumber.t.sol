@@ -21,6 +21,7 @@ import {Receipt as RiscZeroReceipt} from "risc0/IRiscZeroVerifier.sol";
 import {RiscZeroMockVerifier} from "risc0/test/RiscZeroMockVerifier.sol";
 import {VerificationFailed} from "risc0/IRiscZeroVerifier.sol";
 import {EvenNumber} from "../src/EvenNumber.sol";
+import {IEvenNumber} from "../src/IEvenNumber.sol";
 import {ImageID} from "../src/ImageID.sol";
 
 contract EvenNumberTest is RiscZeroCheats, Test {
@@ -41,6 +42,15 @@ contract EvenNumberTest is RiscZeroCheats, Test {
         assertEq(evenNumber.get(), number);
     }
 
+    function test_SetEmitsNumberSet() public {
+        uint256 number = 42;
+        RiscZeroReceipt memory receipt = verifier.mockProve(ImageID.IS_EVEN_ID, sha256(abi.encode(number)));
+
+        vm.expectEmit(address(evenNumber));
+        emit IEvenNumber.NumberSet(number);
+        evenNumber.set(number, receipt.seal);
+    }
+
     function test_SetZero() public {
         uint256 number = 0;
         RiscZeroReceipt memory receipt = verifier.mockProve(ImageID.IS_EVEN_ID, sha256(abi.encode(number)));
@@ -49,6 +59,80 @@ contract EvenNumberTest is RiscZeroCheats, Test {
         assertEq(evenNumber.get(), number);
     }
 
+    // The guest commits the number as a single 32-byte big-endian word, which must be the journal
+    // expected by the contract. The guest side is checked by the property tests in guests/tests.
+    function testFuzz_AcceptsBigEndianWordJournal(uint256 number) public {
+        bytes memory journal = abi.encodePacked(bytes32(number));
+        RiscZeroReceipt memory receipt = verifier.mockProve(ImageID.IS_EVEN_ID, sha256(journal));
+
+        evenNumber.set(number, receipt.seal);
+        assertEq(evenNumber.get(), number);
+    }
+
+    function leaf(uint256 x) internal pure returns (bytes32) {
+        return keccak256(bytes.concat(keccak256(abi.encode(x))));
+    }
+
+    function node(bytes32 a, bytes32 b) internal pure returns (bytes32) {
+        return a < b ? keccak256(abi.encodePacked(a, b)) : keccak256(abi.encodePacked(b, a));
+    }
+
+    // Set the root of the batch [2, 4, 6], whose last leaf is carried up to the root, as by the
+    // guest and guests::merkle.
+    function setBatchRoot() internal returns (bytes32 root) {
+        root = node(node(leaf(2), leaf(4)), leaf(6));
+        RiscZeroReceipt memory receipt = verifier.mockProve(ImageID.IS_EVEN_ID, sha256(abi.encode(root, 3)));
+
+        vm.expectEmit(address(evenNumber));
+        emit IEvenNumber.RootSet(root, 3);
+        evenNumber.setRoot(root, 3, receipt.seal);
+        assertTrue(evenNumber.roots(root));
+    }
+
+    function test_ClaimFromBatch() public {
+        setBatchRoot();
+
+        bytes32[] memory proof = new bytes32[](2);
+        proof[0] = leaf(2);
+        proof[1] = leaf(6);
+        evenNumber.claim(4, proof);
+        assertEq(evenNumber.get(), 4);
+
+        proof = new bytes32[](1);
+        proof[0] = node(leaf(2), leaf(4));
+        vm.expectEmit(address(evenNumber));
+        emit IEvenNumber.NumberSet(6);
+        evenNumber.claim(6, proof);
+        assertEq(evenNumber.get(), 6);
+    }
+
+    function test_RejectClaimNotInBatch() public {
+        bytes32 root = setBatchRoot();
+
+        bytes32[] memory proof = new bytes32[](2);
+        proof[0] = leaf(2);
+        proof[1] = leaf(6);
+        vm.expectRevert(abi.encodeWithSelector(IEvenNumber.UnknownRoot.selector, node(node(leaf(2), leaf(5)), leaf(6))));
+        evenNumber.claim(5, proof);
+
+        // An inner node of the tree is not a number of the batch.
+        proof = new bytes32[](1);
+        proof[0] = leaf(6);
+        uint256 inner = uint256(node(leaf(2), leaf(4)));
+        vm.expectRevert();
+        evenNumber.claim(inner, proof);
+        assertTrue(evenNumber.roots(root));
+    }
+
+    // Try using the journal of a single number to set a root.
+    function test_RejectRootWithoutBatchProof() public {
+        bytes32 root = node(leaf(2), leaf(4));
+        RiscZeroReceipt memory receipt = verifier.mockProve(ImageID.IS_EVEN_ID, sha256(abi.encode(root)));
+
+        vm.expectRevert(VerificationFailed.selector);
+        evenNumber.setRoot(root, 2, receipt.seal);
+    }
+
     // Try using a proof for the evenness of 4 to set 1 on the contract.
     function test_RejectInvalidProof() public {
         RiscZeroReceipt memory receipt = verifier.mockProve(ImageID.IS_EVEN_ID, sha256(abi.encode(4)));
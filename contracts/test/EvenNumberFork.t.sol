@@ -0,0 +1,74 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+pragma solidity ^0.8.20;
+
+import {Test} from "forge-std/Test.sol";
+import {IRiscZeroVerifier} from "risc0/IRiscZeroVerifier.sol";
+import {EvenNumber} from "../src/EvenNumber.sol";
+import {ImageID} from "../src/ImageID.sol";
+
+/// @notice Tests EvenNumber against the verifier deployed on a network, forked after the
+///         fulfillment of a request, with the journal and seal recorded from it.
+/// @dev Skipped unless FORK_RPC_URL is set. The fixture is read from FORK_FIXTURE, by default
+///      contracts/test/fixtures/fork.json, as written by `app fixture`. The fs_permissions of
+///      foundry.toml only allow reading fixtures under contracts/test/fixtures, so FORK_FIXTURE
+///      must be a path in that directory. If EVEN_NUMBER_ADDRESS is set, the deployed contract is
+///      tested instead of a new one.
+contract EvenNumberForkTest is Test {
+    bool forked;
+    IRiscZeroVerifier verifier;
+    EvenNumber evenNumber;
+    uint256 number;
+    bytes seal;
+
+    function setUp() public {
+        string memory rpcUrl = vm.envOr("FORK_RPC_URL", string(""));
+        forked = bytes(rpcUrl).length > 0;
+        if (!forked) {
+            return;
+        }
+        string memory fixture = vm.readFile(vm.envOr("FORK_FIXTURE", string("contracts/test/fixtures/fork.json")));
+        // Seals of the market prove inclusion in a root that is only known to the set verifier
+        // after the fulfillment, so the fork is taken at the block recorded then.
+        vm.createSelectFork(rpcUrl, vm.parseJsonUint(fixture, ".block"));
+        assertEq(block.chainid, vm.parseJsonUint(fixture, ".chainId"), "FORK_RPC_URL is on another chain");
+        verifier = IRiscZeroVerifier(vm.parseJsonAddress(fixture, ".verifier"));
+        address deployed = vm.envOr("EVEN_NUMBER_ADDRESS", address(0));
+        evenNumber = deployed == address(0) ? new EvenNumber(verifier) : EvenNumber(deployed);
+        number = vm.parseJsonUint(fixture, ".number");
+        seal = vm.parseJsonBytes(fixture, ".seal");
+        assertEq(vm.parseJsonBytes(fixture, ".journal"), abi.encode(number), "journal is not the number");
+    }
+
+    function test_Configuration() public {
+        vm.skip(!forked);
+        assertGt(address(verifier).code.length, 0, "no verifier deployed at the recorded address");
+        assertEq(address(evenNumber.verifier()), address(verifier), "EvenNumber uses another verifier");
+        assertEq(evenNumber.imageId(), ImageID.IS_EVEN_ID, "EvenNumber expects another image ID");
+    }
+
+    function test_SetWithRecordedSeal() public {
+        vm.skip(!forked);
+        evenNumber.set(number, seal);
+        assertEq(evenNumber.get(), number);
+    }
+
+    // The seal of the proof for one number must not set another.
+    function test_RejectRecordedSealForOtherNumber() public {
+        vm.skip(!forked);
+        vm.expectRevert();
+        evenNumber.set(number + 2, seal);
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks of the work done on every submission before the request is sent: encoding the
+//! input, executing the guest for the preflight, and decoding its journal.
+
+use std::hint::black_box;
+
+use alloy_primitives::U256;
+use criterion::{criterion_group, criterion_main, Criterion};
+use guests::{encode_input, Journal, IS_EVEN_ELF};
+use risc0_zkvm::{default_executor, ExecutorEnv};
+
+fn input_encoding(c: &mut Criterion) {
+    let number = U256::from(1304);
+    c.bench_function("encode input", |b| {
+        b.iter(|| encode_input(black_box(number)))
+    });
+}
+
+fn journal_decoding(c: &mut Criterion) {
+    let journal = Journal::from(U256::from(1304)).encode();
+    c.bench_function("decode journal", |b| {
+        b.iter(|| Journal::decode(black_box(&journal)).unwrap())
+    });
+}
+
+fn preflight(c: &mut Criterion) {
+    let input = encode_input(U256::from(1304));
+    let mut group = c.benchmark_group("preflight");
+    // Each iteration executes the guest in the zkVM, which takes far longer than the encoding.
+    group.sample_size(10);
+    group.bench_function("execute guest", |b| {
+        b.iter(|| {
+            let env = ExecutorEnv::builder().write_slice(&input).build().unwrap();
+            default_executor().execute(env, IS_EVEN_ELF).unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, input_encoding, journal_decoding, preflight);
+criterion_main!(benches);
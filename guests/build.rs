@@ -37,6 +37,15 @@ fn main() {
     }
     let guest_options = builder.build().unwrap();
 
+    // Export the version of the zkVM the guest is built with, for the compatibility checks of the
+    // app.
+    println!("cargo:rerun-if-changed=is-even/Cargo.lock");
+    let lock_path = manifest_dir.join("is-even/Cargo.lock");
+    let lock = std::fs::read_to_string(&lock_path)
+        .unwrap_or_else(|e| panic!("failed to read {}: {e}", lock_path.display()));
+    let version = zkvm_version(&lock).expect("risc0-zkvm is in the lock file of the guest");
+    println!("cargo:rustc-env=IS_EVEN_ZKVM_VERSION={version}");
+
     // Generate Rust source files for the methods crate.
     let guests = embed_methods_with_options(HashMap::from([("is-even", guest_options)]));
 
@@ -49,3 +58,11 @@ fn main() {
         println!("cargo:warning=Failed to generate Solidity files: {}", e);
     };
 }
+
+/// Version of `risc0-zkvm` in a `Cargo.lock` file.
+fn zkvm_version(lock: &str) -> Option<&str> {
+    let mut lines = lock.lines();
+    lines.find(|line| *line == r#"name = "risc0-zkvm""#)?;
+    let version = lines.next()?.strip_prefix("version = ")?;
+    Some(version.trim_matches('"'))
+}
@@ -14,7 +14,7 @@
 
 use std::io::Read;
 
-use alloy_primitives::U256;
+use alloy_primitives::{keccak256, B256, U256};
 use alloy_sol_types::SolValue;
 use risc0_zkvm::guest::env;
 
@@ -22,6 +22,11 @@ fn main() {
     // Read the input data for this application.
     let mut input_bytes = Vec::<u8>::new();
     env::stdin().read_to_end(&mut input_bytes).unwrap();
+
+    // A single number is encoded as one 32-byte word, and a batch as an array of numbers.
+    if input_bytes.len() != 32 {
+        return batch(&input_bytes);
+    }
     // Decode and parse the input
     let number = <U256>::abi_decode(&input_bytes).unwrap();
 
@@ -33,3 +38,29 @@ fn main() {
     // Journal is encoded using Solidity ABI for easy decoding in the app contract.
     env::commit_slice(number.abi_encode().as_slice());
 }
+
+/// Assert that every number of the batch is even, and commit the root of their Merkle tree with
+/// their count, so that the numbers are claimed one by one on the contract. The tree is the one
+/// of `guests::merkle`.
+fn batch(input_bytes: &[u8]) {
+    let numbers = <Vec<U256>>::abi_decode(input_bytes).unwrap();
+    assert!(!numbers.is_empty(), "batch is empty");
+    assert!(numbers.iter().all(|n| !n.bit(0)), "number is not even");
+
+    let mut level: Vec<B256> = numbers
+        .iter()
+        .map(|n| keccak256(keccak256(n.abi_encode())))
+        .collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] if a <= b => keccak256([a.as_slice(), b.as_slice()].concat()),
+                [a, b] => keccak256([b.as_slice(), a.as_slice()].concat()),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    env::commit_slice((level[0], U256::from(numbers.len())).abi_encode().as_slice());
+}
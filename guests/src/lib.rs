@@ -12,4 +12,81 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+//! The is-even guest, and the encoding of its input and journal.
+//!
+//! The input and the journal are both the number, ABI-encoded as a single 32-byte big-endian
+//! word. The EvenNumber contract rebuilds the journal with `abi.encode(x)` to verify a seal, so
+//! they must be encoded with [Journal] rather than by hand.
+//!
+//! In batch mode, the input is an ABI-encoded array of numbers, and the guest commits the root
+//! of their [merkle] tree with their count, as a [BatchJournal]. A single seal then sets the root
+//! on the EvenNumber contract, and each number is claimed later with its proof of inclusion.
+
+use alloy_sol_types::SolValue;
+
+pub mod merkle;
+
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
+
+/// Version of the zkVM the is-even guest is built with, from its lock file.
+pub const IS_EVEN_ZKVM_VERSION: &str = env!("IS_EVEN_ZKVM_VERSION");
+
+alloy_sol_types::sol! {
+    /// Journal of the is-even guest, as expected by `EvenNumber.set`: `abi.encode(x)`.
+    #[derive(Debug, PartialEq, Eq)]
+    struct Journal {
+        uint256 number;
+    }
+}
+
+impl Journal {
+    /// ABI-encode the journal, as the guest commits it. The input of the guest has the same
+    /// encoding.
+    pub fn encode(&self) -> Vec<u8> {
+        self.abi_encode()
+    }
+
+    /// Decode a journal committed by the guest.
+    pub fn decode(bytes: &[u8]) -> Result<Self, alloy_sol_types::Error> {
+        Self::abi_decode(bytes)
+    }
+}
+
+/// Encode the number as the input of the guest.
+pub fn encode_input(number: alloy_primitives::U256) -> Vec<u8> {
+    Journal { number }.encode()
+}
+
+impl From<alloy_primitives::U256> for Journal {
+    fn from(number: alloy_primitives::U256) -> Self {
+        Self { number }
+    }
+}
+
+alloy_sol_types::sol! {
+    /// Journal of the is-even guest in batch mode, as expected by `EvenNumber.setRoot`:
+    /// `abi.encode(root, count)`.
+    #[derive(Debug, PartialEq, Eq)]
+    struct BatchJournal {
+        bytes32 root;
+        uint256 count;
+    }
+}
+
+impl BatchJournal {
+    /// ABI-encode the journal, as the guest commits it.
+    pub fn encode(&self) -> Vec<u8> {
+        self.abi_encode()
+    }
+
+    /// Decode a journal committed by the guest in batch mode.
+    pub fn decode(bytes: &[u8]) -> Result<Self, alloy_sol_types::Error> {
+        Self::abi_decode(bytes)
+    }
+}
+
+/// Encode the numbers as the input of the guest in batch mode, `abi.encode(uint256[])`. The
+/// encoding is never 32 bytes long, which the guest reads as a single number.
+pub fn encode_batch_input(numbers: &[alloy_primitives::U256]) -> Vec<u8> {
+    numbers.to_vec().abi_encode()
+}
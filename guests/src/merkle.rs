@@ -0,0 +1,84 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Merkle tree of the numbers of a batch, as built by the is-even guest in batch mode and
+//! checked by `EvenNumber.claim`.
+//!
+//! A leaf is the double keccak256 hash of `abi.encode(number)`, so that a leaf can't be mistaken
+//! for an inner node. An inner node is the keccak256 hash of its two children, sorted, so that a
+//! proof only lists the siblings of the path, as with OpenZeppelin's `MerkleProof`. The last node
+//! of an odd level is carried up unchanged. The guest implements the same tree, as it can't
+//! depend on this crate.
+
+use alloy_primitives::{keccak256, B256, U256};
+use alloy_sol_types::SolValue;
+
+/// Leaf of the number in the tree.
+pub fn leaf(number: U256) -> B256 {
+    keccak256(keccak256(number.abi_encode()))
+}
+
+/// Inner node of the tree with the given children.
+pub fn node(a: B256, b: B256) -> B256 {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    keccak256([low.as_slice(), high.as_slice()].concat())
+}
+
+/// Root of the tree of the numbers, or `None` if there are none.
+pub fn root(numbers: &[U256]) -> Option<B256> {
+    let mut level: Vec<B256> = numbers.iter().copied().map(leaf).collect();
+    while level.len() > 1 {
+        level = parent_level(&level);
+    }
+    level.first().copied()
+}
+
+/// Proof of inclusion of the number at `index` in the tree of the numbers: the siblings of its
+/// path from its leaf to the root, as passed to `EvenNumber.claim`.
+pub fn proof(numbers: &[U256], index: usize) -> Option<Vec<B256>> {
+    if index >= numbers.len() {
+        return None;
+    }
+    let mut level: Vec<B256> = numbers.iter().copied().map(leaf).collect();
+    let mut index = index;
+    let mut proof = Vec::new();
+    while level.len() > 1 {
+        // The last node of an odd level has no sibling, and is carried up.
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        level = parent_level(&level);
+        index /= 2;
+    }
+    Some(proof)
+}
+
+/// Whether the proof includes the number in the tree with the given root.
+pub fn verify(root: B256, number: U256, proof: &[B256]) -> bool {
+    proof
+        .iter()
+        .fold(leaf(number), |hash, &sibling| node(hash, sibling))
+        == root
+}
+
+fn parent_level(level: &[B256]) -> Vec<B256> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [a, b] => node(*a, *b),
+            [a] => *a,
+            _ => unreachable!(),
+        })
+        .collect()
+}
@@ -0,0 +1,85 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use alloy_primitives::{keccak256, B256, U256};
+use alloy_sol_types::SolValue;
+use guests::{encode_batch_input, merkle, BatchJournal};
+use proptest::prelude::*;
+
+#[test]
+fn hashes_pairs_in_order() {
+    let (a, b) = (merkle::leaf(U256::from(2)), merkle::leaf(U256::from(4)));
+    assert_eq!(merkle::node(a, b), merkle::node(b, a));
+    let (low, high) = if a < b { (a, b) } else { (b, a) };
+    assert_eq!(
+        merkle::node(a, b),
+        keccak256([low.as_slice(), high.as_slice()].concat())
+    );
+}
+
+#[test]
+fn uses_the_leaf_as_root_of_one_number() {
+    let number = U256::from(8);
+    assert_eq!(merkle::root(&[number]), Some(merkle::leaf(number)));
+    assert_eq!(merkle::proof(&[number], 0), Some(vec![]));
+    assert_eq!(merkle::root(&[]), None);
+}
+
+#[test]
+fn carries_the_last_node_of_odd_levels() {
+    let numbers: Vec<_> = [2u64, 4, 6].map(U256::from).into();
+    let leaves: Vec<_> = numbers.iter().copied().map(merkle::leaf).collect();
+    let root = merkle::node(merkle::node(leaves[0], leaves[1]), leaves[2]);
+    assert_eq!(merkle::root(&numbers), Some(root));
+    assert_eq!(
+        merkle::proof(&numbers, 2),
+        Some(vec![merkle::node(leaves[0], leaves[1])])
+    );
+}
+
+#[test]
+fn encodes_the_batch_journal_as_two_words() {
+    let journal = BatchJournal {
+        root: B256::repeat_byte(7),
+        count: U256::from(3),
+    };
+    let encoded = journal.encode();
+    assert_eq!(encoded, (journal.root, journal.count).abi_encode());
+    assert_eq!(encoded.len(), 64);
+    assert_eq!(BatchJournal::decode(&encoded).unwrap(), journal);
+}
+
+#[test]
+fn never_encodes_a_batch_as_a_single_word() {
+    for len in 0..4 {
+        let numbers = vec![U256::from(2); len];
+        let input = encode_batch_input(&numbers);
+        assert_ne!(input.len(), 32);
+        assert_eq!(<Vec<U256>>::abi_decode(&input).unwrap(), numbers);
+    }
+}
+
+proptest! {
+    #[test]
+    fn proves_inclusion_of_every_number(numbers in prop::collection::vec(any::<[u8; 32]>(), 1..40)) {
+        let numbers: Vec<_> = numbers.into_iter().map(U256::from_be_bytes).collect();
+        let root = merkle::root(&numbers).unwrap();
+        for (index, number) in numbers.iter().enumerate() {
+            let proof = merkle::proof(&numbers, index).unwrap();
+            prop_assert!(merkle::verify(root, *number, &proof));
+            prop_assert!(!merkle::verify(root, number ^ U256::from(1), &proof));
+        }
+        prop_assert_eq!(merkle::proof(&numbers, numbers.len()), None);
+    }
+}
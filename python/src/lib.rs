@@ -0,0 +1,283 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Python bindings of the publisher library, built with maturin as the `boundless_app` module.
+//!
+//! A `Client` takes the options of the `app` binary as keyword arguments, e.g.
+//! `Client(rpc_url=..., private_key=...)`, and falls back to the same environment variables. Its
+//! methods block until the call to the market is done, releasing the GIL meanwhile.
+
+use std::time::Duration;
+
+use alloy::providers::Provider;
+use anyhow::{anyhow, Context};
+use boundless_app::{
+    build_request,
+    history::Timings,
+    state_dir::{Access, StateArgs},
+    submit_request,
+    tui::parse_request_id,
+    upload_program,
+    wait::{self, RequestEvent},
+    ClientArgs,
+};
+use boundless_market::StandardClient;
+use clap::Parser;
+use futures_util::StreamExt;
+use guests::Journal;
+use pyo3::{
+    create_exception,
+    exceptions::PyException,
+    prelude::*,
+    types::{PyBytes, PyDict, PyList},
+};
+use tokio::runtime::Runtime;
+
+create_exception!(boundless_app, BoundlessError, PyException);
+create_exception!(boundless_app, RequestExpired, BoundlessError);
+
+/// Number of blocks before the current one in which the lock of a request is looked up.
+const LOCK_EVENT_LOOKBACK: u64 = 10_000;
+
+/// Options of a client, parsed from the keyword arguments as command line flags.
+#[derive(Parser, Debug)]
+#[clap(name = "boundless_app", no_binary_name = true)]
+struct Options {
+    #[clap(flatten)]
+    client: ClientArgs,
+    #[clap(flatten)]
+    state: StateArgs,
+}
+
+/// Client of the Boundless Market, submitting requests for the proof of the is-even guest.
+#[pyclass(module = "boundless_app", frozen)]
+struct Client {
+    runtime: Runtime,
+    client: StandardClient,
+    state: StateArgs,
+}
+
+/// Journal and seal of a fulfilled request.
+#[pyclass(module = "boundless_app", frozen, get_all)]
+struct Proof {
+    request_id: String,
+    journal: Py<PyBytes>,
+    seal: Py<PyBytes>,
+}
+
+#[pymethods]
+impl Client {
+    /// Connect to the market, with the options of the `app` binary given as keyword arguments:
+    /// `rpc_url="..."` for `--rpc-url`, `True` for a flag, and a list for a repeated option.
+    #[new]
+    #[pyo3(signature = (**options))]
+    fn new(py: Python<'_>, options: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        boundless_app::config::load().map_err(to_py_err)?;
+        let options = Options::try_parse_from(flags(options)?)
+            .map_err(|e| BoundlessError::new_err(e.to_string()))?;
+        let runtime = Runtime::new()?;
+        let client = py
+            .allow_threads(|| runtime.block_on(options.client.build_client()))
+            .map_err(to_py_err)?;
+        Ok(Self {
+            runtime,
+            client,
+            state: options.state,
+        })
+    }
+
+    /// Upload the guest, build the request for the proof that the number is even, and submit it.
+    /// Returns the ID of the request, which is recorded in the history.
+    #[pyo3(signature = (number, groth16 = false, preflight_cache = true))]
+    fn submit(
+        &self,
+        py: Python<'_>,
+        number: u32,
+        groth16: bool,
+        preflight_cache: bool,
+    ) -> PyResult<String> {
+        py.allow_threads(|| {
+            self.runtime.block_on(async {
+                let history = self.state.open(Access::Shared)?;
+                let mut timings = Timings::default();
+                let program_url = upload_program(&self.client, &mut timings).await?;
+                let request = build_request(
+                    &self.client,
+                    &history,
+                    &program_url,
+                    number,
+                    groth16,
+                    preflight_cache,
+                    &mut timings,
+                )
+                .await?;
+                let submitted =
+                    submit_request(&self.client, &history, &request, number, &[]).await?;
+                history.set_timings(submitted.request_id, &timings)?;
+                Ok(format!("{:#x}", submitted.request_id))
+            })
+        })
+        .map_err(to_py_err)
+    }
+
+    /// Status of the request: "Unknown" while its auction is open, then "Locked", and "Fulfilled"
+    /// or "Expired".
+    fn status(&self, py: Python<'_>, request_id: &str) -> PyResult<String> {
+        let request_id = parse_request_id(request_id).map_err(to_py_err)?;
+        py.allow_threads(|| {
+            self.runtime.block_on(async {
+                let expires_at = self
+                    .client
+                    .fetch_order(request_id, None, None)
+                    .await
+                    .ok()
+                    .map(|order| order.request.expires_at());
+                let status = self
+                    .client
+                    .boundless_market
+                    .get_status(request_id, expires_at)
+                    .await?;
+                Ok(format!("{status:?}"))
+            })
+        })
+        .map_err(to_py_err)
+    }
+
+    /// Wait for the request to be fulfilled, and return its proof. Raises `RequestExpired` if the
+    /// request expires first.
+    #[pyo3(signature = (request_id, poll_interval = 5, max_poll_interval = 60))]
+    fn wait(
+        &self,
+        py: Python<'_>,
+        request_id: &str,
+        poll_interval: u64,
+        max_poll_interval: u64,
+    ) -> PyResult<Proof> {
+        let request_id = parse_request_id(request_id).map_err(to_py_err)?;
+        let schedule = wait::PollSchedule::new(
+            Duration::from_secs(poll_interval),
+            Duration::from_secs(max_poll_interval),
+        )
+        .map_err(to_py_err)?;
+        let (journal, seal) = py
+            .allow_threads(|| {
+                self.runtime.block_on(async {
+                    let order = self
+                        .client
+                        .fetch_order(request_id, None, None)
+                        .await
+                        .context("request not found onchain or in the order stream")?;
+                    let latest = self.client.provider().get_block_number().await?;
+                    let from_block = latest.saturating_sub(LOCK_EVENT_LOOKBACK);
+                    let events = wait::events(&self.client, order.request, from_block, schedule);
+                    let mut events = std::pin::pin!(events);
+                    while let Some(event) = events.next().await {
+                        match event? {
+                            RequestEvent::Fulfilled { journal, seal, .. } => {
+                                return Ok(Ok((journal, seal)))
+                            }
+                            RequestEvent::Expired { .. } => return Ok(Err(())),
+                            _ => (),
+                        }
+                    }
+                    Err(anyhow!(
+                        "the events of the request ended before it was fulfilled"
+                    ))
+                })
+            })
+            .map_err(to_py_err)?
+            .map_err(|()| RequestExpired::new_err(format!("request {request_id:#x} expired")))?;
+        Ok(Proof {
+            request_id: format!("{request_id:#x}"),
+            journal: PyBytes::new(py, &journal).unbind(),
+            seal: PyBytes::new(py, &seal).unbind(),
+        })
+    }
+
+    /// Proof of a fulfilled request.
+    fn fetch_proof(&self, py: Python<'_>, request_id: &str) -> PyResult<Proof> {
+        let request_id = parse_request_id(request_id).map_err(to_py_err)?;
+        let (journal, seal) = py
+            .allow_threads(|| {
+                self.runtime.block_on(
+                    self.client
+                        .boundless_market
+                        .get_request_fulfillment(request_id),
+                )
+            })
+            .context("failed to get the fulfillment of the request")
+            .map_err(to_py_err)?;
+        Ok(Proof {
+            request_id: format!("{request_id:#x}"),
+            journal: PyBytes::new(py, &journal).unbind(),
+            seal: PyBytes::new(py, &seal).unbind(),
+        })
+    }
+}
+
+#[pymethods]
+impl Proof {
+    /// Number proven even, decoded from the journal, as a decimal string.
+    #[getter]
+    fn number(&self, py: Python<'_>) -> PyResult<String> {
+        let journal = Journal::decode(self.journal.as_bytes(py))
+            .map_err(|e| BoundlessError::new_err(format!("invalid journal: {e}")))?;
+        Ok(journal.number.to_string())
+    }
+}
+
+/// Command line flags of the keyword arguments.
+fn flags(options: Option<&Bound<'_, PyDict>>) -> PyResult<Vec<String>> {
+    let mut flags = Vec::new();
+    for (key, value) in options.into_iter().flatten() {
+        let flag = format!("--{}", key.extract::<String>()?.replace('_', "-"));
+        if value.is_none() {
+            continue;
+        }
+        if let Ok(enabled) = value.extract::<bool>() {
+            if enabled {
+                flags.push(flag);
+            }
+        } else if let Ok(values) = value.downcast::<PyList>() {
+            for value in values {
+                flags.extend([flag.clone(), value.str()?.to_string()]);
+            }
+        } else {
+            flags.extend([flag, value.str()?.to_string()]);
+        }
+    }
+    Ok(flags)
+}
+
+fn to_py_err(e: anyhow::Error) -> PyErr {
+    match boundless_app::revert::explain(&e) {
+        Some(reason) => BoundlessError::new_err(format!("{e:#}\nReverted: {reason}")),
+        None => BoundlessError::new_err(format!("{e:#}")),
+    }
+}
+
+/// Python bindings of the EvenNumber publisher.
+#[pymodule]
+#[pyo3(name = "boundless_app")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // Logs go to stderr, filtered with RUST_LOG like those of the binary.
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .try_init();
+    m.add_class::<Client>()?;
+    m.add_class::<Proof>()?;
+    m.add("BoundlessError", m.py().get_type::<BoundlessError>())?;
+    m.add("RequestExpired", m.py().get_type::<RequestExpired>())?;
+    Ok(())
+}
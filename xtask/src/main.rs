@@ -0,0 +1,339 @@
+// Copyright 2024 RISC Zero, Inc.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Development tasks of the workspace, run with `cargo xtask`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command as Process,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use serde_json::Value;
+
+/// Development tasks of the workspace.
+#[derive(Parser, Debug)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export the ABIs of the compiled contracts, and generate TypeScript bindings for them.
+    Bindings(BindingsArgs),
+    /// Regenerate the `alloy::sol!` bindings of the interfaces of the contracts for the app.
+    SolBindings(SolBindingsArgs),
+}
+
+/// Arguments of the bindings command.
+#[derive(Args, Debug)]
+struct BindingsArgs {
+    /// Name of a contract to export. Can be repeated.
+    #[clap(
+        long = "contract",
+        default_values = ["EvenNumber", "IEvenNumber", "IRiscZeroVerifier"]
+    )]
+    contracts: Vec<String>,
+    /// Directory of the artifacts written by `forge build`, relative to the workspace root.
+    #[clap(long, default_value = "contracts/out")]
+    artifacts: PathBuf,
+    /// Directory to write the ABIs and bindings to, relative to the workspace root.
+    #[clap(long, default_value = "bindings")]
+    out_dir: PathBuf,
+    /// Run `forge build` before exporting the ABIs.
+    #[clap(long)]
+    build: bool,
+}
+
+/// Arguments of the sol-bindings command.
+#[derive(Args, Debug)]
+struct SolBindingsArgs {
+    /// Directory of the Solidity sources, relative to the workspace root.
+    #[clap(long, default_value = "contracts/src")]
+    contracts: PathBuf,
+    /// Rust module to write the bindings to, relative to the workspace root.
+    #[clap(long, default_value = "apps/src/bindings.rs")]
+    out: PathBuf,
+    /// Fail if the module is not up to date, instead of writing it.
+    #[clap(long)]
+    check: bool,
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Bindings(args) => bindings(args),
+        Command::SolBindings(args) => sol_bindings(args),
+    }
+}
+
+/// Write `abi/<Contract>.json` and a `<Contract>.ts` module exporting the ABI as a constant for
+/// each contract, and an `index.ts` module re-exporting them.
+///
+/// The constants are declared `as const`, so that viem infers the types of the functions and
+/// events; ethers accepts them as they are.
+fn bindings(args: BindingsArgs) -> Result<()> {
+    let root = workspace_root();
+    if args.build {
+        let status = Process::new("forge")
+            .arg("build")
+            .current_dir(&root)
+            .status()
+            .context("failed to run forge, is Foundry installed?")?;
+        ensure!(status.success(), "forge build failed");
+    }
+
+    let artifacts = root.join(&args.artifacts);
+    let out_dir = root.join(&args.out_dir);
+    fs::create_dir_all(out_dir.join("abi"))?;
+    let mut index = String::from(HEADER);
+    for contract in &args.contracts {
+        let abi = read_abi(&artifacts, contract)?;
+        fs::write(
+            out_dir.join("abi").join(format!("{contract}.json")),
+            serde_json::to_string_pretty(&abi)? + "\n",
+        )?;
+        fs::write(
+            out_dir.join(format!("{contract}.ts")),
+            typescript_module(contract, &abi)?,
+        )?;
+        index += &format!(
+            "export {{ {} }} from \"./{contract}\";\n",
+            abi_const_name(contract)
+        );
+        println!("Exported {contract}");
+    }
+    fs::write(out_dir.join("index.ts"), index)?;
+    println!("Wrote the bindings to {}", out_dir.display());
+    Ok(())
+}
+
+/// Write a Rust module with an `alloy::sol!` binding module per Solidity file of the contracts
+/// declaring an interface, e.g. `even_number` for `IEvenNumber.sol`, or check that it is up to
+/// date.
+///
+/// The macro doesn't resolve imports, so files importing others, such as the contracts
+/// implementing the interfaces, are skipped.
+fn sol_bindings(args: SolBindingsArgs) -> Result<()> {
+    let root = workspace_root();
+    let module = sol_bindings_module(&root, &args.contracts, &args.out)?;
+    let out = root.join(&args.out);
+    if args.check {
+        let current = fs::read_to_string(&out).unwrap_or_default();
+        ensure!(
+            current == module,
+            "{} is stale, run `cargo xtask sol-bindings`",
+            args.out.display()
+        );
+        println!("{} is up to date", args.out.display());
+    } else {
+        fs::write(&out, module)?;
+        println!("Wrote the bindings to {}", out.display());
+    }
+    Ok(())
+}
+
+/// Rust module binding the interfaces of the Solidity files in `contracts`, to be written to
+/// `out`, both relative to the workspace root.
+fn sol_bindings_module(root: &Path, contracts: &Path, out: &Path) -> Result<String> {
+    let mut files: Vec<_> = fs::read_dir(root.join(contracts))
+        .with_context(|| format!("failed to list {}", contracts.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<_>>()?;
+    files.retain(|path| path.extension().is_some_and(|ext| ext == "sol"));
+    files.sort();
+
+    // The path of a file given to the macro is relative to the crate of the module.
+    let crate_depth = out.components().count().saturating_sub(2);
+    let to_root = "../".repeat(crate_depth);
+    let mut module = String::from(RUST_HEADER);
+    for path in files {
+        let source = fs::read_to_string(&path)?;
+        let name = path.file_name().unwrap().to_string_lossy();
+        if source
+            .lines()
+            .any(|line| line.trim_start().starts_with("import "))
+        {
+            println!("Skipped {name}, which imports other files");
+            continue;
+        }
+        let Some(interface) = source.lines().find_map(|line| {
+            let rest = line.trim_start().strip_prefix("interface ")?;
+            rest.split(|c: char| !c.is_alphanumeric() && c != '_')
+                .next()
+                .filter(|name| !name.is_empty())
+        }) else {
+            continue;
+        };
+        let file = Path::new(&to_root).join(contracts).join(&*name);
+        module += &format!(
+            "\n/// Bindings of the {interface} interface, from `{}`.\n\
+             pub mod {} {{\n    \
+                 alloy::sol!(\n        \
+                     #![sol(rpc, all_derives)]\n        \
+                     \"{}\"\n    \
+                 );\n\
+             }}\n",
+            contracts.join(&*name).display(),
+            module_name(interface),
+            file.display()
+        );
+    }
+    Ok(module)
+}
+
+/// Name of the binding module of an interface, e.g. `even_number` for `IEvenNumber`.
+fn module_name(interface: &str) -> String {
+    let mut chars = interface.chars().peekable();
+    let name: String = match (chars.next(), chars.peek()) {
+        (Some('I'), Some(c)) if c.is_ascii_uppercase() => chars.collect(),
+        (first, _) => first.into_iter().chain(chars).collect(),
+    };
+    let mut snake = String::new();
+    for (i, c) in name.char_indices() {
+        if c.is_ascii_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// First lines of the generated Rust module: the license of the crate, as at the top of its
+/// other sources.
+const RUST_HEADER: &str = "\
+// Copyright 2024 RISC Zero, Inc.\n\
+//\n\
+// Licensed under the Apache License, Version 2.0 (the \"License\");\n\
+// you may not use this file except in compliance with the License.\n\
+// You may obtain a copy of the License at\n\
+//\n\
+//     http://www.apache.org/licenses/LICENSE-2.0\n\
+//\n\
+// Unless required by applicable law or agreed to in writing, software\n\
+// distributed under the License is distributed on an \"AS IS\" BASIS,\n\
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.\n\
+// See the License for the specific language governing permissions and\n\
+// limitations under the License.\n\
+\n\
+// Generated by `cargo xtask sol-bindings`, do not edit.\n\n\
+//! Bindings of the interfaces of the contracts.\n";
+
+/// First line of the generated TypeScript modules.
+const HEADER: &str = "// Generated by `cargo xtask bindings`, do not edit.\n\n";
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("the xtask crate is in the workspace")
+        .to_path_buf()
+}
+
+/// Read the ABI of a contract from the artifact written by `forge build`.
+fn read_abi(artifacts: &Path, contract: &str) -> Result<Value> {
+    let path = artifacts
+        .join(format!("{contract}.sol"))
+        .join(format!("{contract}.json"));
+    let artifact = fs::read(&path).with_context(|| {
+        format!(
+            "failed to read {}, run `forge build` or pass --build",
+            path.display()
+        )
+    })?;
+    let artifact: Value = serde_json::from_slice(&artifact)
+        .with_context(|| format!("invalid artifact {}", path.display()))?;
+    match artifact.get("abi") {
+        Some(abi @ Value::Array(_)) => Ok(abi.clone()),
+        _ => bail!("no ABI in {}", path.display()),
+    }
+}
+
+/// TypeScript module exporting the ABI of the contract.
+fn typescript_module(contract: &str, abi: &Value) -> Result<String> {
+    Ok(format!(
+        "{HEADER}export const {} = {} as const;\n",
+        abi_const_name(contract),
+        serde_json::to_string_pretty(abi)?
+    ))
+}
+
+/// Name of the constant holding the ABI of the contract, e.g. `evenNumberAbi`.
+fn abi_const_name(contract: &str) -> String {
+    let mut chars = contract.chars();
+    let first = chars.next().map(|c| c.to_ascii_lowercase());
+    first.into_iter().chain(chars).collect::<String>() + "Abi"
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn generates_typescript_module() {
+        assert_eq!(abi_const_name("EvenNumber"), "evenNumberAbi");
+        assert_eq!(abi_const_name("IEvenNumber"), "iEvenNumberAbi");
+
+        let abi = json!([{ "type": "function", "name": "get", "inputs": [] }]);
+        let module = typescript_module("EvenNumber", &abi).unwrap();
+        assert!(module.starts_with(HEADER));
+        assert!(module.contains("export const evenNumberAbi = [\n"));
+        assert!(module.ends_with("] as const;\n"));
+    }
+
+    #[test]
+    fn names_binding_modules() {
+        assert_eq!(module_name("IEvenNumber"), "even_number");
+        assert_eq!(module_name("IRiscZeroVerifier"), "risc_zero_verifier");
+        assert_eq!(module_name("Ownable"), "ownable");
+    }
+
+    #[test]
+    fn checked_in_sol_bindings_are_up_to_date() {
+        let root = workspace_root();
+        let out = Path::new("apps/src/bindings.rs");
+        let module = sol_bindings_module(&root, Path::new("contracts/src"), out).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join(out)).unwrap(),
+            module,
+            "run `cargo xtask sol-bindings`"
+        );
+    }
+
+    #[test]
+    fn sol_bindings_have_the_license_of_the_crate() {
+        let license = RUST_HEADER.split("\n\n").next().unwrap();
+        assert!(include_str!("main.rs").starts_with(license));
+    }
+
+    #[test]
+    fn reads_abi_from_artifact() {
+        let artifacts = std::env::temp_dir().join(format!("xtask-{}", std::process::id()));
+        let dir = artifacts.join("EvenNumber.sol");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("EvenNumber.json"),
+            r#"{"abi": [{"type": "constructor", "inputs": []}], "bytecode": {}}"#,
+        )
+        .unwrap();
+
+        let abi = read_abi(&artifacts, "EvenNumber").unwrap();
+        assert_eq!(abi, json!([{ "type": "constructor", "inputs": [] }]));
+        assert!(read_abi(&artifacts, "Missing").is_err());
+        fs::remove_dir_all(artifacts).unwrap();
+    }
+}